@@ -0,0 +1,188 @@
+//! `dara dark`: dark pool order commands.
+
+use std::path::PathBuf;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use clap::{Subcommand, ValueEnum};
+use dara_client::state::{DarkOrder, ProtocolConfig};
+use dara_client::{args, instructions, pda, Market, Note, OrderNote};
+use solana_signer::Signer;
+
+use crate::notes;
+use crate::{Context, Result};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Side {
+    /// The maker sells base for quote, escrowing base.
+    Sell,
+    /// The maker buys base with quote, escrowing quote plus the maker fee.
+    Buy,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Place an order signed by the maker and store its note in `--out`.
+    Place {
+        #[arg(long)]
+        base_mint: Pubkey,
+        #[arg(long)]
+        quote_mint: Pubkey,
+        #[arg(long, value_enum)]
+        side: Side,
+        /// Base units the order trades.
+        #[arg(long)]
+        base_amount: u64,
+        /// Quote units the order trades them for.
+        #[arg(long)]
+        quote_amount: u64,
+        /// Unix timestamp after which anyone can expire the order; 0 never expires.
+        #[arg(long, default_value_t = 0)]
+        expires_at: i64,
+        /// File to create for the taker; an existing file is never overwritten.
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Fill an order whose note the maker shared.
+    Fill {
+        #[arg(long)]
+        note_file: PathBuf,
+        /// Base units to fill [default: everything still unfilled]
+        #[arg(long)]
+        fill_base: Option<u64>,
+    },
+    /// Cancel an order and return its remaining escrow; the signer must be its maker.
+    Cancel {
+        #[arg(long)]
+        note_file: PathBuf,
+    },
+}
+
+pub fn run(context: &Context, command: Command) -> Result<()> {
+    match command {
+        Command::Place {
+            base_mint,
+            quote_mint,
+            side,
+            base_amount,
+            quote_amount,
+            expires_at,
+            out,
+        } => {
+            let signer = context.signer()?;
+            let order = OrderNote::new(
+                base_mint,
+                quote_mint,
+                signer.pubkey(),
+                rand::random(),
+                side as u8,
+                base_amount,
+                quote_amount,
+            );
+            let (escrow_base, escrow_quote) = match side {
+                Side::Sell => (base_amount, 0),
+                Side::Buy => {
+                    let config: ProtocolConfig = context.fetch(&pda::protocol_config().0)?;
+                    let maker_fee = ProtocolConfig::fee(quote_amount, config.maker_fee_bps);
+                    (
+                        0,
+                        quote_amount
+                            .checked_add(maker_fee)
+                            .ok_or("quote amount overflows")?,
+                    )
+                }
+            };
+            let market = market(context, &order)?;
+            let ix = instructions::place_dark_order(
+                &signer.pubkey(),
+                &market,
+                args::PlaceDarkOrder {
+                    order_hash: order.order_hash(),
+                    escrow_base,
+                    escrow_quote,
+                    expires_at,
+                },
+            );
+            // Written first: escrow placed under a lost secret could only be cancelled
+            notes::save(&out, &Note::DarkOrder(order.clone()))?;
+            context.send(&signer, &[ix])?;
+            println!("order: {}", order.address());
+        }
+        Command::Fill {
+            note_file,
+            fill_base,
+        } => {
+            let signer = context.signer()?;
+            let order = notes::load_order(&note_file)?;
+            let state: DarkOrder = context.fetch(&order.address())?;
+            order.validate(&state)?;
+            let market = market(context, &order)?;
+            let fill_base =
+                fill_base.unwrap_or(order.base_amount.saturating_sub(state.filled_base));
+
+            // Whoever receives tokens in this fill needs an account for them
+            let taker = signer.pubkey();
+            let (taker_receives, maker_receives) = if order.side == 0 {
+                (
+                    (market.base_mint, market.base_token_program),
+                    (market.quote_mint, market.quote_token_program),
+                )
+            } else {
+                (
+                    (market.quote_mint, market.quote_token_program),
+                    (market.base_mint, market.base_token_program),
+                )
+            };
+            let fill = instructions::fill_dark_order(
+                &taker,
+                &market,
+                &order.maker,
+                args::FillDarkOrder {
+                    secret: order.secret,
+                    side: order.side,
+                    base_amount: order.base_amount,
+                    quote_amount: order.quote_amount,
+                    fill_base,
+                },
+            );
+            context.send(
+                &signer,
+                &[
+                    create_associated_token_account_idempotent(
+                        &taker,
+                        &taker,
+                        &taker_receives.0,
+                        &taker_receives.1,
+                    ),
+                    create_associated_token_account_idempotent(
+                        &taker,
+                        &order.maker,
+                        &maker_receives.0,
+                        &maker_receives.1,
+                    ),
+                    fill,
+                ],
+            )?;
+        }
+        Command::Cancel { note_file } => {
+            let signer = context.signer()?;
+            let order = notes::load_order(&note_file)?;
+            if order.maker != signer.pubkey() {
+                return Err(format!("only the maker {} can cancel", order.maker).into());
+            }
+            let market = market(context, &order)?;
+            let ix =
+                instructions::cancel_dark_order(&signer.pubkey(), &market, &order.order_hash());
+            context.send(&signer, &[ix])?;
+        }
+    }
+    Ok(())
+}
+
+fn market(context: &Context, order: &OrderNote) -> Result<Market> {
+    Ok(Market {
+        base_token_program: context.token_program(&order.base_mint)?,
+        quote_token_program: context.token_program(&order.quote_mint)?,
+        ..Market::new(order.base_mint, order.quote_mint)
+    })
+}
@@ -0,0 +1,159 @@
+//! `dara`, a command-line tool for operating anonymous presales and dark pools.
+//!
+//! Commands talk to any JSON-RPC endpoint, a local test validator by default, and sign with
+//! a Solana CLI keypair file. Secrets are generated locally and written to note files before
+//! any funds move, so an interrupted command never leaves a commitment or order unrecoverable.
+//!
+//! ```text
+//! dara presale new-note --presale <PRESALE> --claim-wallet <WALLET> --lamports <N> --out a.note
+//! dara --keypair burner.json presale commit --note-file a.note
+//! dara --url https://api.devnet.solana.com note check a.note
+//! ```
+
+mod dark_pool;
+mod notes;
+mod presale;
+mod rpc;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::AccountDeserialize;
+use clap::{Parser, Subcommand, ValueEnum};
+use solana_keypair::{read_keypair_file, Keypair};
+use solana_signer::Signer;
+use solana_transaction::Transaction;
+
+use rpc::RpcClient;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Parser)]
+#[command(name = "dara", version, about)]
+struct Cli {
+    /// JSON-RPC endpoint of the cluster.
+    #[arg(
+        long,
+        short,
+        global = true,
+        env = "DARA_RPC_URL",
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: String,
+
+    /// Keypair file that signs and pays for transactions [default: ~/.config/solana/id.json]
+    #[arg(long, short, global = true, env = "DARA_KEYPAIR")]
+    keypair: Option<PathBuf>,
+
+    /// Commitment level to read state at and wait for transactions to reach.
+    #[arg(long, global = true, value_enum, default_value_t = Commitment::Confirmed)]
+    commitment: Commitment,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create, fund, finalize and claim presales.
+    #[command(subcommand)]
+    Presale(presale::Command),
+    /// Place, fill and cancel dark pool orders.
+    #[command(subcommand)]
+    Dark(dark_pool::Command),
+    /// Inspect commitment and dark order notes.
+    #[command(subcommand)]
+    Note(notes::Command),
+}
+
+/// The cluster and signer every command runs against.
+pub struct Context {
+    pub rpc: RpcClient,
+    keypair: PathBuf,
+}
+
+impl Context {
+    /// Loaded on demand, so read-only commands work without a keypair file.
+    pub fn signer(&self) -> Result<Keypair> {
+        read_keypair_file(&self.keypair)
+            .map_err(|err| format!("keypair {}: {err}", self.keypair.display()).into())
+    }
+
+    /// Signs `instructions` with `signer` as fee payer, sends them and prints the signature.
+    pub fn send(&self, signer: &Keypair, instructions: &[Instruction]) -> Result<()> {
+        let blockhash = self.rpc.latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signer.pubkey()),
+            &[signer],
+            blockhash,
+        );
+        let signature = self.rpc.send_and_confirm(&transaction)?;
+        println!("signature: {signature}");
+        Ok(())
+    }
+
+    /// Fetches and decodes a program account.
+    pub fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T> {
+        let account = self
+            .rpc
+            .account(address)?
+            .ok_or_else(|| format!("account {address} does not exist"))?;
+        if account.owner != anon_presale::ID {
+            return Err(format!("account {address} is not owned by the presale program").into());
+        }
+        Ok(dara_client::state::decode(&account.data)
+            .map_err(|err| format!("account {address}: {err}"))?)
+    }
+
+    /// The token program owning `mint`, classic or Token-2022.
+    pub fn token_program(&self, mint: &Pubkey) -> Result<Pubkey> {
+        let account = self
+            .rpc
+            .account(mint)?
+            .ok_or_else(|| format!("mint {mint} does not exist"))?;
+        if account.owner != anchor_spl::token::ID && account.owner != anchor_spl::token_2022::ID {
+            return Err(format!("{mint} is not a token mint").into());
+        }
+        Ok(account.owner)
+    }
+}
+
+fn default_keypair() -> PathBuf {
+    let home = std::env::var_os("HOME").unwrap_or_default();
+    PathBuf::from(home).join(".config/solana/id.json")
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let commitment = match cli.commitment {
+        Commitment::Processed => "processed",
+        Commitment::Confirmed => "confirmed",
+        Commitment::Finalized => "finalized",
+    };
+    let context = Context {
+        rpc: RpcClient::new(&cli.url, commitment),
+        keypair: cli.keypair.unwrap_or_else(default_keypair),
+    };
+    let result = match cli.command {
+        Command::Presale(command) => presale::run(&context, command),
+        Command::Dark(command) => dark_pool::run(&context, command),
+        Command::Note(command) => notes::run(&context, command),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
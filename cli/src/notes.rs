@@ -0,0 +1,203 @@
+//! `dara note`, and the note files other commands read and write.
+//!
+//! A note file holds everything needed to claim a commitment or open an order later.
+//! Files are created with owner-only permissions and never overwritten: losing one loses
+//! the funds behind it.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+use dara_client::state::{BatchOrder, Commitment, CommitmentTree, DarkOrder};
+use dara_client::{CommitmentNote, Note, OrderNote};
+
+use crate::{Context, Result};
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Decode a note and check it against the account it opens.
+    Check { note_file: PathBuf },
+}
+
+pub fn run(context: &Context, command: Command) -> Result<()> {
+    match command {
+        Command::Check { note_file } => {
+            match load(&note_file)? {
+                Note::Commitment(note) => {
+                    println!("kind:          commitment");
+                    println!("presale:       {}", note.presale);
+                    println!("claim wallet:  {}", note.claim_wallet);
+                    println!("lamports:      {}", note.sol_amount);
+                    println!("commitment:    {}", note.address());
+                    // Fully claimed and refunded commitments are closed, so absence is not an error
+                    if context.rpc.account(&note.address())?.is_none() {
+                        println!("status:        not on chain (not yet committed, or settled and closed)");
+                        return Ok(());
+                    }
+                    let commitment: Commitment = context.fetch(&note.address())?;
+                    note.validate(&commitment)?;
+                    println!("claimed:       {}", commitment.claimed_amount);
+                    println!(
+                        "status:        {}",
+                        if commitment.is_claimed {
+                            "claimed"
+                        } else {
+                            "valid"
+                        }
+                    );
+                }
+                Note::DarkOrder(note) => {
+                    println!("kind:          dark order");
+                    println!("dark pool:     {}", note.dark_pool());
+                    println!("maker:         {}", note.maker);
+                    println!("side:          {}", side(note.side));
+                    println!("base amount:   {}", note.base_amount);
+                    println!("quote amount:  {}", note.quote_amount);
+                    println!("order:         {}", note.address());
+                    if context.rpc.account(&note.address())?.is_none() {
+                        println!("status:        not on chain (not yet placed, or closed)");
+                        return Ok(());
+                    }
+                    let order: DarkOrder = context.fetch(&note.address())?;
+                    note.validate(&order)?;
+                    print_order_status(&order);
+                }
+                Note::Pool(note) => {
+                    println!("kind:          pooled commitment");
+                    println!("presale:       {}", note.presale);
+                    println!("claim wallet:  {}", note.claim_wallet);
+                    println!("leaf:          {}", hex(&note.leaf()?));
+                    let nullifier = note.nullifier_address()?;
+                    println!("nullifier:     {nullifier}");
+                    if context.rpc.account(&note.address())?.is_none() {
+                        println!("status:        no commitment tree (not yet opened, or closed)");
+                        return Ok(());
+                    }
+                    let tree: CommitmentTree = context.fetch(&note.address())?;
+                    note.validate(&tree)?;
+                    // The nullifier only exists once the leaf has been claimed from or refunded
+                    let status = if context.rpc.account(&nullifier)?.is_some() {
+                        "spent"
+                    } else {
+                        "unspent"
+                    };
+                    println!("status:        {status}");
+                }
+                Note::LimitOrder(note) => {
+                    println!("kind:          limit order");
+                    println!("dark pool:     {}", note.dark_pool());
+                    println!("maker:         {}", note.maker);
+                    println!("side:          {}", side(note.side));
+                    println!("price:         {}", note.price);
+                    println!("max size:      {}", note.max_size);
+                    println!("order:         {}", note.address());
+                    if context.rpc.account(&note.address())?.is_none() {
+                        println!("status:        not on chain (not yet placed, or closed)");
+                        return Ok(());
+                    }
+                    let order: DarkOrder = context.fetch(&note.address())?;
+                    note.validate(&order)?;
+                    print_order_status(&order);
+                }
+                Note::DesignatedOrder(note) => {
+                    println!("kind:          designated-taker order");
+                    println!("dark pool:     {}", note.dark_pool());
+                    println!("maker:         {}", note.maker);
+                    println!("taker:         {}", note.taker);
+                    println!("side:          {}", side(note.side));
+                    println!("base amount:   {}", note.base_amount);
+                    println!("quote amount:  {}", note.quote_amount);
+                    println!("order:         {}", note.address());
+                    if context.rpc.account(&note.address())?.is_none() {
+                        println!("status:        not on chain (not yet placed, or closed)");
+                        return Ok(());
+                    }
+                    let order: DarkOrder = context.fetch(&note.address())?;
+                    note.validate(&order)?;
+                    print_order_status(&order);
+                }
+                Note::BatchOrder(note) => {
+                    println!("kind:          batch order");
+                    println!("batch:         {} ({})", note.batch(), note.batch_id);
+                    println!("owner:         {}", note.owner);
+                    println!("side:          {}", side(note.side));
+                    println!("price:         {}", note.price);
+                    println!("size:          {}", note.size);
+                    println!("order:         {}", note.address());
+                    if context.rpc.account(&note.address())?.is_none() {
+                        println!("status:        not on chain (not yet committed, or claimed)");
+                        return Ok(());
+                    }
+                    let order: BatchOrder = context.fetch(&note.address())?;
+                    note.validate(&order)?;
+                    println!("filled base:   {}", order.fill_base);
+                    let status = if order.is_revealed {
+                        "revealed"
+                    } else {
+                        "committed"
+                    };
+                    println!("status:        {status}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn side(side: u8) -> &'static str {
+    if side == 0 {
+        "sell"
+    } else {
+        "buy"
+    }
+}
+
+fn print_order_status(order: &DarkOrder) {
+    println!("filled base:   {}", order.filled_base);
+    let status = if order.is_cancelled {
+        "cancelled"
+    } else if order.is_filled {
+        "filled"
+    } else {
+        "open"
+    };
+    println!("status:        {status}");
+}
+
+pub fn save(path: &Path, note: &Note) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .map_err(|err| format!("{}: {err}", path.display()))?;
+    writeln!(file, "{note}")?;
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Result<Note> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    Ok(text
+        .parse()
+        .map_err(|err| format!("{}: {err}", path.display()))?)
+}
+
+pub fn load_commitment(path: &Path) -> Result<CommitmentNote> {
+    match load(path)? {
+        Note::Commitment(note) => Ok(note),
+        other => Err(format!("{} is a {} note", path.display(), other.kind()).into()),
+    }
+}
+
+pub fn load_order(path: &Path) -> Result<OrderNote> {
+    match load(path)? {
+        Note::DarkOrder(note) => Ok(note),
+        other => Err(format!("{} is a {} note", path.display(), other.kind()).into()),
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
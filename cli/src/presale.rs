@@ -0,0 +1,268 @@
+//! `dara presale`: presale lifecycle commands.
+
+use std::path::PathBuf;
+
+use anchor_lang::prelude::Pubkey;
+use clap::Subcommand;
+use dara_client::state::{Commitment, Presale};
+use dara_client::{
+    args, instructions, pda, CommitmentNote, ContributionLimits, Note, PresaleKeys, VestingSchedule,
+};
+use solana_signer::Signer;
+
+use crate::notes::{self, hex};
+use crate::{Context, Result};
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Create a presale of `--mint`, funding its vault from the signer's token account.
+    Create {
+        #[arg(long)]
+        mint: Pubkey,
+        /// Most lamports the presale accepts.
+        #[arg(long)]
+        hard_cap: u64,
+        /// Lamports needed to finalize; below it participants are refunded.
+        #[arg(long, default_value_t = 0)]
+        soft_cap: u64,
+        /// Tokens, in base units, moved into the vault and sold at `tokens_for_sale / hard_cap`.
+        #[arg(long)]
+        tokens_for_sale: u64,
+        /// Unix timestamp commits open at.
+        #[arg(long)]
+        start: i64,
+        /// Unix timestamp commits close at.
+        #[arg(long)]
+        end: i64,
+        /// Seconds after finalization after which unclaimed tokens can be swept; 0 for none.
+        #[arg(long, default_value_t = 0)]
+        claim_window: i64,
+        /// Share of each claim unlocked at finalization, in basis points.
+        #[arg(long, default_value_t = 0)]
+        tge_unlock_bps: u16,
+        /// Seconds after finalization before vesting starts.
+        #[arg(long, default_value_t = 0)]
+        cliff: i64,
+        /// Seconds over which the rest vests linearly; 0 unlocks everything after the cliff.
+        #[arg(long, default_value_t = 0)]
+        vesting_duration: i64,
+        #[arg(long, default_value_t = 0)]
+        min_commitment: u64,
+        /// 0 for no per-commitment maximum.
+        #[arg(long, default_value_t = 0)]
+        max_commitment: u64,
+        /// 0 for no cap on the number of commitments.
+        #[arg(long, default_value_t = 0)]
+        max_commitments: u32,
+        /// Every commitment must be exactly this many lamports; 0 for any amount.
+        #[arg(long, default_value_t = 0)]
+        denomination: u64,
+    },
+    /// Generate a commitment secret bound to `--claim-wallet` and store its note in `--out`.
+    NewNote {
+        #[arg(long)]
+        presale: Pubkey,
+        /// Wallet the tokens will be claimed to.
+        #[arg(long)]
+        claim_wallet: Pubkey,
+        /// Lamports the commitment will pay.
+        #[arg(long)]
+        lamports: u64,
+        /// File to create; an existing file is never overwritten.
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Commit a note's lamports from the signer, usually a burner.
+    Commit {
+        #[arg(long)]
+        note_file: PathBuf,
+    },
+    /// Finalize an ended presale; the signer must be its creator.
+    Finalize { presale: Pubkey },
+    /// Claim vested tokens to the wallet a note is bound to.
+    Claim {
+        #[arg(long)]
+        note_file: PathBuf,
+        /// Receives the commitment's rent once fully claimed [default: the signer]
+        #[arg(long)]
+        rent_recipient: Option<Pubkey>,
+    },
+    /// Print a presale's state.
+    Show { presale: Pubkey },
+}
+
+pub fn run(context: &Context, command: Command) -> Result<()> {
+    match command {
+        Command::Create {
+            mint,
+            hard_cap,
+            soft_cap,
+            tokens_for_sale,
+            start,
+            end,
+            claim_window,
+            tge_unlock_bps,
+            cliff,
+            vesting_duration,
+            min_commitment,
+            max_commitment,
+            max_commitments,
+            denomination,
+        } => {
+            let signer = context.signer()?;
+            let keys = PresaleKeys {
+                token_program: context.token_program(&mint)?,
+                ..PresaleKeys::new(mint, signer.pubkey())
+            };
+            let ix = instructions::initialize_presale(
+                &keys,
+                args::InitializePresale {
+                    hard_cap,
+                    soft_cap,
+                    tokens_for_sale,
+                    start_time: start,
+                    end_time: end,
+                    claim_window,
+                    vesting: VestingSchedule {
+                        tge_unlock_bps,
+                        cliff_duration: cliff,
+                        vesting_duration,
+                    },
+                    limits: ContributionLimits {
+                        min_commitment,
+                        max_commitment,
+                        max_commitments,
+                    },
+                    denomination,
+                },
+            );
+            context.send(&signer, &[ix])?;
+            println!("presale: {}", keys.address());
+        }
+        Command::NewNote {
+            presale,
+            claim_wallet,
+            lamports,
+            out,
+        } => {
+            let note = CommitmentNote::new(presale, claim_wallet, rand::random(), lamports);
+            notes::save(&out, &Note::Commitment(note.clone()))?;
+            println!("commitment hash: {}", hex(&note.commitment_hash()));
+            println!("commitment: {}", note.address());
+            println!("note written to {}", out.display());
+        }
+        Command::Commit { note_file } => {
+            let signer = context.signer()?;
+            let note = notes::load_commitment(&note_file)?;
+            let presale: Presale = context.fetch(&note.presale)?;
+            if presale.whitelist_root != [0u8; 32] {
+                return Err(format!(
+                    "presale {} is whitelisted; its commits need a membership proof from dara-prover",
+                    note.presale
+                )
+                .into());
+            }
+            let ix = instructions::commit_to_presale(
+                &signer.pubkey(),
+                &note.presale,
+                args::CommitToPresale {
+                    commitment_hash: note.commitment_hash(),
+                    sol_amount: note.sol_amount,
+                    whitelist_nullifier_hash: [0; 32],
+                    whitelist_proof: None,
+                },
+            );
+            context.send(&signer, &[ix])?;
+            println!("commitment: {}", note.address());
+        }
+        Command::Finalize { presale } => {
+            let signer = context.signer()?;
+            let (keys, _) = presale_keys(context, &presale)?;
+            if keys.creator != signer.pubkey() {
+                return Err(format!("only the creator {} can finalize", keys.creator).into());
+            }
+            context.send(&signer, &[instructions::finalize_presale(&keys)])?;
+        }
+        Command::Claim {
+            note_file,
+            rent_recipient,
+        } => {
+            let signer = context.signer()?;
+            let note = notes::load_commitment(&note_file)?;
+            let commitment: Commitment = context.fetch(&note.address())?;
+            note.validate(&commitment)?;
+            let (keys, _) = presale_keys(context, &note.presale)?;
+            let ix = instructions::claim_tokens(
+                &signer.pubkey(),
+                &keys,
+                &note.claim_wallet,
+                &rent_recipient.unwrap_or(signer.pubkey()),
+                args::ClaimTokens {
+                    secret: note.secret,
+                },
+            );
+            context.send(&signer, &[ix])?;
+        }
+        Command::Show { presale } => {
+            let (keys, state) = presale_keys(context, &presale)?;
+            print_presale(&presale, &keys, &state);
+        }
+    }
+    Ok(())
+}
+
+/// Reads a presale and the token program its mint belongs to.
+fn presale_keys(context: &Context, address: &Pubkey) -> Result<(PresaleKeys, Presale)> {
+    let presale: Presale = context.fetch(address)?;
+    let keys = PresaleKeys {
+        token_program: context.token_program(&presale.mint)?,
+        ..PresaleKeys::new(presale.mint, presale.creator)
+    };
+    Ok((keys, presale))
+}
+
+fn print_presale(address: &Pubkey, keys: &PresaleKeys, presale: &Presale) {
+    let status = if presale.is_cancelled {
+        "cancelled"
+    } else if presale.is_finalized {
+        "finalized"
+    } else {
+        "open"
+    };
+    println!("presale:           {address}");
+    println!("status:            {status}");
+    println!("creator:           {}", presale.creator);
+    println!("mint:              {}", presale.mint);
+    println!("token program:     {}", keys.token_program);
+    println!("token vault:       {}", pda::token_vault(address).0);
+    println!(
+        "committed:         {} / {} lamports (soft cap {})",
+        presale.total_sol_committed, presale.hard_cap, presale.soft_cap
+    );
+    println!("tokens for sale:   {}", presale.tokens_for_sale);
+    println!(
+        "window:            {} .. {}",
+        presale.start_time, presale.end_time
+    );
+    println!("claim window:      {}s", presale.claim_window);
+    println!(
+        "vesting:           {} bps at TGE, {}s cliff, {}s linear",
+        presale.vesting.tge_unlock_bps,
+        presale.vesting.cliff_duration,
+        presale.vesting.vesting_duration
+    );
+    println!(
+        "limits:            min {}, max {}, at most {} commitments",
+        presale.limits.min_commitment,
+        presale.limits.max_commitment,
+        presale.limits.max_commitments
+    );
+    println!("denomination:      {}", presale.denomination);
+    println!("whitelist root:    {}", hex(&presale.whitelist_root));
+    println!(
+        "commitments:       {} ({} settled)",
+        presale.commitment_count, presale.settled_count
+    );
+    println!("finalized at:      {}", presale.finalized_at);
+    println!("vault closed:      {}", presale.vault_closed);
+}
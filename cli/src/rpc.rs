@@ -0,0 +1,145 @@
+//! A minimal blocking JSON-RPC client covering the calls the CLI makes.
+
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::Hash;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde_json::{json, Value};
+use solana_transaction::Transaction;
+
+use crate::Result;
+
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+fn level(commitment: &str) -> u8 {
+    match commitment {
+        "finalized" => 2,
+        "confirmed" => 1,
+        _ => 0,
+    }
+}
+
+/// An account as returned by `getAccountInfo`.
+#[derive(Debug)]
+pub struct RemoteAccount {
+    pub lamports: u64,
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+}
+
+pub struct RpcClient {
+    url: String,
+    commitment: String,
+    agent: ureq::Agent,
+}
+
+impl RpcClient {
+    pub fn new(url: &str, commitment: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            commitment: commitment.to_string(),
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let mut response: Value = self
+            .agent
+            .post(&self.url)
+            .send_json(request)
+            .map_err(|err| format!("{method} to {}: {err}", self.url))?
+            .into_json()?;
+        if let Some(error) = response.get("error") {
+            let message = error["message"].as_str().unwrap_or("unknown error");
+            // Preflight failures carry the program logs, which name the actual error
+            let logs = error["data"]["logs"]
+                .as_array()
+                .map(|logs| {
+                    logs.iter()
+                        .filter_map(Value::as_str)
+                        .map(|line| format!("\n  {line}"))
+                        .collect::<String>()
+                })
+                .unwrap_or_default();
+            return Err(format!("{method}: {message}{logs}").into());
+        }
+        Ok(response["result"].take())
+    }
+
+    /// The account at `address`, or `None` if it does not exist.
+    pub fn account(&self, address: &Pubkey) -> Result<Option<RemoteAccount>> {
+        let result = self.call(
+            "getAccountInfo",
+            json!([address.to_string(), { "encoding": "base64", "commitment": self.commitment }]),
+        )?;
+        let value = &result["value"];
+        if value.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(RemoteAccount {
+            lamports: value["lamports"]
+                .as_u64()
+                .ok_or("account without lamports")?,
+            owner: value["owner"]
+                .as_str()
+                .ok_or("account without owner")?
+                .parse()?,
+            data: BASE64.decode(value["data"][0].as_str().ok_or("account without data")?)?,
+        }))
+    }
+
+    pub fn latest_blockhash(&self) -> Result<Hash> {
+        let result = self.call(
+            "getLatestBlockhash",
+            json!([{ "commitment": self.commitment }]),
+        )?;
+        Ok(result["value"]["blockhash"]
+            .as_str()
+            .ok_or("response without blockhash")?
+            .parse()?)
+    }
+
+    /// Sends a signed transaction after simulating it, then waits until it reaches the
+    /// client's commitment level.
+    pub fn send_and_confirm(&self, transaction: &Transaction) -> Result<String> {
+        let wire = BASE64.encode(bincode::serialize(transaction)?);
+        let signature = self.call(
+            "sendTransaction",
+            json!([wire, {
+                "encoding": "base64",
+                "preflightCommitment": self.commitment,
+            }]),
+        )?;
+        let signature = signature
+            .as_str()
+            .ok_or("response without signature")?
+            .to_string();
+
+        let started = Instant::now();
+        while started.elapsed() < CONFIRM_TIMEOUT {
+            let result = self.call(
+                "getSignatureStatuses",
+                json!([[signature], { "searchTransactionHistory": false }]),
+            )?;
+            let status = &result["value"][0];
+            if !status.is_null() {
+                if !status["err"].is_null() {
+                    return Err(format!("transaction {signature} failed: {}", status["err"]).into());
+                }
+                let reached = status["confirmationStatus"].as_str().unwrap_or_default();
+                if level(reached) >= level(&self.commitment) {
+                    return Ok(signature);
+                }
+            }
+            sleep(POLL_INTERVAL);
+        }
+        Err(format!("transaction {signature} was not confirmed within {CONFIRM_TIMEOUT:?}").into())
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::thread;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::AccountSerialize;
+use anon_presale::{Commitment, ContributionLimits, Presale, VestingSchedule};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use dara_client::{CommitmentNote, Note};
+use serde_json::{json, Value};
+
+fn dara(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dara"))
+        .args(args)
+        .env_remove("DARA_RPC_URL")
+        .env_remove("DARA_KEYPAIR")
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dara-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// Serves canned `getAccountInfo` responses on a local port and returns its URL.
+fn serve_accounts(accounts: HashMap<String, (Pubkey, Vec<u8>)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let request: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(request["method"], "getAccountInfo");
+
+            let value = match accounts.get(request["params"][0].as_str().unwrap()) {
+                Some((owner, data)) => json!({
+                    "lamports": 1_000_000,
+                    "owner": owner.to_string(),
+                    "data": [BASE64.encode(data), "base64"],
+                    "executable": false,
+                }),
+                None => Value::Null,
+            };
+            let response = json!({ "jsonrpc": "2.0", "id": 1, "result": { "value": value } });
+            let response = response.to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                response.len()
+            )
+            .unwrap();
+        }
+    });
+    url
+}
+
+#[test]
+fn new_note_never_overwrites_an_existing_file() {
+    let path = temp_path("commitment.note");
+    let (presale, wallet) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let new_note = |path: &PathBuf| {
+        dara(&[
+            "presale",
+            "new-note",
+            "--presale",
+            &presale.to_string(),
+            "--claim-wallet",
+            &wallet.to_string(),
+            "--lamports",
+            "1000000000",
+            "--out",
+            path.to_str().unwrap(),
+        ])
+    };
+
+    let first = new_note(&path);
+    assert!(first.status.success(), "{first:?}");
+    let written = std::fs::read_to_string(&path).unwrap();
+    let Note::Commitment(note) = written.parse().unwrap() else {
+        panic!("expected a commitment note");
+    };
+    assert_eq!((note.presale, note.claim_wallet), (presale, wallet));
+    assert_eq!(note.sol_amount, 1_000_000_000);
+    assert!(stdout(&first).contains(&note.address().to_string()));
+
+    let second = new_note(&path);
+    assert!(!second.status.success());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+}
+
+#[test]
+fn note_check_validates_against_the_commitment_account() {
+    let note = CommitmentNote::new(Pubkey::new_unique(), Pubkey::new_unique(), [5u8; 32], 7);
+    let path = temp_path("check.note");
+    std::fs::write(&path, Note::Commitment(note.clone()).encode()).unwrap();
+
+    let mut commitment = Commitment {
+        presale: note.presale,
+        commitment_hash: note.commitment_hash(),
+        sol_amount: 7,
+        claimed_amount: 3,
+        is_claimed: false,
+        bump: 255,
+    };
+    let check = |commitment: &Commitment| {
+        let mut data = Vec::new();
+        commitment.try_serialize(&mut data).unwrap();
+        let url = serve_accounts(HashMap::from([(
+            note.address().to_string(),
+            (anon_presale::ID, data),
+        )]));
+        dara(&["--url", &url, "note", "check", path.to_str().unwrap()])
+    };
+
+    let output = check(&commitment);
+    assert!(output.status.success(), "{output:?}");
+    assert!(stdout(&output).contains("valid"));
+
+    commitment.sol_amount = 8;
+    let output = check(&commitment);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("amount does not match"));
+}
+
+#[test]
+fn show_decodes_presale_state_from_rpc() {
+    let (creator, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let address = dara_client::pda::presale(&mint, &creator).0;
+    let presale = Presale {
+        creator,
+        mint,
+        total_sol_committed: 3_000_000_000,
+        hard_cap: 10_000_000_000,
+        soft_cap: 2_000_000_000,
+        tokens_for_sale: 1_000_000,
+        start_time: 100,
+        end_time: 200,
+        claim_window: 0,
+        vesting: VestingSchedule::default(),
+        limits: ContributionLimits::default(),
+        denomination: 0,
+        whitelist_root: [0u8; 32],
+        finalized_at: 250,
+        is_finalized: true,
+        is_cancelled: false,
+        vault_closed: false,
+        commitment_count: 3,
+        settled_count: 1,
+        whitelist_nullifier_count: 0,
+        open_pool_leaves: 0,
+        bump: 255,
+        vault_auth_bump: 255,
+    };
+    let mut data = Vec::new();
+    presale.try_serialize(&mut data).unwrap();
+
+    let url = serve_accounts(HashMap::from([
+        (address.to_string(), (anon_presale::ID, data)),
+        (mint.to_string(), (anchor_spl::token::ID, vec![0; 82])),
+    ]));
+    let output = dara(&["--url", &url, "presale", "show", &address.to_string()]);
+    assert!(output.status.success(), "{output:?}");
+    let text = stdout(&output);
+    assert!(text.contains("finalized"));
+    assert!(text.contains("3000000000 / 10000000000 lamports"));
+    assert!(text.contains("3 (1 settled)"));
+}
+
+#[test]
+fn show_rejects_accounts_the_program_does_not_own() {
+    let address = Pubkey::new_unique();
+    let url = serve_accounts(HashMap::from([(
+        address.to_string(),
+        (anchor_spl::token::ID, vec![0; 165]),
+    )]));
+    let output = dara(&["--url", &url, "presale", "show", &address.to_string()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not owned by the presale program"));
+}
@@ -0,0 +1,810 @@
+//! Instruction builders for every program instruction.
+//!
+//! Builders derive all PDAs themselves and take instruction arguments as the program's own
+//! `args` structs. User token accounts are assumed to be associated token accounts. Mints
+//! with a Token-2022 transfer hook need the hook's extra accounts appended to the returned
+//! instruction's `accounts`, which the program forwards to every transfer.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{system_program, InstructionData};
+use anchor_spl::associated_token::{self, get_associated_token_address_with_program_id};
+use anon_presale::{accounts, instruction as args};
+
+use crate::pda;
+
+fn build(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: anon_presale::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+/// A presale's mint and creator, which derive its address, and the mint's token program.
+#[derive(Clone, Copy, Debug)]
+pub struct PresaleKeys {
+    pub mint: Pubkey,
+    pub creator: Pubkey,
+    pub token_program: Pubkey,
+}
+
+impl PresaleKeys {
+    /// A presale of a mint owned by the classic token program.
+    pub fn new(mint: Pubkey, creator: Pubkey) -> Self {
+        Self {
+            mint,
+            creator,
+            token_program: anchor_spl::token::ID,
+        }
+    }
+
+    pub fn address(&self) -> Pubkey {
+        pda::presale(&self.mint, &self.creator).0
+    }
+
+    pub fn token_account(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, &self.mint, &self.token_program)
+    }
+}
+
+/// A dark pool's mints, which derive its address, and the token program owning each.
+#[derive(Clone, Copy, Debug)]
+pub struct Market {
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub base_token_program: Pubkey,
+    pub quote_token_program: Pubkey,
+}
+
+impl Market {
+    /// A market between two mints owned by the classic token program.
+    pub fn new(base_mint: Pubkey, quote_mint: Pubkey) -> Self {
+        Self {
+            base_mint,
+            quote_mint,
+            base_token_program: anchor_spl::token::ID,
+            quote_token_program: anchor_spl::token::ID,
+        }
+    }
+
+    pub fn dark_pool(&self) -> Pubkey {
+        pda::dark_pool(&self.base_mint, &self.quote_mint).0
+    }
+
+    pub fn base_account(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(
+            owner,
+            &self.base_mint,
+            &self.base_token_program,
+        )
+    }
+
+    pub fn quote_account(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(
+            owner,
+            &self.quote_mint,
+            &self.quote_token_program,
+        )
+    }
+}
+
+// ─── Presale ───
+
+pub fn initialize_presale(presale: &PresaleKeys, args: args::InitializePresale) -> Instruction {
+    let address = presale.address();
+    build(
+        accounts::InitializePresale {
+            creator: presale.creator,
+            mint: presale.mint,
+            presale: address,
+            token_vault: pda::token_vault(&address).0,
+            vault_authority: pda::vault_authority(&address).0,
+            creator_token_account: presale.token_account(&presale.creator),
+            token_program: presale.token_program,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// The whitelist accounts are passed when `args` carries a membership proof.
+pub fn commit_to_presale(
+    participant: &Pubkey,
+    presale: &Pubkey,
+    args: args::CommitToPresale,
+) -> Instruction {
+    let whitelisted = args.whitelist_proof.is_some();
+    build(
+        accounts::CommitToPresale {
+            participant: *participant,
+            presale: *presale,
+            protocol_config: pda::protocol_config().0,
+            commitment: pda::commitment(presale, &args.commitment_hash).0,
+            system_program: system_program::ID,
+            claim_verifying_key: whitelisted.then(|| pda::claim_verifying_key().0),
+            whitelist_nullifier: whitelisted
+                .then(|| pda::whitelist_nullifier(presale, &args.whitelist_nullifier_hash).0),
+        },
+        args,
+    )
+}
+
+pub fn set_whitelist_root(presale: &PresaleKeys, args: args::SetWhitelistRoot) -> Instruction {
+    build(
+        accounts::SetWhitelistRoot {
+            creator: presale.creator,
+            presale: presale.address(),
+        },
+        args,
+    )
+}
+
+/// Returns a spent allowlist nullifier's rent to `payer`, the burner that committed with it.
+pub fn close_whitelist_nullifier(
+    presale: &Pubkey,
+    payer: &Pubkey,
+    args: args::CloseWhitelistNullifier,
+) -> Instruction {
+    build(
+        accounts::CloseWhitelistNullifier {
+            presale: *presale,
+            whitelist_nullifier: pda::whitelist_nullifier(presale, &args.nullifier_hash).0,
+            payer: *payer,
+        },
+        args,
+    )
+}
+
+pub fn finalize_presale(presale: &PresaleKeys) -> Instruction {
+    build(
+        accounts::FinalizePresale {
+            creator: presale.creator,
+            presale: presale.address(),
+            protocol_config: pda::protocol_config().0,
+            system_program: system_program::ID,
+        },
+        args::FinalizePresale {},
+    )
+}
+
+/// Claims into `claim_wallet`'s associated token account. The commitment is found from the
+/// secret and wallet it was made with.
+pub fn claim_tokens(
+    claimer: &Pubkey,
+    presale: &PresaleKeys,
+    claim_wallet: &Pubkey,
+    rent_recipient: &Pubkey,
+    args: args::ClaimTokens,
+) -> Instruction {
+    let address = presale.address();
+    let commitment_hash = anon_presale::commitment_hash(&args.secret, claim_wallet);
+    build(
+        accounts::ClaimTokens {
+            claimer: *claimer,
+            presale: address,
+            protocol_config: pda::protocol_config().0,
+            commitment: pda::commitment(&address, &commitment_hash).0,
+            claim_wallet: *claim_wallet,
+            rent_recipient: *rent_recipient,
+            mint: presale.mint,
+            token_vault: pda::token_vault(&address).0,
+            vault_authority: pda::vault_authority(&address).0,
+            claim_token_account: presale.token_account(claim_wallet),
+            token_program: presale.token_program,
+            associated_token_program: associated_token::ID,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn refund_commitment(
+    claimer: &Pubkey,
+    presale: &Pubkey,
+    refund_wallet: &Pubkey,
+    rent_recipient: &Pubkey,
+    args: args::RefundCommitment,
+) -> Instruction {
+    let commitment_hash = anon_presale::commitment_hash(&args.secret, refund_wallet);
+    build(
+        accounts::RefundCommitment {
+            claimer: *claimer,
+            presale: *presale,
+            commitment: pda::commitment(presale, &commitment_hash).0,
+            refund_wallet: *refund_wallet,
+            rent_recipient: *rent_recipient,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Settles a commitment left unclaimed past the claim window. Anyone can send it, knowing
+/// only the commitment's address from its `CommitmentAdded` event.
+pub fn close_stale_commitment(
+    presale: &Pubkey,
+    commitment: &Pubkey,
+    rent_recipient: &Pubkey,
+) -> Instruction {
+    build(
+        accounts::CloseStaleCommitment {
+            presale: *presale,
+            commitment: *commitment,
+            rent_recipient: *rent_recipient,
+        },
+        args::CloseStaleCommitment {},
+    )
+}
+
+pub fn cancel_presale(presale: &PresaleKeys) -> Instruction {
+    let address = presale.address();
+    build(
+        accounts::CancelPresale {
+            creator: presale.creator,
+            presale: address,
+            mint: presale.mint,
+            token_vault: pda::token_vault(&address).0,
+            vault_authority: pda::vault_authority(&address).0,
+            creator_token_account: presale.token_account(&presale.creator),
+            token_program: presale.token_program,
+        },
+        args::CancelPresale {},
+    )
+}
+
+pub fn withdraw_remaining_tokens(presale: &PresaleKeys) -> Instruction {
+    let address = presale.address();
+    build(
+        accounts::WithdrawRemainingTokens {
+            creator: presale.creator,
+            presale: address,
+            mint: presale.mint,
+            token_vault: pda::token_vault(&address).0,
+            vault_authority: pda::vault_authority(&address).0,
+            creator_token_account: presale.token_account(&presale.creator),
+            token_program: presale.token_program,
+        },
+        args::WithdrawRemainingTokens {},
+    )
+}
+
+pub fn close_presale(presale: &PresaleKeys) -> Instruction {
+    let address = presale.address();
+    build(
+        accounts::ClosePresale {
+            creator: presale.creator,
+            presale: address,
+            commitment_tree: pda::commitment_tree(&address).0,
+        },
+        args::ClosePresale {},
+    )
+}
+
+// ─── Commitment Pool ───
+
+pub fn initialize_commitment_tree(presale: &PresaleKeys) -> Instruction {
+    let address = presale.address();
+    build(
+        accounts::InitializeCommitmentTree {
+            creator: presale.creator,
+            presale: address,
+            commitment_tree: pda::commitment_tree(&address).0,
+            system_program: system_program::ID,
+        },
+        args::InitializeCommitmentTree {},
+    )
+}
+
+/// The whitelist accounts are passed when `args` carries a membership proof.
+pub fn commit_to_pool(
+    participant: &Pubkey,
+    presale: &Pubkey,
+    args: args::CommitToPool,
+) -> Instruction {
+    let whitelisted = args.whitelist_proof.is_some();
+    build(
+        accounts::CommitToPool {
+            participant: *participant,
+            presale: *presale,
+            protocol_config: pda::protocol_config().0,
+            commitment_tree: pda::commitment_tree(presale).0,
+            system_program: system_program::ID,
+            claim_verifying_key: whitelisted.then(|| pda::claim_verifying_key().0),
+            whitelist_nullifier: whitelisted
+                .then(|| pda::whitelist_nullifier(presale, &args.whitelist_nullifier_hash).0),
+        },
+        args,
+    )
+}
+
+fn claim_from_pool_accounts(
+    claimer: &Pubkey,
+    presale: &PresaleKeys,
+    claim_wallet: &Pubkey,
+    nullifier_hash: &[u8; 32],
+    claim_verifying_key: Option<Pubkey>,
+) -> accounts::ClaimFromPool {
+    let address = presale.address();
+    accounts::ClaimFromPool {
+        claimer: *claimer,
+        presale: address,
+        protocol_config: pda::protocol_config().0,
+        commitment_tree: pda::commitment_tree(&address).0,
+        nullifier: pda::nullifier(&address, nullifier_hash).0,
+        claim_wallet: *claim_wallet,
+        mint: presale.mint,
+        token_vault: pda::token_vault(&address).0,
+        vault_authority: pda::vault_authority(&address).0,
+        claim_token_account: presale.token_account(claim_wallet),
+        token_program: presale.token_program,
+        associated_token_program: associated_token::ID,
+        system_program: system_program::ID,
+        claim_verifying_key,
+    }
+}
+
+/// Test path only: the claim reveals its leaf and is linkable to the commit that inserted it.
+/// Use [`claim_tokens_zk`] for private claims.
+pub fn claim_from_pool(
+    claimer: &Pubkey,
+    presale: &PresaleKeys,
+    claim_wallet: &Pubkey,
+    args: args::ClaimFromPool,
+) -> Instruction {
+    build(
+        claim_from_pool_accounts(claimer, presale, claim_wallet, &args.nullifier_hash, None),
+        args,
+    )
+}
+
+pub fn claim_tokens_zk(
+    claimer: &Pubkey,
+    presale: &PresaleKeys,
+    claim_wallet: &Pubkey,
+    args: args::ClaimTokensZk,
+) -> Instruction {
+    build(
+        claim_from_pool_accounts(
+            claimer,
+            presale,
+            claim_wallet,
+            &args.nullifier_hash,
+            Some(pda::claim_verifying_key().0),
+        ),
+        args,
+    )
+}
+
+pub fn refund_from_pool(
+    claimer: &Pubkey,
+    presale: &Pubkey,
+    refund_wallet: &Pubkey,
+    args: args::RefundFromPool,
+) -> Instruction {
+    build(
+        accounts::RefundFromPool {
+            claimer: *claimer,
+            presale: *presale,
+            commitment_tree: pda::commitment_tree(presale).0,
+            nullifier: pda::nullifier(presale, &args.nullifier_hash).0,
+            refund_wallet: *refund_wallet,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+// ─── Dark Pool ───
+
+pub fn initialize_dark_pool(authority: &Pubkey, market: &Market) -> Instruction {
+    let dark_pool = market.dark_pool();
+    build(
+        accounts::InitializeDarkPool {
+            authority: *authority,
+            base_mint: market.base_mint,
+            quote_mint: market.quote_mint,
+            dark_pool,
+            dp_base_vault: pda::dp_base_vault(&dark_pool).0,
+            dp_quote_vault: pda::dp_quote_vault(&dark_pool).0,
+            dp_fee_vault: pda::dp_fee_vault(&dark_pool).0,
+            dp_vault_authority: pda::dp_vault_authority(&dark_pool).0,
+            base_token_program: market.base_token_program,
+            quote_token_program: market.quote_token_program,
+            system_program: system_program::ID,
+        },
+        args::InitializeDarkPool {},
+    )
+}
+
+pub fn place_dark_order(
+    maker: &Pubkey,
+    market: &Market,
+    args: args::PlaceDarkOrder,
+) -> Instruction {
+    let dark_pool = market.dark_pool();
+    build(
+        accounts::PlaceDarkOrder {
+            maker: *maker,
+            dark_pool,
+            protocol_config: pda::protocol_config().0,
+            dark_order: pda::dark_order(&dark_pool, &args.order_hash).0,
+            base_mint: market.base_mint,
+            quote_mint: market.quote_mint,
+            dp_base_vault: pda::dp_base_vault(&dark_pool).0,
+            dp_quote_vault: pda::dp_quote_vault(&dark_pool).0,
+            maker_base_account: market.base_account(maker),
+            maker_quote_account: market.quote_account(maker),
+            base_token_program: market.base_token_program,
+            quote_token_program: market.quote_token_program,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+fn fill_accounts(
+    taker: &Pubkey,
+    market: &Market,
+    maker: &Pubkey,
+    order_hash: &[u8; 32],
+) -> accounts::FillDarkOrder {
+    let dark_pool = market.dark_pool();
+    accounts::FillDarkOrder {
+        taker: *taker,
+        dark_pool,
+        dark_order: pda::dark_order(&dark_pool, order_hash).0,
+        maker: *maker,
+        base_mint: market.base_mint,
+        quote_mint: market.quote_mint,
+        protocol_config: pda::protocol_config().0,
+        dp_base_vault: pda::dp_base_vault(&dark_pool).0,
+        dp_quote_vault: pda::dp_quote_vault(&dark_pool).0,
+        dp_fee_vault: pda::dp_fee_vault(&dark_pool).0,
+        dp_vault_authority: pda::dp_vault_authority(&dark_pool).0,
+        taker_base_account: market.base_account(taker),
+        taker_quote_account: market.quote_account(taker),
+        maker_base_account: market.base_account(maker),
+        maker_quote_account: market.quote_account(maker),
+        base_token_program: market.base_token_program,
+        quote_token_program: market.quote_token_program,
+    }
+}
+
+/// Fills an order whose terms the maker shared; the order is found from those terms.
+pub fn fill_dark_order(
+    taker: &Pubkey,
+    market: &Market,
+    maker: &Pubkey,
+    args: args::FillDarkOrder,
+) -> Instruction {
+    let order_hash = anon_presale::dark_order_hash(
+        &args.secret,
+        args.side,
+        args.base_amount,
+        args.quote_amount,
+        maker,
+    );
+    build(fill_accounts(taker, market, maker, &order_hash), args)
+}
+
+pub fn fill_designated_order(
+    taker: &Pubkey,
+    market: &Market,
+    maker: &Pubkey,
+    args: args::FillDesignatedOrder,
+) -> Instruction {
+    let order_hash = anon_presale::designated_order_hash(
+        &args.secret,
+        args.side,
+        args.base_amount,
+        args.quote_amount,
+        taker,
+        maker,
+    );
+    build(fill_accounts(taker, market, maker, &order_hash), args)
+}
+
+pub fn fill_limit_order(
+    taker: &Pubkey,
+    market: &Market,
+    maker: &Pubkey,
+    args: args::FillLimitOrder,
+) -> Instruction {
+    let order_hash =
+        anon_presale::limit_order_hash(&args.nonce, args.side, args.price, args.max_size, maker);
+    build(fill_accounts(taker, market, maker, &order_hash), args)
+}
+
+pub fn cancel_dark_order(maker: &Pubkey, market: &Market, order_hash: &[u8; 32]) -> Instruction {
+    let dark_pool = market.dark_pool();
+    build(
+        accounts::CancelDarkOrder {
+            maker: *maker,
+            dark_pool,
+            dark_order: pda::dark_order(&dark_pool, order_hash).0,
+            base_mint: market.base_mint,
+            quote_mint: market.quote_mint,
+            dp_base_vault: pda::dp_base_vault(&dark_pool).0,
+            dp_quote_vault: pda::dp_quote_vault(&dark_pool).0,
+            dp_vault_authority: pda::dp_vault_authority(&dark_pool).0,
+            maker_base_account: market.base_account(maker),
+            maker_quote_account: market.quote_account(maker),
+            base_token_program: market.base_token_program,
+            quote_token_program: market.quote_token_program,
+        },
+        args::CancelDarkOrder {},
+    )
+}
+
+pub fn expire_dark_order(
+    cranker: &Pubkey,
+    market: &Market,
+    maker: &Pubkey,
+    order_hash: &[u8; 32],
+) -> Instruction {
+    let dark_pool = market.dark_pool();
+    build(
+        accounts::ExpireDarkOrder {
+            cranker: *cranker,
+            dark_pool,
+            dark_order: pda::dark_order(&dark_pool, order_hash).0,
+            maker: *maker,
+            base_mint: market.base_mint,
+            quote_mint: market.quote_mint,
+            dp_base_vault: pda::dp_base_vault(&dark_pool).0,
+            dp_quote_vault: pda::dp_quote_vault(&dark_pool).0,
+            dp_vault_authority: pda::dp_vault_authority(&dark_pool).0,
+            maker_base_account: market.base_account(maker),
+            maker_quote_account: market.quote_account(maker),
+            base_token_program: market.base_token_program,
+            quote_token_program: market.quote_token_program,
+        },
+        args::ExpireDarkOrder {},
+    )
+}
+
+pub fn close_dark_pool(authority: &Pubkey, market: &Market) -> Instruction {
+    let dark_pool = market.dark_pool();
+    build(
+        accounts::CloseDarkPool {
+            authority: *authority,
+            dark_pool,
+            base_mint: market.base_mint,
+            quote_mint: market.quote_mint,
+            dp_base_vault: pda::dp_base_vault(&dark_pool).0,
+            dp_quote_vault: pda::dp_quote_vault(&dark_pool).0,
+            dp_fee_vault: pda::dp_fee_vault(&dark_pool).0,
+            dp_vault_authority: pda::dp_vault_authority(&dark_pool).0,
+            authority_base_account: market.base_account(authority),
+            authority_quote_account: market.quote_account(authority),
+            base_token_program: market.base_token_program,
+            quote_token_program: market.quote_token_program,
+        },
+        args::CloseDarkPool {},
+    )
+}
+
+// ─── Batch Auctions ───
+
+/// Opens batch `batch_id`, which must be the pool's current `batch_count`.
+pub fn open_batch(
+    authority: &Pubkey,
+    market: &Market,
+    batch_id: u64,
+    args: args::OpenBatch,
+) -> Instruction {
+    let dark_pool = market.dark_pool();
+    build(
+        accounts::OpenBatch {
+            authority: *authority,
+            dark_pool,
+            batch: pda::batch(&dark_pool, batch_id).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn commit_batch_order(
+    owner: &Pubkey,
+    market: &Market,
+    batch: &Pubkey,
+    args: args::CommitBatchOrder,
+) -> Instruction {
+    let dark_pool = market.dark_pool();
+    build(
+        accounts::CommitBatchOrder {
+            owner: *owner,
+            dark_pool,
+            protocol_config: pda::protocol_config().0,
+            batch: *batch,
+            batch_order: pda::batch_order(batch, &args.order_hash).0,
+            base_mint: market.base_mint,
+            quote_mint: market.quote_mint,
+            dp_base_vault: pda::dp_base_vault(&dark_pool).0,
+            dp_quote_vault: pda::dp_quote_vault(&dark_pool).0,
+            owner_base_account: market.base_account(owner),
+            owner_quote_account: market.quote_account(owner),
+            base_token_program: market.base_token_program,
+            quote_token_program: market.quote_token_program,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// Reveals an order; the committed order is found from the revealed terms.
+pub fn reveal_batch_order(
+    owner: &Pubkey,
+    market: &Market,
+    batch: &Pubkey,
+    args: args::RevealBatchOrder,
+) -> Instruction {
+    let order_hash =
+        anon_presale::batch_order_hash(&args.nonce, args.side, args.price, args.size, owner);
+    build(
+        accounts::RevealBatchOrder {
+            owner: *owner,
+            dark_pool: market.dark_pool(),
+            batch: *batch,
+            batch_order: pda::batch_order(batch, &order_hash).0,
+            base_mint: market.base_mint,
+            protocol_config: pda::protocol_config().0,
+        },
+        args,
+    )
+}
+
+/// Settles a batch. `revealed_orders` must list every revealed order in reveal order.
+pub fn settle_batch(
+    cranker: &Pubkey,
+    market: &Market,
+    batch: &Pubkey,
+    revealed_orders: &[Pubkey],
+) -> Instruction {
+    let mut ix = build(
+        accounts::SettleBatch {
+            cranker: *cranker,
+            dark_pool: market.dark_pool(),
+            protocol_config: pda::protocol_config().0,
+            batch: *batch,
+            base_mint: market.base_mint,
+        },
+        args::SettleBatch {},
+    );
+    ix.accounts.extend(
+        revealed_orders
+            .iter()
+            .map(|order| AccountMeta::new(*order, false)),
+    );
+    ix
+}
+
+pub fn claim_batch_order(
+    cranker: &Pubkey,
+    market: &Market,
+    batch: &Pubkey,
+    owner: &Pubkey,
+    order_hash: &[u8; 32],
+) -> Instruction {
+    let dark_pool = market.dark_pool();
+    build(
+        accounts::ClaimBatchOrder {
+            cranker: *cranker,
+            dark_pool,
+            batch: *batch,
+            batch_order: pda::batch_order(batch, order_hash).0,
+            owner: *owner,
+            base_mint: market.base_mint,
+            quote_mint: market.quote_mint,
+            dp_base_vault: pda::dp_base_vault(&dark_pool).0,
+            dp_quote_vault: pda::dp_quote_vault(&dark_pool).0,
+            dp_fee_vault: pda::dp_fee_vault(&dark_pool).0,
+            dp_vault_authority: pda::dp_vault_authority(&dark_pool).0,
+            owner_base_account: market.base_account(owner),
+            owner_quote_account: market.quote_account(owner),
+            base_token_program: market.base_token_program,
+            quote_token_program: market.quote_token_program,
+        },
+        args::ClaimBatchOrder {},
+    )
+}
+
+/// Closes a batch whose orders have all been claimed, returning its rent to the pool authority.
+pub fn close_batch(market: &Market, authority: &Pubkey, batch: &Pubkey) -> Instruction {
+    build(
+        accounts::CloseBatch {
+            dark_pool: market.dark_pool(),
+            batch: *batch,
+            authority: *authority,
+        },
+        args::CloseBatch {},
+    )
+}
+
+// ─── Protocol ───
+
+pub fn initialize_protocol_config(
+    admin: &Pubkey,
+    args: args::InitializeProtocolConfig,
+) -> Instruction {
+    build(
+        accounts::InitializeProtocolConfig {
+            admin: *admin,
+            protocol_config: pda::protocol_config().0,
+            program: anon_presale::ID,
+            program_data: pda::program_data().0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+fn admin_accounts(admin: &Pubkey) -> accounts::UpdateProtocolConfig {
+    accounts::UpdateProtocolConfig {
+        admin: *admin,
+        protocol_config: pda::protocol_config().0,
+    }
+}
+
+pub fn update_protocol_config(admin: &Pubkey, args: args::UpdateProtocolConfig) -> Instruction {
+    build(admin_accounts(admin), args)
+}
+
+pub fn set_pause_flags(admin: &Pubkey, args: args::SetPauseFlags) -> Instruction {
+    build(admin_accounts(admin), args)
+}
+
+pub fn propose_admin(admin: &Pubkey, args: args::ProposeAdmin) -> Instruction {
+    build(admin_accounts(admin), args)
+}
+
+pub fn set_claim_verifying_key(admin: &Pubkey, args: args::SetClaimVerifyingKey) -> Instruction {
+    build(
+        accounts::SetClaimVerifyingKey {
+            admin: *admin,
+            protocol_config: pda::protocol_config().0,
+            claim_verifying_key: pda::claim_verifying_key().0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+pub fn accept_admin(pending_admin: &Pubkey) -> Instruction {
+    build(
+        accounts::AcceptAdmin {
+            pending_admin: *pending_admin,
+            protocol_config: pda::protocol_config().0,
+        },
+        args::AcceptAdmin {},
+    )
+}
+
+pub fn collect_protocol_fees(fee_recipient: &Pubkey) -> Instruction {
+    build(
+        accounts::CollectProtocolFees {
+            protocol_config: pda::protocol_config().0,
+            fee_recipient: *fee_recipient,
+        },
+        args::CollectProtocolFees {},
+    )
+}
+
+/// Sweeps a pool's fees into the fee recipient's associated quote token account.
+pub fn collect_dark_pool_fees(market: &Market, fee_recipient: &Pubkey) -> Instruction {
+    let dark_pool = market.dark_pool();
+    build(
+        accounts::CollectDarkPoolFees {
+            protocol_config: pda::protocol_config().0,
+            dark_pool,
+            quote_mint: market.quote_mint,
+            dp_fee_vault: pda::dp_fee_vault(&dark_pool).0,
+            dp_vault_authority: pda::dp_vault_authority(&dark_pool).0,
+            fee_recipient_quote_account: market.quote_account(fee_recipient),
+            quote_token_program: market.quote_token_program,
+        },
+        args::CollectDarkPoolFees {},
+    )
+}
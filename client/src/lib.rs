@@ -0,0 +1,40 @@
+//! Rust client for the `anon_presale` program: instruction builders, PDA derivation,
+//! account decoding and the hashes participants commit to.
+//!
+//! ```ignore
+//! use dara_client::{args, instructions, pda, CommitmentNote, PresaleKeys};
+//!
+//! let keys = PresaleKeys::new(mint, creator);
+//! // `note.encode()` is all the participant needs to keep to claim later
+//! let note = CommitmentNote::new(keys.address(), claim_wallet, secret, sol_amount);
+//! let commitment_hash = note.commitment_hash();
+//! let ix = instructions::commit_to_presale(
+//!     &burner,
+//!     &keys.address(),
+//!     args::CommitToPresale {
+//!         commitment_hash,
+//!         sol_amount,
+//!         whitelist_nullifier_hash: [0; 32],
+//!         whitelist_proof: None,
+//!     },
+//! );
+//! ```
+
+pub mod instructions;
+pub mod note;
+pub mod pda;
+pub mod state;
+pub mod whitelist;
+
+pub use anon_presale::groth16::Groth16Proof;
+pub use anon_presale::instruction as args;
+pub use anon_presale::{
+    batch_order_hash, commitment_hash, dark_order_hash, designated_order_hash, limit_order_hash,
+    ContributionLimits, PauseFlags, VestingSchedule, ID as PROGRAM_ID,
+};
+pub use instructions::{Market, PresaleKeys};
+pub use note::{
+    BatchOrderNote, CommitmentNote, DesignatedOrderNote, LimitOrderNote, Note, NoteError,
+    OrderNote, PoolNote,
+};
+pub use whitelist::Whitelist;
@@ -0,0 +1,763 @@
+//! Notes: one string holding everything needed to claim a commitment or fill a dark order.
+//!
+//! A note is `dara-note-` followed by the lowercase hex of
+//! `version || kind || program_id || body || checksum`, where the checksum is the first four
+//! bytes of SHA256 over everything before it, so a mistyped or truncated note is rejected
+//! rather than silently deriving the wrong commitment. Bodies are
+//!
+//! - commitment: `presale || claim_wallet || secret || sol_amount_le`
+//! - dark order: `base_mint || quote_mint || maker || secret || side || base_amount_le ||
+//!   quote_amount_le`
+//! - pooled commitment: `presale || claim_wallet || nullifier || secret`
+//! - limit order: `base_mint || quote_mint || maker || nonce || side || price_le ||
+//!   max_size_le`
+//! - designated-taker order: `base_mint || quote_mint || maker || taker || secret || side ||
+//!   base_amount_le || quote_amount_le`
+//! - batch order: `base_mint || quote_mint || batch_id_le || owner || nonce || side ||
+//!   price_le || size_le`
+//!
+//! Order notes store the pool's mints rather than its address, since the address derives
+//! from them and filling needs both. Pooled commitments carry no amount: every leaf in a
+//! pool pays the presale's denomination.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hash;
+use anon_presale::{merkle, BatchOrder, Commitment, CommitmentTree, DarkOrder};
+
+use crate::pda;
+
+pub const NOTE_PREFIX: &str = "dara-note-";
+pub const NOTE_VERSION: u8 = 1;
+
+const KIND_COMMITMENT: u8 = 0;
+const KIND_DARK_ORDER: u8 = 1;
+const KIND_POOL: u8 = 2;
+const KIND_LIMIT_ORDER: u8 = 3;
+const KIND_DESIGNATED_ORDER: u8 = 4;
+const KIND_BATCH_ORDER: u8 = 5;
+const CHECKSUM_LEN: usize = 4;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NoteError {
+    MissingPrefix,
+    InvalidHex,
+    InvalidLength,
+    InvalidChecksum,
+    UnsupportedVersion(u8),
+    UnknownKind(u8),
+    /// The note is for a different deployment of the program.
+    WrongProgram(Pubkey),
+    /// A pooled note's nullifier or secret is not a scalar field element, so it has no leaf.
+    InvalidScalar,
+    /// A field of the note disagrees with the on-chain account.
+    Mismatch(&'static str),
+}
+
+impl fmt::Display for NoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoteError::MissingPrefix => write!(f, "note does not start with `{NOTE_PREFIX}`"),
+            NoteError::InvalidHex => write!(f, "note is not valid hex"),
+            NoteError::InvalidLength => write!(f, "note has the wrong length for its kind"),
+            NoteError::InvalidChecksum => write!(
+                f,
+                "note checksum does not match; it was mistyped or truncated"
+            ),
+            NoteError::UnsupportedVersion(version) => {
+                write!(f, "unsupported note version {version}")
+            }
+            NoteError::UnknownKind(kind) => write!(f, "unknown note kind {kind}"),
+            NoteError::WrongProgram(program_id) => write!(f, "note is for program {program_id}"),
+            NoteError::InvalidScalar => {
+                write!(f, "note nullifier or secret is not a valid field element")
+            }
+            NoteError::Mismatch(field) => {
+                write!(f, "note {field} does not match the on-chain account")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NoteError {}
+
+/// A presale commitment: the secret, the wallet it binds the claim to, and what was paid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitmentNote {
+    pub program_id: Pubkey,
+    pub presale: Pubkey,
+    pub claim_wallet: Pubkey,
+    pub secret: [u8; 32],
+    pub sol_amount: u64,
+}
+
+impl CommitmentNote {
+    pub fn new(presale: Pubkey, claim_wallet: Pubkey, secret: [u8; 32], sol_amount: u64) -> Self {
+        Self {
+            program_id: anon_presale::ID,
+            presale,
+            claim_wallet,
+            secret,
+            sol_amount,
+        }
+    }
+
+    pub fn commitment_hash(&self) -> [u8; 32] {
+        anon_presale::commitment_hash(&self.secret, &self.claim_wallet)
+    }
+
+    /// The `Commitment` account this note opens.
+    pub fn address(&self) -> Pubkey {
+        pda::commitment(&self.presale, &self.commitment_hash()).0
+    }
+
+    /// Checks the note against the commitment account at `address()`.
+    pub fn validate(&self, commitment: &Commitment) -> Result<(), NoteError> {
+        check_program(&self.program_id)?;
+        if commitment.presale != self.presale {
+            return Err(NoteError::Mismatch("presale"));
+        }
+        if commitment.commitment_hash != self.commitment_hash() {
+            return Err(NoteError::Mismatch("secret or claim wallet"));
+        }
+        if commitment.sol_amount != self.sol_amount {
+            return Err(NoteError::Mismatch("amount"));
+        }
+        Ok(())
+    }
+}
+
+/// A dark order's hidden terms, which a taker needs to fill it and its maker to cancel it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderNote {
+    pub program_id: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub maker: Pubkey,
+    pub secret: [u8; 32],
+    pub side: u8,
+    pub base_amount: u64,
+    pub quote_amount: u64,
+}
+
+impl OrderNote {
+    pub fn new(
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        maker: Pubkey,
+        secret: [u8; 32],
+        side: u8,
+        base_amount: u64,
+        quote_amount: u64,
+    ) -> Self {
+        Self {
+            program_id: anon_presale::ID,
+            base_mint,
+            quote_mint,
+            maker,
+            secret,
+            side,
+            base_amount,
+            quote_amount,
+        }
+    }
+
+    pub fn order_hash(&self) -> [u8; 32] {
+        anon_presale::dark_order_hash(
+            &self.secret,
+            self.side,
+            self.base_amount,
+            self.quote_amount,
+            &self.maker,
+        )
+    }
+
+    pub fn dark_pool(&self) -> Pubkey {
+        pda::dark_pool(&self.base_mint, &self.quote_mint).0
+    }
+
+    /// The `DarkOrder` account this note opens.
+    pub fn address(&self) -> Pubkey {
+        pda::dark_order(&self.dark_pool(), &self.order_hash()).0
+    }
+
+    /// Checks the note against the order account at `address()`.
+    pub fn validate(&self, order: &DarkOrder) -> Result<(), NoteError> {
+        validate_order(
+            order,
+            &self.program_id,
+            self.dark_pool(),
+            &self.maker,
+            self.order_hash(),
+        )
+    }
+}
+
+/// A pooled commitment's leaf preimage, which `claim_tokens_zk` proves knowledge of and
+/// `refund_from_pool` opens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PoolNote {
+    pub program_id: Pubkey,
+    pub presale: Pubkey,
+    pub claim_wallet: Pubkey,
+    pub nullifier: [u8; 32],
+    pub secret: [u8; 32],
+}
+
+impl PoolNote {
+    pub fn new(
+        presale: Pubkey,
+        claim_wallet: Pubkey,
+        nullifier: [u8; 32],
+        secret: [u8; 32],
+    ) -> Self {
+        Self {
+            program_id: anon_presale::ID,
+            presale,
+            claim_wallet,
+            nullifier,
+            secret,
+        }
+    }
+
+    /// The leaf `commit_to_pool` inserts.
+    pub fn leaf(&self) -> Result<[u8; 32], NoteError> {
+        merkle::leaf_hash(&self.nullifier, &self.secret, &self.claim_wallet)
+            .map_err(|_| NoteError::InvalidScalar)
+    }
+
+    pub fn nullifier_hash(&self) -> Result<[u8; 32], NoteError> {
+        merkle::nullifier_hash(&self.nullifier).map_err(|_| NoteError::InvalidScalar)
+    }
+
+    /// The `CommitmentTree` this note's leaf goes into.
+    pub fn address(&self) -> Pubkey {
+        pda::commitment_tree(&self.presale).0
+    }
+
+    /// The `Nullifier` account created once the leaf is claimed or refunded.
+    pub fn nullifier_address(&self) -> Result<Pubkey, NoteError> {
+        Ok(pda::nullifier(&self.presale, &self.nullifier_hash()?).0)
+    }
+
+    /// Checks the note against the tree at `address()`. The tree keeps only its root, so
+    /// whether the leaf is in it is left to the claim.
+    pub fn validate(&self, tree: &CommitmentTree) -> Result<(), NoteError> {
+        check_program(&self.program_id)?;
+        if tree.presale != self.presale {
+            return Err(NoteError::Mismatch("presale"));
+        }
+        self.leaf()?;
+        Ok(())
+    }
+}
+
+/// A limit order's hidden terms, which a taker needs to fill it at its price.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LimitOrderNote {
+    pub program_id: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub maker: Pubkey,
+    pub nonce: [u8; 32],
+    pub side: u8,
+    /// Quote units per whole base token.
+    pub price: u64,
+    pub max_size: u64,
+}
+
+impl LimitOrderNote {
+    pub fn new(
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        maker: Pubkey,
+        nonce: [u8; 32],
+        side: u8,
+        price: u64,
+        max_size: u64,
+    ) -> Self {
+        Self {
+            program_id: anon_presale::ID,
+            base_mint,
+            quote_mint,
+            maker,
+            nonce,
+            side,
+            price,
+            max_size,
+        }
+    }
+
+    pub fn order_hash(&self) -> [u8; 32] {
+        anon_presale::limit_order_hash(
+            &self.nonce,
+            self.side,
+            self.price,
+            self.max_size,
+            &self.maker,
+        )
+    }
+
+    pub fn dark_pool(&self) -> Pubkey {
+        pda::dark_pool(&self.base_mint, &self.quote_mint).0
+    }
+
+    /// The `DarkOrder` account this note opens.
+    pub fn address(&self) -> Pubkey {
+        pda::dark_order(&self.dark_pool(), &self.order_hash()).0
+    }
+
+    /// Checks the note against the order account at `address()`.
+    pub fn validate(&self, order: &DarkOrder) -> Result<(), NoteError> {
+        validate_order(
+            order,
+            &self.program_id,
+            self.dark_pool(),
+            &self.maker,
+            self.order_hash(),
+        )
+    }
+}
+
+/// A fixed-price dark order that only `taker` can fill.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DesignatedOrderNote {
+    pub program_id: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub secret: [u8; 32],
+    pub side: u8,
+    pub base_amount: u64,
+    pub quote_amount: u64,
+}
+
+impl DesignatedOrderNote {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        maker: Pubkey,
+        taker: Pubkey,
+        secret: [u8; 32],
+        side: u8,
+        base_amount: u64,
+        quote_amount: u64,
+    ) -> Self {
+        Self {
+            program_id: anon_presale::ID,
+            base_mint,
+            quote_mint,
+            maker,
+            taker,
+            secret,
+            side,
+            base_amount,
+            quote_amount,
+        }
+    }
+
+    pub fn order_hash(&self) -> [u8; 32] {
+        anon_presale::designated_order_hash(
+            &self.secret,
+            self.side,
+            self.base_amount,
+            self.quote_amount,
+            &self.taker,
+            &self.maker,
+        )
+    }
+
+    pub fn dark_pool(&self) -> Pubkey {
+        pda::dark_pool(&self.base_mint, &self.quote_mint).0
+    }
+
+    /// The `DarkOrder` account this note opens.
+    pub fn address(&self) -> Pubkey {
+        pda::dark_order(&self.dark_pool(), &self.order_hash()).0
+    }
+
+    /// Checks the note against the order account at `address()`.
+    pub fn validate(&self, order: &DarkOrder) -> Result<(), NoteError> {
+        validate_order(
+            order,
+            &self.program_id,
+            self.dark_pool(),
+            &self.maker,
+            self.order_hash(),
+        )
+    }
+}
+
+/// A batch auction order's hidden terms, which its owner needs to reveal it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchOrderNote {
+    pub program_id: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub batch_id: u64,
+    pub owner: Pubkey,
+    pub nonce: [u8; 32],
+    pub side: u8,
+    /// Limit price in quote units per whole base token.
+    pub price: u64,
+    pub size: u64,
+}
+
+impl BatchOrderNote {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        base_mint: Pubkey,
+        quote_mint: Pubkey,
+        batch_id: u64,
+        owner: Pubkey,
+        nonce: [u8; 32],
+        side: u8,
+        price: u64,
+        size: u64,
+    ) -> Self {
+        Self {
+            program_id: anon_presale::ID,
+            base_mint,
+            quote_mint,
+            batch_id,
+            owner,
+            nonce,
+            side,
+            price,
+            size,
+        }
+    }
+
+    pub fn order_hash(&self) -> [u8; 32] {
+        anon_presale::batch_order_hash(&self.nonce, self.side, self.price, self.size, &self.owner)
+    }
+
+    pub fn dark_pool(&self) -> Pubkey {
+        pda::dark_pool(&self.base_mint, &self.quote_mint).0
+    }
+
+    pub fn batch(&self) -> Pubkey {
+        pda::batch(&self.dark_pool(), self.batch_id).0
+    }
+
+    /// The `BatchOrder` account this note opens.
+    pub fn address(&self) -> Pubkey {
+        pda::batch_order(&self.batch(), &self.order_hash()).0
+    }
+
+    /// Checks the note against the order account at `address()`.
+    pub fn validate(&self, order: &BatchOrder) -> Result<(), NoteError> {
+        check_program(&self.program_id)?;
+        if order.batch != self.batch() {
+            return Err(NoteError::Mismatch("mints or batch"));
+        }
+        if order.owner != self.owner {
+            return Err(NoteError::Mismatch("owner"));
+        }
+        if order.order_hash != self.order_hash() {
+            return Err(NoteError::Mismatch("terms"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Note {
+    Commitment(CommitmentNote),
+    DarkOrder(OrderNote),
+    Pool(PoolNote),
+    LimitOrder(LimitOrderNote),
+    DesignatedOrder(DesignatedOrderNote),
+    BatchOrder(BatchOrderNote),
+}
+
+impl Note {
+    /// What the note opens, for messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Note::Commitment(_) => "commitment",
+            Note::DarkOrder(_) => "dark order",
+            Note::Pool(_) => "pooled commitment",
+            Note::LimitOrder(_) => "limit order",
+            Note::DesignatedOrder(_) => "designated-taker order",
+            Note::BatchOrder(_) => "batch order",
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let mut bytes = vec![NOTE_VERSION];
+        match self {
+            Note::Commitment(note) => {
+                bytes.push(KIND_COMMITMENT);
+                bytes.extend_from_slice(note.program_id.as_ref());
+                bytes.extend_from_slice(note.presale.as_ref());
+                bytes.extend_from_slice(note.claim_wallet.as_ref());
+                bytes.extend_from_slice(&note.secret);
+                bytes.extend_from_slice(&note.sol_amount.to_le_bytes());
+            }
+            Note::DarkOrder(note) => {
+                bytes.push(KIND_DARK_ORDER);
+                bytes.extend_from_slice(note.program_id.as_ref());
+                bytes.extend_from_slice(note.base_mint.as_ref());
+                bytes.extend_from_slice(note.quote_mint.as_ref());
+                bytes.extend_from_slice(note.maker.as_ref());
+                bytes.extend_from_slice(&note.secret);
+                bytes.push(note.side);
+                bytes.extend_from_slice(&note.base_amount.to_le_bytes());
+                bytes.extend_from_slice(&note.quote_amount.to_le_bytes());
+            }
+            Note::Pool(note) => {
+                bytes.push(KIND_POOL);
+                bytes.extend_from_slice(note.program_id.as_ref());
+                bytes.extend_from_slice(note.presale.as_ref());
+                bytes.extend_from_slice(note.claim_wallet.as_ref());
+                bytes.extend_from_slice(&note.nullifier);
+                bytes.extend_from_slice(&note.secret);
+            }
+            Note::LimitOrder(note) => {
+                bytes.push(KIND_LIMIT_ORDER);
+                bytes.extend_from_slice(note.program_id.as_ref());
+                bytes.extend_from_slice(note.base_mint.as_ref());
+                bytes.extend_from_slice(note.quote_mint.as_ref());
+                bytes.extend_from_slice(note.maker.as_ref());
+                bytes.extend_from_slice(&note.nonce);
+                bytes.push(note.side);
+                bytes.extend_from_slice(&note.price.to_le_bytes());
+                bytes.extend_from_slice(&note.max_size.to_le_bytes());
+            }
+            Note::DesignatedOrder(note) => {
+                bytes.push(KIND_DESIGNATED_ORDER);
+                bytes.extend_from_slice(note.program_id.as_ref());
+                bytes.extend_from_slice(note.base_mint.as_ref());
+                bytes.extend_from_slice(note.quote_mint.as_ref());
+                bytes.extend_from_slice(note.maker.as_ref());
+                bytes.extend_from_slice(note.taker.as_ref());
+                bytes.extend_from_slice(&note.secret);
+                bytes.push(note.side);
+                bytes.extend_from_slice(&note.base_amount.to_le_bytes());
+                bytes.extend_from_slice(&note.quote_amount.to_le_bytes());
+            }
+            Note::BatchOrder(note) => {
+                bytes.push(KIND_BATCH_ORDER);
+                bytes.extend_from_slice(note.program_id.as_ref());
+                bytes.extend_from_slice(note.base_mint.as_ref());
+                bytes.extend_from_slice(note.quote_mint.as_ref());
+                bytes.extend_from_slice(&note.batch_id.to_le_bytes());
+                bytes.extend_from_slice(note.owner.as_ref());
+                bytes.extend_from_slice(&note.nonce);
+                bytes.push(note.side);
+                bytes.extend_from_slice(&note.price.to_le_bytes());
+                bytes.extend_from_slice(&note.size.to_le_bytes());
+            }
+        }
+        let checksum = checksum(&bytes);
+        bytes.extend_from_slice(&checksum);
+
+        let mut text = String::from(NOTE_PREFIX);
+        text.extend(bytes.iter().map(|byte| format!("{byte:02x}")));
+        text
+    }
+
+    pub fn decode(text: &str) -> Result<Self, NoteError> {
+        let hex = text
+            .trim()
+            .strip_prefix(NOTE_PREFIX)
+            .ok_or(NoteError::MissingPrefix)?;
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            return Err(NoteError::InvalidHex);
+        }
+        let bytes = hex
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or(NoteError::InvalidHex)
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        if bytes.len() < 2 + CHECKSUM_LEN {
+            return Err(NoteError::InvalidLength);
+        }
+        let (payload, sum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        if checksum(payload) != sum {
+            return Err(NoteError::InvalidChecksum);
+        }
+        if payload[0] != NOTE_VERSION {
+            return Err(NoteError::UnsupportedVersion(payload[0]));
+        }
+
+        let mut reader = Reader(&payload[2..]);
+        let note = match payload[1] {
+            KIND_COMMITMENT => Note::Commitment(CommitmentNote {
+                program_id: reader.pubkey()?,
+                presale: reader.pubkey()?,
+                claim_wallet: reader.pubkey()?,
+                secret: reader.array()?,
+                sol_amount: reader.u64()?,
+            }),
+            KIND_DARK_ORDER => Note::DarkOrder(OrderNote {
+                program_id: reader.pubkey()?,
+                base_mint: reader.pubkey()?,
+                quote_mint: reader.pubkey()?,
+                maker: reader.pubkey()?,
+                secret: reader.array()?,
+                side: reader.array::<1>()?[0],
+                base_amount: reader.u64()?,
+                quote_amount: reader.u64()?,
+            }),
+            KIND_POOL => Note::Pool(PoolNote {
+                program_id: reader.pubkey()?,
+                presale: reader.pubkey()?,
+                claim_wallet: reader.pubkey()?,
+                nullifier: reader.array()?,
+                secret: reader.array()?,
+            }),
+            KIND_LIMIT_ORDER => Note::LimitOrder(LimitOrderNote {
+                program_id: reader.pubkey()?,
+                base_mint: reader.pubkey()?,
+                quote_mint: reader.pubkey()?,
+                maker: reader.pubkey()?,
+                nonce: reader.array()?,
+                side: reader.array::<1>()?[0],
+                price: reader.u64()?,
+                max_size: reader.u64()?,
+            }),
+            KIND_DESIGNATED_ORDER => Note::DesignatedOrder(DesignatedOrderNote {
+                program_id: reader.pubkey()?,
+                base_mint: reader.pubkey()?,
+                quote_mint: reader.pubkey()?,
+                maker: reader.pubkey()?,
+                taker: reader.pubkey()?,
+                secret: reader.array()?,
+                side: reader.array::<1>()?[0],
+                base_amount: reader.u64()?,
+                quote_amount: reader.u64()?,
+            }),
+            KIND_BATCH_ORDER => Note::BatchOrder(BatchOrderNote {
+                program_id: reader.pubkey()?,
+                base_mint: reader.pubkey()?,
+                quote_mint: reader.pubkey()?,
+                batch_id: reader.u64()?,
+                owner: reader.pubkey()?,
+                nonce: reader.array()?,
+                side: reader.array::<1>()?[0],
+                price: reader.u64()?,
+                size: reader.u64()?,
+            }),
+            kind => return Err(NoteError::UnknownKind(kind)),
+        };
+        if !reader.0.is_empty() {
+            return Err(NoteError::InvalidLength);
+        }
+        Ok(note)
+    }
+}
+
+impl fmt::Display for Note {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.encode())
+    }
+}
+
+impl FromStr for Note {
+    type Err = NoteError;
+
+    fn from_str(text: &str) -> Result<Self, NoteError> {
+        Note::decode(text)
+    }
+}
+
+impl From<CommitmentNote> for Note {
+    fn from(note: CommitmentNote) -> Self {
+        Note::Commitment(note)
+    }
+}
+
+impl From<OrderNote> for Note {
+    fn from(note: OrderNote) -> Self {
+        Note::DarkOrder(note)
+    }
+}
+
+impl From<PoolNote> for Note {
+    fn from(note: PoolNote) -> Self {
+        Note::Pool(note)
+    }
+}
+
+impl From<LimitOrderNote> for Note {
+    fn from(note: LimitOrderNote) -> Self {
+        Note::LimitOrder(note)
+    }
+}
+
+impl From<DesignatedOrderNote> for Note {
+    fn from(note: DesignatedOrderNote) -> Self {
+        Note::DesignatedOrder(note)
+    }
+}
+
+impl From<BatchOrderNote> for Note {
+    fn from(note: BatchOrderNote) -> Self {
+        Note::BatchOrder(note)
+    }
+}
+
+fn checksum(payload: &[u8]) -> [u8; CHECKSUM_LEN] {
+    hash(payload).to_bytes()[..CHECKSUM_LEN].try_into().unwrap()
+}
+
+fn check_program(program_id: &Pubkey) -> Result<(), NoteError> {
+    if *program_id != anon_presale::ID {
+        return Err(NoteError::WrongProgram(*program_id));
+    }
+    Ok(())
+}
+
+/// Checks any kind of dark order note against its `DarkOrder` account.
+fn validate_order(
+    order: &DarkOrder,
+    program_id: &Pubkey,
+    dark_pool: Pubkey,
+    maker: &Pubkey,
+    order_hash: [u8; 32],
+) -> Result<(), NoteError> {
+    check_program(program_id)?;
+    if order.pool != dark_pool {
+        return Err(NoteError::Mismatch("mints"));
+    }
+    if order.maker != *maker {
+        return Err(NoteError::Mismatch("maker"));
+    }
+    if order.order_hash != order_hash {
+        return Err(NoteError::Mismatch("terms"));
+    }
+    Ok(())
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], NoteError> {
+        if self.0.len() < N {
+            return Err(NoteError::InvalidLength);
+        }
+        let (head, rest) = self.0.split_at(N);
+        self.0 = rest;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey, NoteError> {
+        Ok(Pubkey::new_from_array(self.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, NoteError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+}
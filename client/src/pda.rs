@@ -0,0 +1,90 @@
+//! Program-derived addresses for every seed the program uses.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::pubkey;
+
+const BPF_LOADER_UPGRADEABLE_ID: Pubkey = pubkey!("BPFLoaderUpgradeab1e11111111111111111111111");
+
+fn find(seeds: &[&[u8]]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(seeds, &anon_presale::ID)
+}
+
+// ─── Presale ───
+
+pub fn presale(mint: &Pubkey, creator: &Pubkey) -> (Pubkey, u8) {
+    find(&[b"presale", mint.as_ref(), creator.as_ref()])
+}
+
+pub fn token_vault(presale: &Pubkey) -> (Pubkey, u8) {
+    find(&[b"token_vault", presale.as_ref()])
+}
+
+pub fn vault_authority(presale: &Pubkey) -> (Pubkey, u8) {
+    find(&[b"vault_auth", presale.as_ref()])
+}
+
+pub fn commitment(presale: &Pubkey, commitment_hash: &[u8; 32]) -> (Pubkey, u8) {
+    find(&[b"commitment", presale.as_ref(), commitment_hash])
+}
+
+pub fn commitment_tree(presale: &Pubkey) -> (Pubkey, u8) {
+    find(&[b"commitment_tree", presale.as_ref()])
+}
+
+pub fn nullifier(presale: &Pubkey, nullifier_hash: &[u8; 32]) -> (Pubkey, u8) {
+    find(&[b"nullifier", presale.as_ref(), nullifier_hash])
+}
+
+pub fn whitelist_nullifier(presale: &Pubkey, nullifier_hash: &[u8; 32]) -> (Pubkey, u8) {
+    find(&[b"whitelist_nullifier", presale.as_ref(), nullifier_hash])
+}
+
+// ─── Dark Pool ───
+
+pub fn dark_pool(base_mint: &Pubkey, quote_mint: &Pubkey) -> (Pubkey, u8) {
+    find(&[b"dark_pool", base_mint.as_ref(), quote_mint.as_ref()])
+}
+
+pub fn dp_base_vault(dark_pool: &Pubkey) -> (Pubkey, u8) {
+    find(&[b"dp_base_vault", dark_pool.as_ref()])
+}
+
+pub fn dp_quote_vault(dark_pool: &Pubkey) -> (Pubkey, u8) {
+    find(&[b"dp_quote_vault", dark_pool.as_ref()])
+}
+
+pub fn dp_fee_vault(dark_pool: &Pubkey) -> (Pubkey, u8) {
+    find(&[b"dp_fee_vault", dark_pool.as_ref()])
+}
+
+pub fn dp_vault_authority(dark_pool: &Pubkey) -> (Pubkey, u8) {
+    find(&[b"dp_vault_auth", dark_pool.as_ref()])
+}
+
+pub fn dark_order(dark_pool: &Pubkey, order_hash: &[u8; 32]) -> (Pubkey, u8) {
+    find(&[b"dark_order", dark_pool.as_ref(), order_hash])
+}
+
+/// Batch `batch_id` of a pool, i.e. the pool's `batch_count` when the batch was opened.
+pub fn batch(dark_pool: &Pubkey, batch_id: u64) -> (Pubkey, u8) {
+    find(&[b"batch", dark_pool.as_ref(), &batch_id.to_le_bytes()])
+}
+
+pub fn batch_order(batch: &Pubkey, order_hash: &[u8; 32]) -> (Pubkey, u8) {
+    find(&[b"batch_order", batch.as_ref(), order_hash])
+}
+
+// ─── Protocol ───
+
+pub fn protocol_config() -> (Pubkey, u8) {
+    find(&[b"protocol_config"])
+}
+
+pub fn claim_verifying_key() -> (Pubkey, u8) {
+    find(&[b"claim_verifying_key"])
+}
+
+/// The program's `ProgramData` account, whose upgrade authority may create the config.
+pub fn program_data() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[anon_presale::ID.as_ref()], &BPF_LOADER_UPGRADEABLE_ID)
+}
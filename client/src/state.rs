@@ -0,0 +1,13 @@
+//! Program account types and decoding from raw account data.
+
+use anchor_lang::{AccountDeserialize, Result};
+
+pub use anon_presale::{
+    BatchAuction, BatchOrder, Commitment, CommitmentTree, DarkOrder, DarkPool, Nullifier, Presale,
+    ProtocolConfig,
+};
+
+/// Decodes a program account, checking its discriminator.
+pub fn decode<T: AccountDeserialize>(mut data: &[u8]) -> Result<T> {
+    T::try_deserialize(&mut data)
+}
@@ -0,0 +1,59 @@
+//! Builds presale allowlists for `set_whitelist_root`, and the paths commits prove over.
+//!
+//! An allowlist is a Poseidon tree shaped like a presale's `CommitmentTree`, so commits prove
+//! membership with the claim circuit. Each holder picks a nullifier, a secret and the burner
+//! they will commit from, and hands the creator only `merkle::leaf_hash` of the three. The
+//! holder then proves knowledge of that preimage under the root with `dara-prover`, using the
+//! burner as the claim wallet, and the commit spends `merkle::nullifier_hash(nullifier)`.
+
+use anchor_lang::Result;
+use anon_presale::merkle::{poseidon, TREE_DEPTH};
+
+#[derive(Clone, Debug)]
+pub struct Whitelist {
+    /// Every level from the leaves up to the root. Missing right-hand nodes are the empty
+    /// subtree of that level, as in the on-chain tree.
+    levels: Vec<Vec<[u8; 32]>>,
+    zeros: Vec<[u8; 32]>,
+}
+
+impl Whitelist {
+    /// The list order is irrelevant to proofs, but must be kept to rebuild the same root.
+    /// Fails if a leaf is not a scalar field element.
+    pub fn new(leaves: &[[u8; 32]]) -> Result<Self> {
+        let mut levels = vec![leaves.to_vec()];
+        let mut zeros = vec![[0u8; 32]];
+        for depth in 0..TREE_DEPTH {
+            let zero = zeros[depth];
+            let next = levels[depth]
+                .chunks(2)
+                .map(|pair| poseidon(&[&pair[0], pair.get(1).unwrap_or(&zero)]))
+                .collect::<Result<Vec<_>>>()?;
+            levels.push(next);
+            zeros.push(poseidon(&[&zero, &zero])?);
+        }
+        Ok(Self { levels, zeros })
+    }
+
+    /// The root to publish, or all zeros for an empty list (which leaves the presale open).
+    pub fn root(&self) -> [u8; 32] {
+        self.levels[TREE_DEPTH]
+            .first()
+            .copied()
+            .unwrap_or([0u8; 32])
+    }
+
+    /// Index and sibling path of `leaf`, leaf level first, or `None` if it is not listed.
+    pub fn path(&self, leaf: &[u8; 32]) -> Option<(u32, Vec<[u8; 32]>)> {
+        let leaf_index = self.levels[0].iter().position(|node| node == leaf)?;
+        let mut index = leaf_index;
+        let path = (0..TREE_DEPTH)
+            .map(|depth| {
+                let sibling = self.levels[depth].get(index ^ 1).copied();
+                index >>= 1;
+                sibling.unwrap_or(self.zeros[depth])
+            })
+            .collect();
+        Some((leaf_index as u32, path))
+    }
+}
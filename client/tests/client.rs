@@ -0,0 +1,436 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountSerialize, Discriminator};
+use anon_presale::merkle;
+use anon_presale::{BatchOrder, Commitment, CommitmentTree, DarkOrder, PauseFlags, ProtocolConfig};
+use dara_client::{
+    args, batch_order_hash, commitment_hash, dark_order_hash, designated_order_hash, instructions,
+    limit_order_hash, pda, state, BatchOrderNote, CommitmentNote, DesignatedOrderNote,
+    LimitOrderNote, Market, Note, NoteError, OrderNote, PoolNote, PresaleKeys, Whitelist,
+};
+
+fn account_keys(ix: &anchor_lang::solana_program::instruction::Instruction) -> Vec<Pubkey> {
+    ix.accounts.iter().map(|meta| meta.pubkey).collect()
+}
+
+#[test]
+fn claim_finds_the_commitment_made_at_commit_time() {
+    let keys = PresaleKeys::new(Pubkey::new_unique(), Pubkey::new_unique());
+    let (burner, claim_wallet) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let secret = [7u8; 32];
+
+    let commit = instructions::commit_to_presale(
+        &burner,
+        &keys.address(),
+        args::CommitToPresale {
+            commitment_hash: commitment_hash(&secret, &claim_wallet),
+            sol_amount: 1_000_000_000,
+            whitelist_nullifier_hash: [0; 32],
+            whitelist_proof: None,
+        },
+    );
+    let claim = instructions::claim_tokens(
+        &Pubkey::new_unique(),
+        &keys,
+        &claim_wallet,
+        &burner,
+        args::ClaimTokens { secret },
+    );
+
+    let commitment = account_keys(&commit)[3];
+    assert!(account_keys(&claim).contains(&commitment));
+    assert!(account_keys(&claim).contains(&pda::token_vault(&keys.address()).0));
+    assert_eq!(commit.program_id, dara_client::PROGRAM_ID);
+    assert_eq!(&commit.data[..8], args::CommitToPresale::DISCRIMINATOR);
+}
+
+#[test]
+fn fill_finds_the_order_from_its_terms() {
+    let market = Market::new(Pubkey::new_unique(), Pubkey::new_unique());
+    let maker = Pubkey::new_unique();
+    let secret = [3u8; 32];
+    let order_hash = dark_order_hash(&secret, 0, 500, 1_000, &maker);
+
+    let place = instructions::place_dark_order(
+        &maker,
+        &market,
+        args::PlaceDarkOrder {
+            order_hash,
+            escrow_base: 500,
+            escrow_quote: 0,
+            expires_at: 0,
+        },
+    );
+    let fill = instructions::fill_dark_order(
+        &Pubkey::new_unique(),
+        &market,
+        &maker,
+        args::FillDarkOrder {
+            secret,
+            side: 0,
+            base_amount: 500,
+            quote_amount: 1_000,
+            fill_base: 500,
+        },
+    );
+
+    let dark_order = pda::dark_order(&market.dark_pool(), &order_hash).0;
+    assert_eq!(account_keys(&place)[3], dark_order);
+    assert_eq!(account_keys(&fill)[2], dark_order);
+}
+
+#[test]
+fn settle_batch_appends_revealed_orders_in_order() {
+    let market = Market::new(Pubkey::new_unique(), Pubkey::new_unique());
+    let batch = pda::batch(&market.dark_pool(), 0).0;
+    let orders = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let ix = instructions::settle_batch(&Pubkey::new_unique(), &market, &batch, &orders);
+    let tail = &ix.accounts[ix.accounts.len() - 2..];
+    assert_eq!(tail[0].pubkey, orders[0]);
+    assert_eq!(tail[1].pubkey, orders[1]);
+    assert!(tail.iter().all(|meta| meta.is_writable && !meta.is_signer));
+}
+
+#[test]
+fn whitelist_paths_verify_against_the_on_chain_fold() {
+    let leaf = |seed: u8| {
+        merkle::leaf_hash(
+            &[seed; 32],
+            &[seed.wrapping_add(1); 32],
+            &Pubkey::new_unique(),
+        )
+        .unwrap()
+    };
+    for size in 1..=5 {
+        let leaves: Vec<[u8; 32]> = (0..size).map(leaf).collect();
+        let list = Whitelist::new(&leaves).unwrap();
+
+        for (expected_index, leaf) in leaves.iter().enumerate() {
+            let (index, path) = list.path(leaf).unwrap();
+            assert_eq!(index as usize, expected_index);
+            assert_eq!(
+                merkle::root_from_path(*leaf, index, &path).unwrap(),
+                list.root()
+            );
+        }
+        assert!(list.path(&leaf(0x20)).is_none());
+    }
+
+    assert_eq!(Whitelist::new(&[]).unwrap().root(), [0u8; 32]);
+    assert!(Whitelist::new(&[[0xff; 32]]).is_err());
+}
+
+#[test]
+fn decode_checks_the_discriminator() {
+    let config = ProtocolConfig {
+        admin: Pubkey::new_unique(),
+        pending_admin: Pubkey::default(),
+        fee_recipient: Pubkey::new_unique(),
+        presale_fee_bps: 100,
+        taker_fee_bps: 10,
+        maker_fee_bps: 5,
+        paused: PauseFlags::default(),
+        bump: 255,
+    };
+    let mut data = Vec::new();
+    config.try_serialize(&mut data).unwrap();
+
+    let decoded: ProtocolConfig = state::decode(&data).unwrap();
+    assert_eq!(decoded.admin, config.admin);
+    assert_eq!(decoded.presale_fee_bps, 100);
+    assert!(state::decode::<state::DarkPool>(&data).is_err());
+}
+
+#[test]
+fn notes_round_trip_through_their_encoding() {
+    let commitment = Note::from(CommitmentNote::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        [9u8; 32],
+        1_000_000_000,
+    ));
+    let order = Note::from(OrderNote::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        [4u8; 32],
+        1,
+        500,
+        1_000,
+    ));
+    let pool = Note::from(PoolNote::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        [1u8; 32],
+        [2u8; 32],
+    ));
+    let limit = Note::from(LimitOrderNote::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        [5u8; 32],
+        0,
+        2_000_000,
+        1_000,
+    ));
+    let designated = Note::from(DesignatedOrderNote::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        [6u8; 32],
+        1,
+        500,
+        1_000,
+    ));
+    let batch = Note::from(BatchOrderNote::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        7,
+        Pubkey::new_unique(),
+        [8u8; 32],
+        1,
+        2_000_000,
+        1_000,
+    ));
+
+    for note in [commitment, order, pool, limit, designated, batch] {
+        let text = note.encode();
+        assert!(text.starts_with(dara_client::note::NOTE_PREFIX));
+        assert_eq!(Note::decode(&text).unwrap(), note);
+        assert_eq!(format!("  {note}\n").parse::<Note>().unwrap(), note);
+    }
+}
+
+#[test]
+fn notes_reject_typos_truncation_and_unknown_versions() {
+    let note = Note::from(CommitmentNote::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        [9u8; 32],
+        1_000_000_000,
+    ));
+    let text = note.encode();
+
+    let last = text.len() - 10;
+    let flipped = if &text[last..last + 1] == "0" {
+        "1"
+    } else {
+        "0"
+    };
+    let typo = format!("{}{flipped}{}", &text[..last], &text[last + 1..]);
+    assert_eq!(Note::decode(&typo), Err(NoteError::InvalidChecksum));
+    assert_eq!(
+        Note::decode(&text[..text.len() - 2]),
+        Err(NoteError::InvalidChecksum)
+    );
+    assert_eq!(
+        Note::decode(&text[dara_client::note::NOTE_PREFIX.len()..]),
+        Err(NoteError::MissingPrefix)
+    );
+    assert_eq!(Note::decode("dara-note-zz"), Err(NoteError::InvalidHex));
+
+    // A future version with a valid checksum is refused rather than misread
+    let mut payload = vec![2u8, 0];
+    payload.extend_from_slice(&[0u8; 32]);
+    let sum = anchor_lang::solana_program::hash::hash(&payload).to_bytes();
+    payload.extend_from_slice(&sum[..4]);
+    let future: String = payload.iter().map(|byte| format!("{byte:02x}")).collect();
+    assert_eq!(
+        Note::decode(&format!("dara-note-{future}")),
+        Err(NoteError::UnsupportedVersion(2))
+    );
+}
+
+#[test]
+fn notes_validate_against_on_chain_accounts() {
+    let (presale, claim_wallet) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let note = CommitmentNote::new(presale, claim_wallet, [9u8; 32], 1_000_000_000);
+    let mut commitment = Commitment {
+        presale,
+        commitment_hash: commitment_hash(&[9u8; 32], &claim_wallet),
+        sol_amount: 1_000_000_000,
+        claimed_amount: 0,
+        is_claimed: false,
+        bump: 255,
+    };
+    assert_eq!(
+        note.address(),
+        pda::commitment(&presale, &note.commitment_hash()).0
+    );
+    assert_eq!(note.validate(&commitment), Ok(()));
+    commitment.sol_amount = 2_000_000_000;
+    assert_eq!(
+        note.validate(&commitment),
+        Err(NoteError::Mismatch("amount"))
+    );
+
+    let other_program = CommitmentNote {
+        program_id: Pubkey::new_unique(),
+        ..note
+    };
+    assert!(matches!(
+        other_program.validate(&commitment),
+        Err(NoteError::WrongProgram(_))
+    ));
+
+    let market = Market::new(Pubkey::new_unique(), Pubkey::new_unique());
+    let maker = Pubkey::new_unique();
+    let note = OrderNote::new(
+        market.base_mint,
+        market.quote_mint,
+        maker,
+        [3u8; 32],
+        0,
+        500,
+        1_000,
+    );
+    let mut order = DarkOrder {
+        pool: market.dark_pool(),
+        maker,
+        order_hash: dark_order_hash(&[3u8; 32], 0, 500, 1_000, &maker),
+        escrow_base: 500,
+        escrow_quote: 0,
+        remaining_base: 500,
+        remaining_quote: 0,
+        filled_base: 0,
+        filled_quote: 0,
+        is_filled: false,
+        is_cancelled: false,
+        created_at: 0,
+        expires_at: 0,
+        order_id: 1,
+        bump: 255,
+    };
+    assert_eq!(note.validate(&order), Ok(()));
+    order.order_hash = dark_order_hash(&[3u8; 32], 0, 500, 999, &maker);
+    assert_eq!(note.validate(&order), Err(NoteError::Mismatch("terms")));
+}
+
+#[test]
+fn pool_notes_check_their_tree_and_scalars() {
+    let (presale, claim_wallet) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let note = PoolNote::new(presale, claim_wallet, [1u8; 32], [2u8; 32]);
+    let mut tree = CommitmentTree {
+        presale,
+        next_index: 0,
+        root: [0u8; 32],
+        filled_subtrees: [[0u8; 32]; anon_presale::merkle::TREE_DEPTH],
+        bump: 255,
+    };
+    assert_eq!(note.address(), pda::commitment_tree(&presale).0);
+    assert_eq!(
+        note.nullifier_address().unwrap(),
+        pda::nullifier(&presale, &note.nullifier_hash().unwrap()).0
+    );
+    assert_eq!(note.validate(&tree), Ok(()));
+    tree.presale = Pubkey::new_unique();
+    assert_eq!(note.validate(&tree), Err(NoteError::Mismatch("presale")));
+
+    // Above the field modulus, so no leaf could ever have been committed for it
+    let oversized = PoolNote::new(presale, claim_wallet, [0xff; 32], [2u8; 32]);
+    assert_eq!(oversized.leaf(), Err(NoteError::InvalidScalar));
+}
+
+#[test]
+fn order_notes_open_their_own_kind_of_order() {
+    let market = Market::new(Pubkey::new_unique(), Pubkey::new_unique());
+    let (maker, taker) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut order = DarkOrder {
+        pool: market.dark_pool(),
+        maker,
+        order_hash: limit_order_hash(&[5u8; 32], 0, 2_000_000, 1_000, &maker),
+        escrow_base: 1_000,
+        escrow_quote: 0,
+        remaining_base: 1_000,
+        remaining_quote: 0,
+        filled_base: 0,
+        filled_quote: 0,
+        is_filled: false,
+        is_cancelled: false,
+        created_at: 0,
+        expires_at: 0,
+        order_id: 1,
+        bump: 255,
+    };
+    let limit = LimitOrderNote::new(
+        market.base_mint,
+        market.quote_mint,
+        maker,
+        [5u8; 32],
+        0,
+        2_000_000,
+        1_000,
+    );
+    assert_eq!(limit.validate(&order), Ok(()));
+    assert_eq!(
+        limit.address(),
+        pda::dark_order(&market.dark_pool(), &limit.order_hash()).0
+    );
+
+    // The same terms as a fixed-price or designated order hash differently
+    let designated = DesignatedOrderNote::new(
+        market.base_mint,
+        market.quote_mint,
+        maker,
+        taker,
+        [5u8; 32],
+        0,
+        1_000,
+        2_000,
+    );
+    assert_eq!(
+        designated.validate(&order),
+        Err(NoteError::Mismatch("terms"))
+    );
+    order.order_hash = designated_order_hash(&[5u8; 32], 0, 1_000, 2_000, &taker, &maker);
+    assert_eq!(designated.validate(&order), Ok(()));
+    let other_taker = DesignatedOrderNote {
+        taker: Pubkey::new_unique(),
+        ..designated
+    };
+    assert_eq!(
+        other_taker.validate(&order),
+        Err(NoteError::Mismatch("terms"))
+    );
+
+    let owner = Pubkey::new_unique();
+    let note = BatchOrderNote::new(
+        market.base_mint,
+        market.quote_mint,
+        3,
+        owner,
+        [8u8; 32],
+        1,
+        2_000_000,
+        1_000,
+    );
+    let batch = pda::batch(&market.dark_pool(), 3).0;
+    assert_eq!(note.batch(), batch);
+    assert_eq!(
+        note.address(),
+        pda::batch_order(&batch, &note.order_hash()).0
+    );
+    let mut batch_order = BatchOrder {
+        batch,
+        owner,
+        order_hash: batch_order_hash(&[8u8; 32], 1, 2_000_000, 1_000, &owner),
+        escrow_base: 0,
+        escrow_quote: 2_000,
+        is_revealed: false,
+        side: 0,
+        price: 0,
+        size: 0,
+        reveal_index: 0,
+        fill_base: 0,
+        fill_quote: 0,
+        fee_quote: 0,
+        bump: 255,
+    };
+    assert_eq!(note.validate(&batch_order), Ok(()));
+    batch_order.batch = pda::batch(&market.dark_pool(), 4).0;
+    assert_eq!(
+        note.validate(&batch_order),
+        Err(NoteError::Mismatch("mints or batch"))
+    );
+}
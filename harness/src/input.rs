@@ -0,0 +1,59 @@
+//! The loader's input format. `entrypoint::deserialize` turns it into account infos that
+//! point into the buffer, with room behind each account's data for it to grow in place.
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::entrypoint::{
+    BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, NON_DUP_MARKER,
+};
+use anchor_lang::solana_program::instruction::Instruction;
+
+use crate::Account;
+
+/// Serializes `ix` against `accounts`. Accounts the map does not hold are passed as empty
+/// system accounts, and a key listed more than once gets the union of its privileges.
+/// The buffer is `u64`-backed so the loader's aligned reads hold.
+pub(crate) fn serialize(ix: &Instruction, accounts: &HashMap<Pubkey, Account>) -> Vec<u64> {
+    let metas = &ix.accounts;
+    let mut bytes = Vec::new();
+    bytes.extend((metas.len() as u64).to_le_bytes());
+
+    for (index, meta) in metas.iter().enumerate() {
+        if let Some(first) = metas[..index].iter().position(|m| m.pubkey == meta.pubkey) {
+            bytes.push(first as u8);
+            bytes.extend([0u8; 7]);
+            continue;
+        }
+
+        let same_key = || metas.iter().filter(|m| m.pubkey == meta.pubkey);
+        let account = accounts.get(&meta.pubkey).cloned().unwrap_or_default();
+        bytes.push(NON_DUP_MARKER);
+        bytes.push(same_key().any(|m| m.is_signer) as u8);
+        bytes.push(same_key().any(|m| m.is_writable) as u8);
+        bytes.push(account.executable as u8);
+        // Original data length, filled in by the loader
+        bytes.extend([0u8; 4]);
+        bytes.extend(meta.pubkey.as_ref());
+        bytes.extend(account.owner.as_ref());
+        bytes.extend(account.lamports.to_le_bytes());
+        bytes.extend((account.data.len() as u64).to_le_bytes());
+        bytes.extend(&account.data);
+        bytes.resize(bytes.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        bytes.resize(bytes.len().next_multiple_of(BPF_ALIGN_OF_U128), 0);
+        // Rent epoch
+        bytes.extend(u64::MAX.to_le_bytes());
+    }
+
+    bytes.extend((ix.data.len() as u64).to_le_bytes());
+    bytes.extend(&ix.data);
+    bytes.extend(ix.program_id.as_ref());
+
+    let mut buffer = vec![0u64; bytes.len().div_ceil(8)];
+    for (word, chunk) in buffer.iter_mut().zip(bytes.chunks(8)) {
+        let mut le = [0u8; 8];
+        le[..chunk.len()].copy_from_slice(chunk);
+        *word = u64::from_le_bytes(le);
+    }
+    buffer
+}
@@ -0,0 +1,468 @@
+//! In-process runtime for testing the `anon_presale` program natively.
+//!
+//! The program, the System program and both SPL token programs run as ordinary Rust inside
+//! the test process. Accounts are serialized in the loader's input format, while CPIs,
+//! sysvars, events and return data go through `solana_program`'s syscall stubs. No SBF
+//! toolchain or validator is needed, and the clock can be warped between transactions.
+//!
+//! Each transaction is held to the runtime's rules: it must fit a legacy transaction, only
+//! an account's owner may debit it or change its data, lamports are conserved, read-only
+//! accounts stay untouched and writable accounts end rent-exempt unless they already were
+//! not. The alt_bn128 and Poseidon syscalls run the same arkworks and light-poseidon code the
+//! validator does. Compute units are not metered.
+//!
+//! Off-chain, `msg!` prints straight to stdout rather than through the stubs, so program
+//! logs show up in the test output instead of in `Svm::logs`, which traces invocations.
+//!
+//! ```ignore
+//! let mut svm = Svm::new();
+//! let creator = svm.new_wallet(10 * LAMPORTS_PER_SOL);
+//! svm.warp_to(presale_end + 1);
+//! assert_error(svm.process(&finalize), PresaleError::SoftCapNotReached);
+//! ```
+
+mod input;
+mod runtime;
+mod system;
+
+use std::collections::HashMap;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::deserialize;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::{
+    system_program, AccountDeserialize, AccountSerialize, Discriminator, Event, Owner,
+};
+use anchor_spl::associated_token::{
+    get_associated_token_address_with_program_id, spl_associated_token_account,
+};
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config;
+use spl_token_2022::extension::{ExtensionType, StateWithExtensions};
+
+/// An account as the harness stores it between transactions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+/// A failed transaction: the program's error and the invocations it made.
+#[derive(Debug)]
+pub struct Failure {
+    pub error: ProgramError,
+    pub logs: Vec<String>,
+}
+
+impl Failure {
+    /// Whether the transaction failed with `expected`. Every error enum of the program
+    /// numbers its variants from the same offset and only the number reaches the runtime,
+    /// so the instruction under test is what tells them apart.
+    pub fn is(&self, expected: impl Into<anchor_lang::error::Error>) -> bool {
+        self.error == ProgramError::from(expected.into())
+    }
+}
+
+/// Asserts that `result` failed with `expected`.
+#[track_caller]
+pub fn assert_error(
+    result: std::result::Result<(), Failure>,
+    expected: impl Into<anchor_lang::error::Error>,
+) {
+    let expected = expected.into();
+    let description = expected.to_string();
+    match result {
+        Ok(()) => panic!("expected {description}, but the transaction succeeded"),
+        Err(failure) if failure.is(expected) => {}
+        Err(failure) => panic!(
+            "expected {description}, got {}\n{}",
+            failure.error,
+            failure.logs.join("\n")
+        ),
+    }
+}
+
+/// Size limit of a serialized transaction, the IPv6 MTU less headers.
+pub const PACKET_DATA_SIZE: usize = 1232;
+
+pub struct Svm {
+    accounts: HashMap<Pubkey, Account>,
+    clock: Clock,
+    /// Funds and holds the authorities of mints created by the harness.
+    payer: Pubkey,
+    upgrade_authority: Pubkey,
+    logs: Vec<String>,
+    events: Vec<Vec<u8>>,
+}
+
+impl Default for Svm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Svm {
+    /// A fresh chain with the program deployed and upgradeable by `upgrade_authority()`.
+    pub fn new() -> Self {
+        let mut svm = Self {
+            accounts: HashMap::new(),
+            clock: Clock {
+                slot: 1,
+                unix_timestamp: 1_700_000_000,
+                ..Clock::default()
+            },
+            payer: Pubkey::new_unique(),
+            upgrade_authority: Pubkey::new_unique(),
+            logs: Vec::new(),
+            events: Vec::new(),
+        };
+
+        for program_id in [
+            system_program::ID,
+            spl_token::ID,
+            spl_token_2022::ID,
+            spl_associated_token_account::ID,
+        ] {
+            svm.set_account(program_id, executable(Pubkey::default(), Vec::new()));
+        }
+
+        // Upgradeable loader state: `Program { programdata_address }` and
+        // `ProgramData { slot, upgrade_authority_address }`
+        let loader = <ProgramData as Owner>::owner();
+        let program_data = Pubkey::find_program_address(&[anon_presale::ID.as_ref()], &loader).0;
+        let mut program = 2u32.to_le_bytes().to_vec();
+        program.extend(program_data.as_ref());
+        svm.set_account(anon_presale::ID, executable(loader, program));
+        let mut data = 3u32.to_le_bytes().to_vec();
+        data.extend(0u64.to_le_bytes());
+        data.push(1);
+        data.extend(svm.upgrade_authority.as_ref());
+        svm.set_account(
+            program_data,
+            Account {
+                lamports: 1,
+                data,
+                owner: loader,
+                executable: false,
+            },
+        );
+
+        svm.airdrop(&svm.payer.clone(), 1_000_000 * LAMPORTS_PER_SOL);
+        svm
+    }
+
+    /// The key allowed to create the protocol config.
+    pub fn upgrade_authority(&self) -> Pubkey {
+        self.upgrade_authority
+    }
+
+    // ─── Accounts ───
+
+    pub fn account(&self, key: &Pubkey) -> Option<&Account> {
+        self.accounts.get(key)
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: Account) {
+        self.accounts.insert(key, account);
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.accounts.get(key).map_or(0, |account| account.lamports)
+    }
+
+    pub fn airdrop(&mut self, to: &Pubkey, lamports: u64) {
+        self.accounts.entry(*to).or_default().lamports += lamports;
+    }
+
+    /// A new system account holding `lamports`.
+    pub fn new_wallet(&mut self, lamports: u64) -> Pubkey {
+        let wallet = Pubkey::new_unique();
+        self.airdrop(&wallet, lamports);
+        wallet
+    }
+
+    /// Decodes a program account. Panics if it does not exist or has the wrong type.
+    pub fn get<T: AccountDeserialize>(&self, key: &Pubkey) -> T {
+        let account = self
+            .accounts
+            .get(key)
+            .unwrap_or_else(|| panic!("account {key} does not exist"));
+        T::try_deserialize(&mut account.data.as_slice())
+            .unwrap_or_else(|err| panic!("account {key} did not decode: {err}"))
+    }
+
+    /// Overwrites a program account's state in place, for states no instruction sequence
+    /// reaches cheaply.
+    pub fn set<T: AccountSerialize>(&mut self, key: &Pubkey, state: &T) {
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        let account = self.accounts.get_mut(key).expect("account does not exist");
+        account.data[..data.len()].copy_from_slice(&data);
+    }
+
+    // ─── Clock ───
+
+    pub fn now(&self) -> i64 {
+        self.clock.unix_timestamp
+    }
+
+    pub fn warp_to(&mut self, unix_timestamp: i64) {
+        self.clock.slot += 1;
+        self.clock.unix_timestamp = unix_timestamp;
+    }
+
+    pub fn warp(&mut self, seconds: i64) {
+        self.warp_to(self.clock.unix_timestamp + seconds);
+    }
+
+    // ─── Transactions ───
+
+    /// Processes `ix` as a single-instruction transaction, committing its writes only if it
+    /// succeeds. Signatures are taken from the instruction's account metas.
+    pub fn process(&mut self, ix: &Instruction) -> std::result::Result<(), Failure> {
+        let size = self.transaction_size(ix);
+        assert!(
+            size <= PACKET_DATA_SIZE,
+            "transaction is {size} bytes, over the {PACKET_DATA_SIZE} byte limit"
+        );
+
+        let mut input = input::serialize(ix, &self.accounts);
+        runtime::begin(self.clock.clone());
+
+        let (result, writes) = {
+            let (program_id, infos, data) = unsafe { deserialize(input.as_mut_ptr() as *mut u8) };
+            let result = runtime::execute(program_id, &infos, data);
+            let mut writes: Vec<(Pubkey, bool, Account)> = Vec::new();
+            for info in &infos {
+                if writes.iter().all(|(key, _, _)| key != info.key) {
+                    let account = Account {
+                        lamports: info.lamports(),
+                        data: info.data.borrow().to_vec(),
+                        owner: *info.owner,
+                        executable: info.executable,
+                    };
+                    writes.push((*info.key, info.is_writable, account));
+                }
+            }
+            (result, writes)
+        };
+
+        let output = runtime::end();
+        self.logs = output.logs;
+        self.events = output.events;
+        if let Err(error) = result {
+            return Err(Failure {
+                error,
+                logs: self.logs.clone(),
+            });
+        }
+
+        let before: u128 = writes
+            .iter()
+            .map(|(key, _, _)| self.lamports(key) as u128)
+            .sum();
+        let after: u128 = writes
+            .iter()
+            .map(|(_, _, account)| account.lamports as u128)
+            .sum();
+        assert_eq!(before, after, "transaction did not conserve lamports");
+
+        for (key, is_writable, account) in writes {
+            let pre = self.accounts.get(&key).cloned().unwrap_or_default();
+            if !is_writable {
+                assert!(pre == account, "read-only account {key} was modified");
+                continue;
+            }
+            assert!(
+                rent_transition_allowed(&pre, &account),
+                "account {key} was left with {} lamports, short of rent exemption for {} bytes",
+                account.lamports,
+                account.data.len()
+            );
+            if account.lamports == 0 {
+                self.accounts.remove(&key);
+            } else {
+                self.accounts.insert(key, account);
+            }
+        }
+        Ok(())
+    }
+
+    /// Size of `ix` sent as a legacy transaction, fee payer included. The first signer pays,
+    /// or the harness payer signs as well when the instruction has no signer.
+    pub fn transaction_size(&self, ix: &Instruction) -> usize {
+        let mut keys: Vec<(Pubkey, bool)> = Vec::new();
+        if !ix.accounts.iter().any(|meta| meta.is_signer) {
+            keys.push((self.payer, true));
+        }
+        for meta in &ix.accounts {
+            match keys.iter_mut().find(|(key, _)| *key == meta.pubkey) {
+                Some((_, is_signer)) => *is_signer |= meta.is_signer,
+                None => keys.push((meta.pubkey, meta.is_signer)),
+            }
+        }
+        if keys.iter().all(|(key, _)| *key != ix.program_id) {
+            keys.push((ix.program_id, false));
+        }
+        let signatures = keys.iter().filter(|(_, is_signer)| *is_signer).count();
+
+        let compact = |len: usize| match len {
+            0..=0x7f => 1,
+            0x80..=0x3fff => 2,
+            _ => 3,
+        };
+        compact(signatures)
+            + 64 * signatures
+            + 3 // header
+            + compact(keys.len())
+            + 32 * keys.len()
+            + 32 // recent blockhash
+            + compact(1)
+            + 1 // program index
+            + compact(ix.accounts.len())
+            + ix.accounts.len()
+            + compact(ix.data.len())
+            + ix.data.len()
+    }
+
+    /// Invocation trace of the last transaction.
+    pub fn logs(&self) -> &[String] {
+        &self.logs
+    }
+
+    /// Events of type `E` the last transaction emitted.
+    pub fn events<E: Event + Discriminator>(&self) -> Vec<E> {
+        self.events
+            .iter()
+            .filter_map(|data| {
+                let body = data.strip_prefix(E::DISCRIMINATOR)?;
+                Some(E::deserialize(&mut &body[..]).expect("event did not decode"))
+            })
+            .collect()
+    }
+
+    // ─── Tokens ───
+
+    /// A mint of `token_program` whose authority is the harness payer.
+    pub fn create_mint(&mut self, token_program: &Pubkey, decimals: u8) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        self.create_account(&mint, spl_token::state::Mint::LEN, token_program);
+        self.initialize_mint(token_program, &mint, decimals);
+        mint
+    }
+
+    /// A Token-2022 mint charging `fee_bps` on every transfer, capped at `maximum_fee`.
+    pub fn create_transfer_fee_mint(
+        &mut self,
+        decimals: u8,
+        fee_bps: u16,
+        maximum_fee: u64,
+    ) -> Pubkey {
+        let mint = Pubkey::new_unique();
+        let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+            ExtensionType::TransferFeeConfig,
+        ])
+        .unwrap();
+        self.create_account(&mint, space, &spl_token_2022::ID);
+        let init_fee = initialize_transfer_fee_config(
+            &spl_token_2022::ID,
+            &mint,
+            None,
+            Some(&self.payer),
+            fee_bps,
+            maximum_fee,
+        )
+        .unwrap();
+        self.process(&init_fee).unwrap();
+        self.initialize_mint(&spl_token_2022::ID, &mint, decimals);
+        mint
+    }
+
+    /// The associated token account of `owner` for `mint`, created if it does not exist.
+    pub fn token_account(&mut self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let token_program = self.accounts[mint].owner;
+        let address = get_associated_token_address_with_program_id(owner, mint, &token_program);
+        if !self.accounts.contains_key(&address) {
+            let create = spl_associated_token_account::instruction::create_associated_token_account(
+                &self.payer,
+                owner,
+                mint,
+                &token_program,
+            );
+            self.process(&create).unwrap();
+        }
+        address
+    }
+
+    pub fn mint_to(&mut self, mint: &Pubkey, account: &Pubkey, amount: u64) {
+        let token_program = self.accounts[mint].owner;
+        let mint_to = spl_token_2022::instruction::mint_to(
+            &token_program,
+            mint,
+            account,
+            &self.payer,
+            &[],
+            amount,
+        )
+        .unwrap();
+        self.process(&mint_to).unwrap();
+    }
+
+    /// Token balance of a token account, or zero if it does not exist.
+    pub fn token_balance(&self, account: &Pubkey) -> u64 {
+        self.accounts.get(account).map_or(0, |account| {
+            StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+                .unwrap()
+                .base
+                .amount
+        })
+    }
+
+    /// A rent-exempt, zeroed account for `owner` to initialize.
+    fn create_account(&mut self, key: &Pubkey, space: usize, owner: &Pubkey) {
+        let account = Account {
+            lamports: Rent::default().minimum_balance(space),
+            data: vec![0; space],
+            owner: *owner,
+            executable: false,
+        };
+        self.set_account(*key, account);
+    }
+
+    fn initialize_mint(&mut self, token_program: &Pubkey, mint: &Pubkey, decimals: u8) {
+        let init = spl_token_2022::instruction::initialize_mint2(
+            token_program,
+            mint,
+            &self.payer,
+            None,
+            decimals,
+        )
+        .unwrap();
+        self.process(&init).unwrap();
+    }
+}
+
+/// Whether a writable account may go from `pre` to `post`: it must end empty or rent-exempt,
+/// unless it was already short of exemption and neither grew nor gained lamports.
+fn rent_transition_allowed(pre: &Account, post: &Account) -> bool {
+    let rent = Rent::default();
+    let paying = |account: &Account| {
+        account.lamports > 0 && !rent.is_exempt(account.lamports, account.data.len())
+    };
+    !paying(post)
+        || (paying(pre) && pre.data.len() == post.data.len() && post.lamports <= pre.lamports)
+}
+
+fn executable(owner: Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: 1,
+        data,
+        owner,
+        executable: true,
+    }
+}
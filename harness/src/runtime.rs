@@ -0,0 +1,312 @@
+//! Syscall stubs standing in for the runtime: sysvars, events, return data and
+//! cross-program invocation. The stubs are process-wide, so the state they serve lives in a thread local
+//! set up around each transaction, which keeps tests on different threads apart.
+//!
+//! Each invocation's account changes are checked as the runtime checks them when the
+//! invocation returns or makes a CPI: only the owner may debit an account, change its data
+//! or hand it to another owner, and only once its data is zeroed.
+
+use std::cell::RefCell;
+use std::sync::Once;
+
+use anchor_lang::prelude::{msg, AccountInfo, Clock, ProgramError, Pubkey, Rent};
+use anchor_lang::solana_program::entrypoint::{ProgramResult, SUCCESS};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use anchor_lang::system_program;
+use anchor_spl::associated_token::spl_associated_token_account;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+
+use crate::system;
+
+/// An account's state as an invocation last saw it.
+struct Snapshot {
+    key: Pubkey,
+    lamports: u64,
+    data: Vec<u8>,
+    owner: Pubkey,
+}
+
+impl Snapshot {
+    fn of(info: &AccountInfo) -> Self {
+        Self {
+            key: *info.key,
+            lamports: info.lamports(),
+            data: info.data.borrow().to_vec(),
+            owner: *info.owner,
+        }
+    }
+}
+
+/// A program on the invocation stack and the accounts it was handed.
+struct Frame {
+    program_id: Pubkey,
+    accounts: Vec<Snapshot>,
+}
+
+#[derive(Default)]
+struct Context {
+    clock: Clock,
+    stack: Vec<Frame>,
+    logs: Vec<String>,
+    events: Vec<Vec<u8>>,
+    return_data: Option<(Pubkey, Vec<u8>)>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Context> = RefCell::default();
+}
+
+fn with<R>(f: impl FnOnce(&mut Context) -> R) -> R {
+    CONTEXT.with(|context| f(&mut context.borrow_mut()))
+}
+
+/// Everything a transaction logged and emitted.
+pub(crate) struct Output {
+    pub logs: Vec<String>,
+    pub events: Vec<Vec<u8>>,
+}
+
+/// Starts a transaction at `clock`.
+pub(crate) fn begin(clock: Clock) {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        set_syscall_stubs(Box::new(Stubs));
+    });
+    with(|context| {
+        *context = Context {
+            clock,
+            ..Context::default()
+        }
+    });
+}
+
+pub(crate) fn end() -> Output {
+    with(|context| {
+        let context = std::mem::take(context);
+        Output {
+            logs: context.logs,
+            events: context.events,
+        }
+    })
+}
+
+/// Runs the top-level instruction of a transaction.
+pub(crate) fn execute<'a>(
+    program_id: &Pubkey,
+    accounts: &'a [AccountInfo<'a>],
+    data: &[u8],
+) -> ProgramResult {
+    if *program_id == anon_presale::ID {
+        traced(program_id, accounts, || {
+            anon_presale::entry(program_id, accounts, data)
+        })
+    } else {
+        invoke_native(program_id, accounts, data)
+    }
+}
+
+/// Runs one of the programs the program invokes. Only these can be the target of a CPI.
+fn invoke_native(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    traced(program_id, accounts, || match *program_id {
+        system_program::ID => system::process(accounts, data),
+        spl_token::ID => spl_token::processor::Processor::process(program_id, accounts, data),
+        spl_token_2022::ID => {
+            spl_token_2022::processor::Processor::process(program_id, accounts, data)
+        }
+        spl_associated_token_account::ID => {
+            spl_associated_token_account::processor::process_instruction(program_id, accounts, data)
+        }
+        _ => Err(ProgramError::IncorrectProgramId),
+    })
+}
+
+/// Pushes `program_id` onto the invocation stack around `run`, logging as the runtime does,
+/// and checks the changes it made to `accounts` once it succeeds.
+fn traced(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    run: impl FnOnce() -> ProgramResult,
+) -> ProgramResult {
+    let depth = with(|context| {
+        context.stack.push(Frame {
+            program_id: *program_id,
+            accounts: accounts.iter().map(Snapshot::of).collect(),
+        });
+        context.stack.len()
+    });
+    with(|context| {
+        context
+            .logs
+            .push(format!("Program {program_id} invoke [{depth}]"))
+    });
+
+    let result = run();
+    if result.is_ok() {
+        check_frame(accounts);
+    }
+
+    with(|context| {
+        context.stack.pop();
+        context.logs.push(match &result {
+            Ok(()) => format!("Program {program_id} success"),
+            Err(err) => format!("Program {program_id} failed: {err}"),
+        });
+    });
+    result
+}
+
+/// A cross-program invocation. Callee accounts share the caller's lamports and data, so
+/// changes flow both ways, and privileges are checked as the runtime checks them: a signer
+/// must have signed the caller or be a PDA of the caller signed for with `signers_seeds`.
+fn invoke_signed(
+    ix: &Instruction,
+    caller_accounts: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let caller = with(|context| {
+        context
+            .stack
+            .last()
+            .expect("CPI outside a transaction")
+            .program_id
+    });
+    let pda_signers = signers_seeds
+        .iter()
+        .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+
+    let mut accounts = Vec::with_capacity(ix.accounts.len());
+    for meta in &ix.accounts {
+        let info = caller_accounts
+            .iter()
+            .find(|info| *info.key == meta.pubkey)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if meta.is_writable && !info.is_writable {
+            msg!("{}'s writable privilege escalated", meta.pubkey);
+            return Err(ProgramError::InvalidArgument);
+        }
+        if meta.is_signer && !info.is_signer && !pda_signers.contains(&meta.pubkey) {
+            msg!("{}'s signer privilege escalated", meta.pubkey);
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        accounts.push(AccountInfo {
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+            ..info.clone()
+        });
+    }
+
+    // The caller's changes so far are checked before the callee sees them, and whatever the
+    // callee changes becomes the caller's new starting point
+    check_frame(caller_accounts);
+    let result = invoke_native(&ix.program_id, &accounts, &ix.data);
+    with(|context| {
+        let frame = context.stack.last_mut().unwrap();
+        for snapshot in &mut frame.accounts {
+            if let Some(info) = caller_accounts
+                .iter()
+                .find(|info| *info.key == snapshot.key)
+            {
+                *snapshot = Snapshot::of(info);
+            }
+        }
+    });
+    result
+}
+
+/// Checks the changes the innermost invocation made to `accounts` since it last saw them,
+/// as the runtime does. A violation is a bug in the program, so it panics.
+fn check_frame(accounts: &[AccountInfo]) {
+    with(|context| {
+        let frame = context.stack.last().unwrap();
+        let program_id = frame.program_id;
+        for pre in &frame.accounts {
+            let Some(info) = accounts.iter().find(|info| *info.key == pre.key) else {
+                continue;
+            };
+            let key = pre.key;
+            let owned = pre.owner == program_id;
+            let data = info.data.borrow();
+            if info.lamports() != pre.lamports {
+                assert!(
+                    info.is_writable,
+                    "{program_id} changed the lamports of read-only {key}"
+                );
+            }
+            assert!(
+                owned || info.lamports() >= pre.lamports,
+                "{program_id} debited {key}, which it does not own"
+            );
+            if *data != pre.data {
+                assert!(
+                    info.is_writable,
+                    "{program_id} changed the data of read-only {key}"
+                );
+                assert!(
+                    owned,
+                    "{program_id} changed the data of {key}, which it does not own"
+                );
+            }
+            if *info.owner != pre.owner {
+                assert!(
+                    owned && info.is_writable && !info.executable,
+                    "{program_id} reassigned {key}, which it does not own"
+                );
+                assert!(
+                    data.iter().all(|byte| *byte == 0),
+                    "{program_id} reassigned {key} without zeroing its data"
+                );
+            }
+        }
+    });
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        with(|context| context.events.push(fields.concat()));
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        invoke_signed(instruction, account_infos, signers_seeds)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = with(|context| context.clock.clone());
+        unsafe { std::ptr::write(var_addr as *mut Clock, clock) };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { std::ptr::write(var_addr as *mut Rent, Rent::default()) };
+        SUCCESS
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        with(|context| context.return_data.clone())
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        with(|context| {
+            let program_id = context
+                .stack
+                .last()
+                .expect("return data outside a transaction")
+                .program_id;
+            context.return_data = (!data.is_empty()).then(|| (program_id, data.to_vec()));
+        });
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        with(|context| context.stack.len() as u64)
+    }
+}
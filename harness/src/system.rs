@@ -0,0 +1,104 @@
+//! The parts of the System program that the program and the token programs invoke.
+
+use anchor_lang::prelude::{msg, AccountInfo, ProgramError, Pubkey};
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::system_program;
+
+// `SystemError` codes
+const ACCOUNT_ALREADY_IN_USE: u32 = 0;
+const RESULT_WITH_NEGATIVE_LAMPORTS: u32 = 1;
+
+const CREATE_ACCOUNT: u32 = 0;
+const ASSIGN: u32 = 1;
+const TRANSFER: u32 = 2;
+const ALLOCATE: u32 = 8;
+
+pub(crate) fn process(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let account = |index: usize| {
+        accounts
+            .get(index)
+            .ok_or(ProgramError::NotEnoughAccountKeys)
+    };
+    match read_u32(data, 0)? {
+        CREATE_ACCOUNT => {
+            let (from, to) = (account(0)?, account(1)?);
+            if to.lamports() > 0 {
+                msg!("Create Account: account {} already in use", to.key);
+                return Err(ProgramError::Custom(ACCOUNT_ALREADY_IN_USE));
+            }
+            allocate(to, read_u64(data, 12)?)?;
+            assign(to, &read_pubkey(data, 20)?)?;
+            transfer(from, to, read_u64(data, 4)?)
+        }
+        ASSIGN => assign(account(0)?, &read_pubkey(data, 4)?),
+        TRANSFER => transfer(account(0)?, account(1)?, read_u64(data, 4)?),
+        ALLOCATE => allocate(account(0)?, read_u64(data, 4)?),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+fn transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    require_signer(from)?;
+    if !from.data_is_empty() || *from.owner != system_program::ID {
+        msg!("Transfer: `from` must not carry data");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if from.lamports() < lamports {
+        msg!(
+            "Transfer: insufficient lamports {}, need {}",
+            from.lamports(),
+            lamports
+        );
+        return Err(ProgramError::Custom(RESULT_WITH_NEGATIVE_LAMPORTS));
+    }
+    **from.try_borrow_mut_lamports()? -= lamports;
+    **to.try_borrow_mut_lamports()? += lamports;
+    Ok(())
+}
+
+fn allocate(account: &AccountInfo, space: u64) -> ProgramResult {
+    require_signer(account)?;
+    if !account.data_is_empty() || *account.owner != system_program::ID {
+        msg!("Allocate: account {} already in use", account.key);
+        return Err(ProgramError::Custom(ACCOUNT_ALREADY_IN_USE));
+    }
+    account.resize(space as usize)
+}
+
+fn assign(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if account.owner == owner {
+        return Ok(());
+    }
+    require_signer(account)?;
+    account.assign(owner);
+    Ok(())
+}
+
+fn require_signer(account: &AccountInfo) -> ProgramResult {
+    if !account.is_signer {
+        msg!("System program: {} must sign", account.key);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, ProgramError> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let bytes = data
+        .get(offset..offset + 8)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Result<Pubkey, ProgramError> {
+    let bytes = data
+        .get(offset..offset + 32)
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    Ok(Pubkey::new_from_array(bytes.try_into().unwrap()))
+}
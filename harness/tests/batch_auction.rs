@@ -0,0 +1,696 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token::spl_token;
+use anon_presale::batch::{MAX_BATCH_ORDERS, SETTLE_WINDOW};
+use anon_presale::{BatchAuction, BatchOrder, BatchSettled, DarkPool, DarkPoolError};
+use common::*;
+use dara_client::{args, batch_order_hash, instructions, pda, Market};
+use dara_harness::{assert_error, Failure, Svm};
+
+const SELL: u8 = 0;
+const BUY: u8 = 1;
+const COMMIT_WINDOW: i64 = 600;
+const REVEAL_WINDOW: i64 = 600;
+
+struct Batch {
+    market: Market,
+    authority: Pubkey,
+    address: Pubkey,
+    commit_end: i64,
+    reveal_end: i64,
+}
+
+/// A participant's hidden order and the wallet that placed it.
+#[derive(Clone)]
+struct Order {
+    owner: Pubkey,
+    nonce: [u8; 32],
+    side: u8,
+    price: u64,
+    size: u64,
+}
+
+impl Order {
+    fn hash(&self) -> [u8; 32] {
+        batch_order_hash(&self.nonce, self.side, self.price, self.size, &self.owner)
+    }
+
+    fn address(&self, batch: &Batch) -> Pubkey {
+        pda::batch_order(&batch.address, &self.hash()).0
+    }
+}
+
+fn open_batch(svm: &mut Svm) -> Batch {
+    let (market, authority) = create_market(svm, &spl_token::ID);
+    next_batch(svm, market, authority)
+}
+
+fn next_batch(svm: &mut Svm, market: Market, authority: Pubkey) -> Batch {
+    let pool: DarkPool = svm.get(&market.dark_pool());
+    let commit_end = svm.now() + COMMIT_WINDOW;
+    let reveal_end = commit_end + REVEAL_WINDOW;
+    let open = instructions::open_batch(
+        &authority,
+        &market,
+        pool.batch_count,
+        args::OpenBatch {
+            commit_end,
+            reveal_end,
+        },
+    );
+    svm.process(&open).unwrap();
+    Batch {
+        address: pda::batch(&market.dark_pool(), pool.batch_count).0,
+        market,
+        authority,
+        commit_end,
+        reveal_end,
+    }
+}
+
+fn commit(
+    svm: &mut Svm,
+    batch: &Batch,
+    order: &Order,
+    escrow_base: u64,
+    escrow_quote: u64,
+) -> Result<(), Failure> {
+    let commit = instructions::commit_batch_order(
+        &order.owner,
+        &batch.market,
+        &batch.address,
+        args::CommitBatchOrder {
+            order_hash: order.hash(),
+            escrow_base,
+            escrow_quote,
+        },
+    );
+    svm.process(&commit)
+}
+
+fn reveal(svm: &mut Svm, batch: &Batch, order: &Order) -> Result<(), Failure> {
+    let reveal = instructions::reveal_batch_order(
+        &order.owner,
+        &batch.market,
+        &batch.address,
+        args::RevealBatchOrder {
+            nonce: order.nonce,
+            side: order.side,
+            price: order.price,
+            size: order.size,
+        },
+    );
+    svm.process(&reveal)
+}
+
+fn settle(svm: &mut Svm, batch: &Batch, revealed: &[&Order]) -> Result<(), Failure> {
+    let cranker = svm.new_wallet(SOL);
+    let revealed: Vec<Pubkey> = revealed.iter().map(|order| order.address(batch)).collect();
+    svm.process(&instructions::settle_batch(
+        &cranker,
+        &batch.market,
+        &batch.address,
+        &revealed,
+    ))
+}
+
+fn claim(svm: &mut Svm, batch: &Batch, order: &Order) -> Result<(), Failure> {
+    let cranker = svm.new_wallet(SOL);
+    let claim = instructions::claim_batch_order(
+        &cranker,
+        &batch.market,
+        &batch.address,
+        &order.owner,
+        &order.hash(),
+    );
+    svm.process(&claim)
+}
+
+fn order(svm: &mut Svm, batch: &Batch, seed: u8, side: u8, price: u64, size: u64) -> Order {
+    let owner = trader(svm, &batch.market, 100 * UNIT, 100 * UNIT);
+    Order {
+        owner,
+        nonce: [seed; 32],
+        side,
+        price,
+        size,
+    }
+}
+
+#[test]
+fn batch_clears_at_a_uniform_price() {
+    let mut svm = setup();
+    let batch = open_batch(&mut svm);
+    let cheap = order(&mut svm, &batch, 1, SELL, 2 * UNIT, 10 * UNIT);
+    let dear = order(&mut svm, &batch, 2, SELL, 3 * UNIT, 10 * UNIT);
+    let buyer = order(&mut svm, &batch, 3, BUY, 3 * UNIT, 15 * UNIT);
+    let silent = order(&mut svm, &batch, 4, SELL, UNIT, 5 * UNIT);
+    commit(&mut svm, &batch, &cheap, 10 * UNIT, 0).unwrap();
+    commit(&mut svm, &batch, &dear, 10 * UNIT, 0).unwrap();
+    // Over-escrowing in both mints hides the side and size
+    commit(&mut svm, &batch, &buyer, 5 * UNIT, 50 * UNIT).unwrap();
+    commit(&mut svm, &batch, &silent, 5 * UNIT, 0).unwrap();
+    let pool: DarkPool = svm.get(&batch.market.dark_pool());
+    assert_eq!(pool.open_order_count, 4);
+
+    svm.warp_to(batch.commit_end);
+    for order in [&cheap, &dear, &buyer] {
+        reveal(&mut svm, &batch, order).unwrap();
+    }
+    svm.warp_to(batch.reveal_end);
+    settle(&mut svm, &batch, &[&cheap, &dear, &buyer]).unwrap();
+
+    // 15 bid against 20 offered at 3.0; sellers split 15 pro rata, the remainder going first
+    let settled = svm.events::<BatchSettled>();
+    assert_eq!(settled[0].clearing_price, 3 * UNIT);
+    assert_eq!(settled[0].clearing_volume, 15 * UNIT);
+    assert_eq!(settled[0].volume_quote, 45 * UNIT);
+    let fills: Vec<(u64, u64)> = [&cheap, &dear, &buyer, &silent]
+        .iter()
+        .map(|order| {
+            let order: BatchOrder = svm.get(&order.address(&batch));
+            (order.fill_base, order.fill_quote)
+        })
+        .collect();
+    assert_eq!(
+        fills,
+        [
+            (7_500_000, 22_500_000),
+            (7_500_000, 22_500_000),
+            (15 * UNIT, 45 * UNIT),
+            (0, 0)
+        ]
+    );
+
+    for order in [&cheap, &dear, &buyer, &silent] {
+        claim(&mut svm, &batch, order).unwrap();
+        assert!(svm.account(&order.address(&batch)).is_none());
+    }
+    let balances = |svm: &Svm, order: &Order| {
+        (
+            svm.token_balance(&batch.market.base_account(&order.owner)),
+            svm.token_balance(&batch.market.quote_account(&order.owner)),
+        )
+    };
+    assert_eq!(balances(&svm, &cheap), (92_500_000, 122_500_000));
+    assert_eq!(balances(&svm, &dear), (92_500_000, 122_500_000));
+    assert_eq!(balances(&svm, &buyer), (115 * UNIT, 55 * UNIT));
+    assert_eq!(balances(&svm, &silent), (100 * UNIT, 100 * UNIT));
+
+    // Everything is paid out, so the pool can be wound down
+    assert_eq!(
+        svm.token_balance(&pda::dp_base_vault(&batch.market.dark_pool()).0),
+        0
+    );
+    assert_eq!(
+        svm.token_balance(&pda::dp_quote_vault(&batch.market.dark_pool()).0),
+        0
+    );
+    let auction: BatchAuction = svm.get(&batch.address);
+    assert_eq!(auction.claimed_count, 4);
+    assert_error(
+        svm.process(&instructions::close_dark_pool(
+            &batch.authority,
+            &batch.market,
+        )),
+        DarkPoolError::OpenBatchesRemaining,
+    );
+    svm.process(&instructions::close_batch(
+        &batch.market,
+        &batch.authority,
+        &batch.address,
+    ))
+    .unwrap();
+    assert!(svm.account(&batch.address).is_none());
+    svm.process(&instructions::close_dark_pool(
+        &batch.authority,
+        &batch.market,
+    ))
+    .unwrap();
+
+    // A pool re-created on the same market starts again from batch 0
+    svm.process(&instructions::initialize_dark_pool(
+        &batch.authority,
+        &batch.market,
+    ))
+    .unwrap();
+    let again = next_batch(&mut svm, batch.market, batch.authority);
+    assert_eq!(again.address, batch.address);
+}
+
+#[test]
+fn batch_without_a_cross_refunds_everyone() {
+    let mut svm = setup();
+    let batch = open_batch(&mut svm);
+    let seller = order(&mut svm, &batch, 1, SELL, 5 * UNIT, 10 * UNIT);
+    let buyer = order(&mut svm, &batch, 2, BUY, 4 * UNIT, 10 * UNIT);
+    commit(&mut svm, &batch, &seller, 10 * UNIT, 0).unwrap();
+    commit(&mut svm, &batch, &buyer, 0, 40 * UNIT).unwrap();
+    svm.warp_to(batch.commit_end);
+    reveal(&mut svm, &batch, &seller).unwrap();
+    reveal(&mut svm, &batch, &buyer).unwrap();
+    svm.warp_to(batch.reveal_end);
+    settle(&mut svm, &batch, &[&seller, &buyer]).unwrap();
+
+    let auction: BatchAuction = svm.get(&batch.address);
+    assert!(auction.is_settled);
+    assert_eq!((auction.clearing_price, auction.clearing_volume), (0, 0));
+    let close = instructions::close_batch(&batch.market, &batch.authority, &batch.address);
+    assert_error(svm.process(&close), DarkPoolError::OpenOrdersRemaining);
+    for order in [&seller, &buyer] {
+        claim(&mut svm, &batch, order).unwrap();
+        assert_eq!(
+            svm.token_balance(&batch.market.base_account(&order.owner)),
+            100 * UNIT
+        );
+        assert_eq!(
+            svm.token_balance(&batch.market.quote_account(&order.owner)),
+            100 * UNIT
+        );
+    }
+}
+
+#[test]
+fn batch_fills_pay_protocol_fees() {
+    // 1% taker fee on buyers, 0.5% maker fee on sellers
+    let mut svm = setup_with_fees(0, 100, 50);
+    let batch = open_batch(&mut svm);
+    let seller = order(&mut svm, &batch, 1, SELL, 2 * UNIT, 10 * UNIT);
+    let buyer = order(&mut svm, &batch, 2, BUY, 2 * UNIT, 10 * UNIT);
+    let unfunded = order(&mut svm, &batch, 3, BUY, 2 * UNIT, 10 * UNIT);
+    commit(&mut svm, &batch, &seller, 10 * UNIT, 0).unwrap();
+    commit(&mut svm, &batch, &buyer, 0, 21 * UNIT).unwrap();
+    // Covers the 20 quote cost but not the fee on top of it
+    commit(&mut svm, &batch, &unfunded, 0, 20 * UNIT).unwrap();
+    svm.warp_to(batch.commit_end);
+    assert_error(
+        reveal(&mut svm, &batch, &unfunded),
+        DarkPoolError::InsufficientEscrow,
+    );
+    reveal(&mut svm, &batch, &seller).unwrap();
+    reveal(&mut svm, &batch, &buyer).unwrap();
+    svm.warp_to(batch.reveal_end);
+    settle(&mut svm, &batch, &[&seller, &buyer]).unwrap();
+
+    let settled = svm.events::<BatchSettled>();
+    assert_eq!(settled[0].volume_quote, 20 * UNIT);
+    assert_eq!(settled[0].total_fee, 300_000);
+    for order in [&seller, &buyer, &unfunded] {
+        claim(&mut svm, &batch, order).unwrap();
+    }
+    let fee_vault = pda::dp_fee_vault(&batch.market.dark_pool()).0;
+    assert_eq!(svm.token_balance(&fee_vault), 300_000);
+    assert_eq!(
+        svm.token_balance(&batch.market.quote_account(&seller.owner)),
+        120 * UNIT - 100_000
+    );
+    assert_eq!(
+        svm.token_balance(&batch.market.quote_account(&buyer.owner)),
+        80 * UNIT - 200_000
+    );
+    assert_eq!(
+        svm.token_balance(&batch.market.base_account(&buyer.owner)),
+        110 * UNIT
+    );
+    assert_eq!(
+        svm.token_balance(&pda::dp_quote_vault(&batch.market.dark_pool()).0),
+        0
+    );
+}
+
+#[test]
+fn full_batch_settles_in_one_transaction() {
+    let mut svm = setup();
+    let batch = open_batch(&mut svm);
+    let orders: Vec<Order> = (0..MAX_BATCH_ORDERS as u8)
+        .map(|seed| {
+            let side = if seed % 2 == 0 { SELL } else { BUY };
+            order(&mut svm, &batch, seed, side, 2 * UNIT, UNIT)
+        })
+        .collect();
+    for order in &orders {
+        commit(&mut svm, &batch, order, UNIT, 3 * UNIT).unwrap();
+    }
+    let extra = order(&mut svm, &batch, 0xff, SELL, 2 * UNIT, UNIT);
+    assert_error(
+        commit(&mut svm, &batch, &extra, UNIT, 0),
+        DarkPoolError::BatchFull,
+    );
+
+    svm.warp_to(batch.commit_end);
+    for order in &orders {
+        reveal(&mut svm, &batch, order).unwrap();
+    }
+    svm.warp_to(batch.reveal_end);
+    // The harness rejects any transaction over the legacy size limit
+    let revealed: Vec<&Order> = orders.iter().collect();
+    settle(&mut svm, &batch, &revealed).unwrap();
+
+    let settled = svm.events::<BatchSettled>();
+    assert_eq!(
+        settled[0].clearing_volume,
+        MAX_BATCH_ORDERS as u64 / 2 * UNIT
+    );
+}
+
+#[test]
+fn open_batch_validates_window_and_authority() {
+    let mut svm = setup();
+    let (market, authority) = create_market(&mut svm, &spl_token::ID);
+    let now = svm.now();
+    let open = |commit_end, reveal_end| {
+        instructions::open_batch(
+            &authority,
+            &market,
+            0,
+            args::OpenBatch {
+                commit_end,
+                reveal_end,
+            },
+        )
+    };
+
+    assert_error(
+        svm.process(&open(now, now + 10)),
+        DarkPoolError::InvalidBatchWindow,
+    );
+    assert_error(
+        svm.process(&open(now + 10, now + 10)),
+        DarkPoolError::InvalidBatchWindow,
+    );
+    let impostor = svm.new_wallet(SOL);
+    let ix = swap_account(open(now + 10, now + 20), &authority, &impostor);
+    assert_error(svm.process(&ix), DarkPoolError::Unauthorized);
+
+    svm.process(&open(now + 10, now + 20)).unwrap();
+    let second = next_batch(&mut svm, market, authority);
+    let auction: BatchAuction = svm.get(&second.address);
+    assert_eq!(auction.batch_id, 1);
+}
+
+#[test]
+fn commit_checks_window_capacity_and_escrow() {
+    let mut svm = setup();
+    let batch = open_batch(&mut svm);
+    let order = order(&mut svm, &batch, 1, SELL, UNIT, UNIT);
+
+    assert_error(
+        commit(&mut svm, &batch, &order, 0, 0),
+        DarkPoolError::InvalidOrderParams,
+    );
+
+    let mut auction: BatchAuction = svm.get(&batch.address);
+    auction.order_count = MAX_BATCH_ORDERS;
+    svm.set(&batch.address, &auction);
+    assert_error(
+        commit(&mut svm, &batch, &order, UNIT, 0),
+        DarkPoolError::BatchFull,
+    );
+    auction.order_count = 0;
+    svm.set(&batch.address, &auction);
+
+    svm.warp_to(batch.commit_end);
+    assert_error(
+        commit(&mut svm, &batch, &order, UNIT, 0),
+        DarkPoolError::BatchCommitClosed,
+    );
+}
+
+#[test]
+fn orders_must_stay_in_their_batch() {
+    let mut svm = setup();
+    let batch = open_batch(&mut svm);
+    let other = open_batch(&mut svm);
+    let order = order(&mut svm, &batch, 1, SELL, UNIT, UNIT);
+    commit(&mut svm, &batch, &order, UNIT, 0).unwrap();
+
+    // A batch belongs to one pool
+    let ix = instructions::commit_batch_order(
+        &order.owner,
+        &batch.market,
+        &other.address,
+        args::CommitBatchOrder {
+            order_hash: [9; 32],
+            escrow_base: UNIT,
+            escrow_quote: 0,
+        },
+    );
+    assert_error(svm.process(&ix), DarkPoolError::InvalidOrder);
+
+    // An order belongs to one batch
+    let second = next_batch(&mut svm, batch.market, batch.authority);
+    svm.warp_to(second.commit_end);
+    let ix = instructions::reveal_batch_order(
+        &order.owner,
+        &second.market,
+        &second.address,
+        args::RevealBatchOrder {
+            nonce: order.nonce,
+            side: order.side,
+            price: order.price,
+            size: order.size,
+        },
+    );
+    let ix = swap_account(ix, &order.address(&second), &order.address(&batch));
+    assert_error(svm.process(&ix), DarkPoolError::InvalidOrder);
+}
+
+#[test]
+fn reveal_checks_window_owner_and_terms() {
+    let mut svm = setup();
+    let batch = open_batch(&mut svm);
+    let seller = order(&mut svm, &batch, 1, SELL, 2 * UNIT, 10 * UNIT);
+    commit(&mut svm, &batch, &seller, 10 * UNIT, 0).unwrap();
+
+    assert_error(
+        reveal(&mut svm, &batch, &seller),
+        DarkPoolError::BatchNotInReveal,
+    );
+    svm.warp_to(batch.commit_end);
+
+    let impostor = trader(&mut svm, &batch.market, 0, 0);
+    let ix = instructions::reveal_batch_order(
+        &impostor,
+        &batch.market,
+        &batch.address,
+        args::RevealBatchOrder {
+            nonce: seller.nonce,
+            side: seller.side,
+            price: seller.price,
+            size: seller.size,
+        },
+    );
+    let forged = pda::batch_order(
+        &batch.address,
+        &batch_order_hash(
+            &seller.nonce,
+            seller.side,
+            seller.price,
+            seller.size,
+            &impostor,
+        ),
+    )
+    .0;
+    let ix = swap_account(ix, &forged, &seller.address(&batch));
+    assert_error(svm.process(&ix), DarkPoolError::Unauthorized);
+
+    // Different terms point at a different order, so aim them at the committed one
+    let lie = Order {
+        price: UNIT,
+        ..seller.clone()
+    };
+    let ix = instructions::reveal_batch_order(
+        &seller.owner,
+        &batch.market,
+        &batch.address,
+        args::RevealBatchOrder {
+            nonce: lie.nonce,
+            side: lie.side,
+            price: lie.price,
+            size: lie.size,
+        },
+    );
+    let ix = swap_account(ix, &lie.address(&batch), &seller.address(&batch));
+    assert_error(svm.process(&ix), DarkPoolError::InvalidOrderProof);
+
+    reveal(&mut svm, &batch, &seller).unwrap();
+    assert_error(
+        reveal(&mut svm, &batch, &seller),
+        DarkPoolError::AlreadyRevealed,
+    );
+
+    svm.warp_to(batch.reveal_end);
+    assert_error(
+        reveal(&mut svm, &batch, &seller),
+        DarkPoolError::BatchNotInReveal,
+    );
+}
+
+#[test]
+fn reveal_rejects_orders_the_escrow_cannot_cover() {
+    let mut svm = setup();
+    let batch = open_batch(&mut svm);
+    let zero_price = order(&mut svm, &batch, 1, SELL, 0, UNIT);
+    let zero_size = order(&mut svm, &batch, 2, BUY, UNIT, 0);
+    let bad_side = order(&mut svm, &batch, 3, 2, UNIT, UNIT);
+    let short_seller = order(&mut svm, &batch, 4, SELL, UNIT, 10 * UNIT);
+    // Buying 10 at 2.0 costs 20 quote
+    let short_buyer = order(&mut svm, &batch, 5, BUY, 2 * UNIT, 10 * UNIT);
+    for order in [&zero_price, &zero_size, &bad_side] {
+        commit(&mut svm, &batch, order, UNIT, UNIT).unwrap();
+    }
+    commit(&mut svm, &batch, &short_seller, 10 * UNIT - 1, 100 * UNIT).unwrap();
+    commit(&mut svm, &batch, &short_buyer, 100 * UNIT, 20 * UNIT - 1).unwrap();
+    svm.warp_to(batch.commit_end);
+
+    assert_error(
+        reveal(&mut svm, &batch, &zero_price),
+        DarkPoolError::InvalidOrderParams,
+    );
+    assert_error(
+        reveal(&mut svm, &batch, &zero_size),
+        DarkPoolError::InvalidOrderParams,
+    );
+    assert_error(
+        reveal(&mut svm, &batch, &bad_side),
+        DarkPoolError::InvalidSide,
+    );
+    assert_error(
+        reveal(&mut svm, &batch, &short_seller),
+        DarkPoolError::InsufficientEscrow,
+    );
+    assert_error(
+        reveal(&mut svm, &batch, &short_buyer),
+        DarkPoolError::InsufficientEscrow,
+    );
+}
+
+#[test]
+fn settle_requires_every_revealed_order_after_the_window() {
+    let mut svm = setup();
+    let batch = open_batch(&mut svm);
+    let seller = order(&mut svm, &batch, 1, SELL, UNIT, UNIT);
+    let buyer = order(&mut svm, &batch, 2, BUY, UNIT, UNIT);
+    commit(&mut svm, &batch, &seller, UNIT, 0).unwrap();
+    commit(&mut svm, &batch, &buyer, 0, UNIT).unwrap();
+    svm.warp_to(batch.commit_end);
+    reveal(&mut svm, &batch, &seller).unwrap();
+    reveal(&mut svm, &batch, &buyer).unwrap();
+
+    assert_error(
+        settle(&mut svm, &batch, &[&seller, &buyer]),
+        DarkPoolError::BatchRevealOpen,
+    );
+    assert_error(
+        claim(&mut svm, &batch, &seller),
+        DarkPoolError::BatchNotSettled,
+    );
+
+    svm.warp_to(batch.reveal_end);
+    assert_error(
+        settle(&mut svm, &batch, &[&seller]),
+        DarkPoolError::IncompleteBatch,
+    );
+    assert_error(
+        settle(&mut svm, &batch, &[&buyer, &seller]),
+        DarkPoolError::IncompleteBatch,
+    );
+    settle(&mut svm, &batch, &[&seller, &buyer]).unwrap();
+    assert_error(
+        settle(&mut svm, &batch, &[&seller, &buyer]),
+        DarkPoolError::BatchAlreadySettled,
+    );
+
+    let impostor = trader(&mut svm, &batch.market, 0, 0);
+    let cranker = svm.new_wallet(SOL);
+    let ix = instructions::claim_batch_order(
+        &cranker,
+        &batch.market,
+        &batch.address,
+        &seller.owner,
+        &seller.hash(),
+    );
+    assert_error(
+        svm.process(&swap_account(ix, &seller.owner, &impostor)),
+        DarkPoolError::InvalidMaker,
+    );
+    claim(&mut svm, &batch, &seller).unwrap();
+    assert_eq!(
+        svm.token_balance(&batch.market.quote_account(&seller.owner)),
+        101 * UNIT
+    );
+}
+
+#[test]
+fn bids_beyond_u64_still_clear() {
+    let mut svm = setup();
+    let batch = open_batch(&mut svm);
+    // At a price of one quote unit per whole token, u64::MAX base units cost ~1.8e13 quote
+    let seller = order(&mut svm, &batch, 1, SELL, 1, 10 * UNIT);
+    let buyers = [2, 3].map(|seed| order(&mut svm, &batch, seed, BUY, 1, u64::MAX));
+    let escrow = u64::MAX / UNIT + 1;
+    commit(&mut svm, &batch, &seller, 10 * UNIT, 0).unwrap();
+    for buyer in &buyers {
+        let account = batch.market.quote_account(&buyer.owner);
+        svm.mint_to(&batch.market.quote_mint, &account, escrow);
+        commit(&mut svm, &batch, buyer, 0, escrow).unwrap();
+    }
+    svm.warp_to(batch.commit_end);
+    for order in [&seller, &buyers[0], &buyers[1]] {
+        reveal(&mut svm, &batch, order).unwrap();
+    }
+    svm.warp_to(batch.reveal_end);
+
+    // The bid volume overflows u64, which used to make the batch impossible to settle
+    settle(&mut svm, &batch, &[&seller, &buyers[0], &buyers[1]]).unwrap();
+    let auction: BatchAuction = svm.get(&batch.address);
+    assert_eq!(auction.clearing_volume, 10 * UNIT);
+    for buyer in &buyers {
+        let order: BatchOrder = svm.get(&buyer.address(&batch));
+        assert_eq!((order.fill_base, order.fill_quote), (5 * UNIT, 5));
+    }
+}
+
+#[test]
+fn unsettled_batch_refunds_after_its_settlement_window() {
+    let mut svm = setup();
+    let batch = open_batch(&mut svm);
+    let seller = order(&mut svm, &batch, 1, SELL, UNIT, UNIT);
+    let buyer = order(&mut svm, &batch, 2, BUY, UNIT, UNIT);
+    commit(&mut svm, &batch, &seller, UNIT, 0).unwrap();
+    commit(&mut svm, &batch, &buyer, 0, 2 * UNIT).unwrap();
+    svm.warp_to(batch.commit_end);
+    reveal(&mut svm, &batch, &seller).unwrap();
+    reveal(&mut svm, &batch, &buyer).unwrap();
+
+    svm.warp_to(batch.reveal_end + SETTLE_WINDOW - 1);
+    assert_error(
+        claim(&mut svm, &batch, &buyer),
+        DarkPoolError::BatchNotSettled,
+    );
+
+    // Nobody settled in time: settling is over and every order gets its escrow back
+    svm.warp_to(batch.reveal_end + SETTLE_WINDOW);
+    assert_error(
+        settle(&mut svm, &batch, &[&seller, &buyer]),
+        DarkPoolError::BatchAbandoned,
+    );
+    for order in [&seller, &buyer] {
+        claim(&mut svm, &batch, order).unwrap();
+        assert_eq!(
+            svm.token_balance(&batch.market.base_account(&order.owner)),
+            100 * UNIT
+        );
+        assert_eq!(
+            svm.token_balance(&batch.market.quote_account(&order.owner)),
+            100 * UNIT
+        );
+    }
+    svm.process(&instructions::close_batch(
+        &batch.market,
+        &batch.authority,
+        &batch.address,
+    ))
+    .unwrap();
+}
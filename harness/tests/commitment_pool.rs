@@ -0,0 +1,624 @@
+mod common;
+
+use anchor_lang::prelude::{ProgramError, Pubkey};
+use anon_presale::merkle::{self, TREE_DEPTH};
+use anon_presale::{
+    ClaimVerifyingKey, CommitmentTree, Nullifier, PoolCommitmentAdded, Presale, PresaleError,
+    ProtocolError,
+};
+use ark_std::rand::rngs::StdRng;
+use ark_std::rand::{RngCore, SeedableRng};
+use common::*;
+use dara_client::{args, instructions, pda, PresaleKeys};
+use dara_harness::{assert_error, Failure, Svm};
+use dara_prover::{prove, verifying_key_data, ClaimWitness, MerkleTree};
+
+/// The private half of a pooled commitment.
+struct Note {
+    nullifier: [u8; 32],
+    secret: [u8; 32],
+    wallet: Pubkey,
+}
+
+impl Note {
+    /// Random 31-byte scalars, always below the field modulus.
+    fn new(rng: &mut StdRng) -> Self {
+        let mut scalar = || {
+            let mut bytes = [0u8; 32];
+            rng.fill_bytes(&mut bytes[1..]);
+            bytes
+        };
+        Self {
+            nullifier: scalar(),
+            secret: scalar(),
+            wallet: Pubkey::new_unique(),
+        }
+    }
+
+    fn leaf(&self) -> [u8; 32] {
+        merkle::leaf_hash(&self.nullifier, &self.secret, &self.wallet).unwrap()
+    }
+
+    fn nullifier_hash(&self) -> [u8; 32] {
+        merkle::nullifier_hash(&self.nullifier).unwrap()
+    }
+}
+
+/// A denominated presale with its commitment tree and an off-chain mirror of it.
+struct Pool {
+    keys: PresaleKeys,
+    tree: MerkleTree,
+}
+
+impl Pool {
+    fn new(svm: &mut Svm) -> Self {
+        Self::with_claim_window(svm, 0)
+    }
+
+    fn with_claim_window(svm: &mut Svm, claim_window: i64) -> Self {
+        let args = args::InitializePresale {
+            denomination: SOL,
+            claim_window,
+            ..presale_args(svm)
+        };
+        let keys = create_presale(svm, args);
+        svm.process(&instructions::initialize_commitment_tree(&keys))
+            .unwrap();
+        open(svm, &keys);
+        Self {
+            keys,
+            tree: MerkleTree::new(),
+        }
+    }
+
+    fn commit(&mut self, svm: &mut Svm, note: &Note) -> Result<(), Failure> {
+        let participant = svm.new_wallet(2 * SOL);
+        let commit = instructions::commit_to_pool(
+            &participant,
+            &self.keys.address(),
+            args::CommitToPool {
+                leaf: note.leaf(),
+                whitelist_nullifier_hash: [0; 32],
+                whitelist_proof: None,
+            },
+        );
+        svm.process(&commit)?;
+        self.tree.insert(note.leaf());
+        Ok(())
+    }
+
+    fn claim_args(&self, note: &Note) -> args::ClaimFromPool {
+        let leaf_index = self.leaf_index(note);
+        args::ClaimFromPool {
+            nullifier_hash: note.nullifier_hash(),
+            nullifier: note.nullifier,
+            secret: note.secret,
+            leaf_index,
+            path: self.tree.path(leaf_index).unwrap(),
+        }
+    }
+
+    fn claim(
+        &self,
+        svm: &mut Svm,
+        args: args::ClaimFromPool,
+        wallet: &Pubkey,
+    ) -> Result<(), Failure> {
+        let claimer = svm.new_wallet(SOL);
+        svm.process(&instructions::claim_from_pool(
+            &claimer, &self.keys, wallet, args,
+        ))
+    }
+
+    fn refund(
+        &self,
+        svm: &mut Svm,
+        args: args::ClaimFromPool,
+        wallet: &Pubkey,
+    ) -> Result<(), Failure> {
+        let claimer = svm.new_wallet(SOL);
+        let refund = instructions::refund_from_pool(
+            &claimer,
+            &self.keys.address(),
+            wallet,
+            args::RefundFromPool {
+                nullifier_hash: args.nullifier_hash,
+                nullifier: args.nullifier,
+                secret: args.secret,
+                leaf_index: args.leaf_index,
+                path: args.path,
+            },
+        );
+        svm.process(&refund)
+    }
+
+    fn claim_zk(&self, svm: &mut Svm, note: &Note, wallet: &Pubkey) -> Result<(), Failure> {
+        let leaf_index = self.leaf_index(note);
+        let witness = ClaimWitness {
+            nullifier: note.nullifier,
+            secret: note.secret,
+            claim_wallet: note.wallet,
+            leaf_index,
+            path: self.tree.path(leaf_index).unwrap(),
+            root: self.tree.root().unwrap(),
+        };
+        let proof = prove(&dev_keys().0, &witness, &mut StdRng::seed_from_u64(7)).unwrap();
+        let claimer = svm.new_wallet(SOL);
+        let claim = instructions::claim_tokens_zk(
+            &claimer,
+            &self.keys,
+            wallet,
+            args::ClaimTokensZk {
+                nullifier_hash: proof.nullifier_hash,
+                proof: proof.proof,
+            },
+        );
+        svm.process(&claim)
+    }
+
+    fn leaf_index(&self, note: &Note) -> u32 {
+        let leaf = note.leaf();
+        self.tree.leaves().iter().position(|l| *l == leaf).unwrap() as u32
+    }
+
+    fn finalize(&self, svm: &mut Svm) {
+        end(svm, &self.keys);
+        svm.process(&instructions::finalize_presale(&self.keys))
+            .unwrap();
+    }
+}
+
+#[test]
+fn tree_requires_an_open_denominated_presale() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = create_presale(&mut svm, args);
+    assert_error(
+        svm.process(&instructions::initialize_commitment_tree(&keys)),
+        PresaleError::PoolRequiresDenomination,
+    );
+
+    let args = args::InitializePresale {
+        denomination: SOL,
+        ..presale_args(&svm)
+    };
+    let keys = create_presale(&mut svm, args);
+    let impostor = svm.new_wallet(SOL);
+    let as_impostor = swap_account(
+        instructions::initialize_commitment_tree(&keys),
+        &keys.creator,
+        &impostor,
+    );
+    assert_error(svm.process(&as_impostor), PresaleError::Unauthorized);
+    svm.process(&instructions::cancel_presale(&keys)).unwrap();
+    assert_error(
+        svm.process(&instructions::initialize_commitment_tree(&keys)),
+        PresaleError::Cancelled,
+    );
+
+    let args = args::InitializePresale {
+        denomination: 10 * SOL,
+        ..presale_args(&svm)
+    };
+    let keys = create_presale(&mut svm, args);
+    open(&mut svm, &keys);
+    commit(&mut svm, &keys, [1; 32], &Pubkey::new_unique(), 10 * SOL).unwrap();
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+    assert_error(
+        svm.process(&instructions::initialize_commitment_tree(&keys)),
+        PresaleError::AlreadyFinalized,
+    );
+}
+
+#[test]
+fn commits_track_the_off_chain_tree() {
+    let mut svm = setup();
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut pool = Pool::new(&mut svm);
+    let before = svm.lamports(&pool.keys.address());
+
+    for _ in 0..3 {
+        pool.commit(&mut svm, &Note::new(&mut rng)).unwrap();
+    }
+
+    let tree: CommitmentTree = svm.get(&pda::commitment_tree(&pool.keys.address()).0);
+    assert_eq!(tree.next_index, 3);
+    assert_eq!(tree.root, pool.tree.root().unwrap());
+    let events = svm.events::<PoolCommitmentAdded>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].leaf_index, 2);
+    assert_eq!(events[0].root, tree.root);
+
+    let presale: Presale = svm.get(&pool.keys.address());
+    assert_eq!(presale.total_sol_committed, 3 * SOL);
+    assert_eq!(svm.lamports(&pool.keys.address()), before + 3 * SOL);
+}
+
+#[test]
+fn commit_to_a_full_tree() {
+    let mut svm = setup();
+    let mut rng = StdRng::seed_from_u64(2);
+    let mut pool = Pool::new(&mut svm);
+
+    let address = pda::commitment_tree(&pool.keys.address()).0;
+    let mut tree: CommitmentTree = svm.get(&address);
+    tree.next_index = 1 << TREE_DEPTH;
+    svm.set(&address, &tree);
+    assert_error(
+        pool.commit(&mut svm, &Note::new(&mut rng)),
+        PresaleError::TreeFull,
+    );
+}
+
+#[test]
+fn pool_commits_follow_the_presale_rules() {
+    let mut svm = setup();
+    let mut rng = StdRng::seed_from_u64(3);
+    let args = args::InitializePresale {
+        denomination: SOL,
+        ..presale_args(&svm)
+    };
+    let keys = create_presale(&mut svm, args);
+    svm.process(&instructions::initialize_commitment_tree(&keys))
+        .unwrap();
+    let mut pool = Pool {
+        keys,
+        tree: MerkleTree::new(),
+    };
+
+    assert_error(
+        pool.commit(&mut svm, &Note::new(&mut rng)),
+        PresaleError::NotStarted,
+    );
+    end(&mut svm, &pool.keys);
+    assert_error(
+        pool.commit(&mut svm, &Note::new(&mut rng)),
+        PresaleError::Ended,
+    );
+}
+
+#[test]
+fn claim_from_pool_pays_one_unit() {
+    let mut svm = setup();
+    let mut rng = StdRng::seed_from_u64(4);
+    let mut pool = Pool::new(&mut svm);
+    let notes: Vec<Note> = (0..4).map(|_| Note::new(&mut rng)).collect();
+    for note in &notes {
+        pool.commit(&mut svm, note).unwrap();
+    }
+
+    assert_error(
+        pool.claim(&mut svm, pool.claim_args(&notes[1]), &notes[1].wallet),
+        PresaleError::NotFinalized,
+    );
+    pool.finalize(&mut svm);
+
+    pool.claim(&mut svm, pool.claim_args(&notes[1]), &notes[1].wallet)
+        .unwrap();
+    let account = pool.keys.token_account(&notes[1].wallet);
+    // One unit of a 10-unit hard cap, whatever the pool raised
+    assert_eq!(svm.token_balance(&account), TOKENS_FOR_SALE / 10);
+
+    let spent: Nullifier =
+        svm.get(&pda::nullifier(&pool.keys.address(), &notes[1].nullifier_hash()).0);
+    assert!(spent.is_settled);
+    assert_error(
+        pool.claim(&mut svm, pool.claim_args(&notes[1]), &notes[1].wallet),
+        PresaleError::AlreadyClaimed,
+    );
+}
+
+#[test]
+fn claim_from_pool_checks_the_preimage() {
+    let mut svm = setup();
+    let mut rng = StdRng::seed_from_u64(5);
+    let mut pool = Pool::new(&mut svm);
+    let notes: Vec<Note> = (0..2).map(|_| Note::new(&mut rng)).collect();
+    for note in &notes {
+        pool.commit(&mut svm, note).unwrap();
+    }
+    pool.finalize(&mut svm);
+    let note = &notes[0];
+
+    let args = args::ClaimFromPool {
+        nullifier: notes[1].nullifier,
+        ..pool.claim_args(note)
+    };
+    assert_error(
+        pool.claim(&mut svm, args, &note.wallet),
+        PresaleError::InvalidNullifier,
+    );
+
+    // A valid path for a different wallet
+    assert_error(
+        pool.claim(&mut svm, pool.claim_args(note), &Pubkey::new_unique()),
+        PresaleError::InvalidMerkleProof,
+    );
+    let args = args::ClaimFromPool {
+        leaf_index: 1,
+        ..pool.claim_args(note)
+    };
+    assert_error(
+        pool.claim(&mut svm, args, &note.wallet),
+        PresaleError::InvalidMerkleProof,
+    );
+    let args = args::ClaimFromPool {
+        path: vec![[0; 32]; TREE_DEPTH - 1],
+        ..pool.claim_args(note)
+    };
+    assert_error(
+        pool.claim(&mut svm, args, &note.wallet),
+        PresaleError::InvalidMerkleProof,
+    );
+
+    let args = args::ClaimFromPool {
+        nullifier: [0xff; 32],
+        ..pool.claim_args(note)
+    };
+    assert_error(
+        pool.claim(&mut svm, args, &note.wallet),
+        PresaleError::InvalidFieldElement,
+    );
+}
+
+#[test]
+fn claim_tokens_zk_needs_an_installed_verifying_key() {
+    let mut svm = setup();
+    let mut rng = StdRng::seed_from_u64(8);
+    let mut pool = Pool::new(&mut svm);
+    let note = Note::new(&mut rng);
+    pool.commit(&mut svm, &note).unwrap();
+    pool.commit(&mut svm, &Note::new(&mut rng)).unwrap();
+    pool.finalize(&mut svm);
+
+    assert_error(
+        pool.claim_zk(&mut svm, &note, &note.wallet),
+        PresaleError::ZkClaimsDisabled,
+    );
+
+    let admin = svm.upgrade_authority();
+    let stranger = svm.new_wallet(SOL);
+    assert_error(
+        set_claim_verifying_key(&mut svm, &stranger),
+        ProtocolError::Unauthorized,
+    );
+    let mut key = verifying_key_data(&dev_keys().1);
+    key.ic[1][63] ^= 1;
+    assert_error(
+        svm.process(&instructions::set_claim_verifying_key(
+            &admin,
+            args::SetClaimVerifyingKey { key },
+        )),
+        ProtocolError::InvalidVerifyingKey,
+    );
+
+    set_claim_verifying_key(&mut svm, &admin).unwrap();
+    let installed: ClaimVerifyingKey = svm.get(&pda::claim_verifying_key().0);
+    assert_eq!(
+        installed.key.alpha_g1,
+        verifying_key_data(&dev_keys().1).alpha_g1
+    );
+    // Once set, the key cannot be swapped for one whose toxic waste someone knows
+    assert_error(
+        set_claim_verifying_key(&mut svm, &admin),
+        ProtocolError::VerifyingKeyAlreadySet,
+    );
+
+    pool.claim_zk(&mut svm, &note, &note.wallet).unwrap();
+}
+
+#[test]
+fn claim_tokens_zk_pays_without_revealing_the_leaf() {
+    let mut svm = setup();
+    let admin = svm.upgrade_authority();
+    set_claim_verifying_key(&mut svm, &admin).unwrap();
+    let mut rng = StdRng::seed_from_u64(6);
+    let mut pool = Pool::new(&mut svm);
+    let notes: Vec<Note> = (0..2).map(|_| Note::new(&mut rng)).collect();
+    for note in &notes {
+        pool.commit(&mut svm, note).unwrap();
+    }
+
+    let note = &notes[0];
+    assert_error(
+        pool.claim_zk(&mut svm, note, &note.wallet),
+        PresaleError::NotFinalized,
+    );
+    pool.finalize(&mut svm);
+
+    // The proof binds the wallet
+    assert_error(
+        pool.claim_zk(&mut svm, note, &Pubkey::new_unique()),
+        PresaleError::InvalidZkProof,
+    );
+    pool.claim_zk(&mut svm, note, &note.wallet).unwrap();
+    assert_eq!(
+        svm.token_balance(&pool.keys.token_account(&note.wallet)),
+        TOKENS_FOR_SALE / 10
+    );
+
+    // The same nullifier cannot be spent again through either path
+    assert_error(
+        pool.claim_zk(&mut svm, note, &note.wallet),
+        PresaleError::AlreadyClaimed,
+    );
+    assert_error(
+        pool.claim(&mut svm, pool.claim_args(note), &note.wallet),
+        PresaleError::AlreadyClaimed,
+    );
+}
+
+#[test]
+fn claim_tokens_zk_rejects_non_canonical_inputs() {
+    let mut svm = setup();
+    let admin = svm.upgrade_authority();
+    set_claim_verifying_key(&mut svm, &admin).unwrap();
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut pool = Pool::new(&mut svm);
+    let note = Note::new(&mut rng);
+    for _ in 0..2 {
+        pool.commit(&mut svm, &Note::new(&mut rng)).unwrap();
+    }
+    pool.commit(&mut svm, &note).unwrap();
+    pool.finalize(&mut svm);
+
+    let claimer = svm.new_wallet(SOL);
+    let claim = instructions::claim_tokens_zk(
+        &claimer,
+        &pool.keys,
+        &note.wallet,
+        args::ClaimTokensZk {
+            nullifier_hash: [0xff; 32],
+            proof: dara_client::Groth16Proof {
+                a: [0; 64],
+                b: [0; 128],
+                c: [0; 64],
+            },
+        },
+    );
+    assert_error(svm.process(&claim), PresaleError::InvalidFieldElement);
+}
+
+#[test]
+fn unclaimed_leaves_lapse_with_the_claim_window() {
+    let mut svm = setup();
+    let admin = svm.upgrade_authority();
+    set_claim_verifying_key(&mut svm, &admin).unwrap();
+    let mut rng = StdRng::seed_from_u64(11);
+    let mut pool = Pool::with_claim_window(&mut svm, 1_000);
+    let notes: Vec<Note> = (0..2).map(|_| Note::new(&mut rng)).collect();
+    for note in &notes {
+        pool.commit(&mut svm, note).unwrap();
+    }
+    pool.finalize(&mut svm);
+    pool.claim(&mut svm, pool.claim_args(&notes[0]), &notes[0].wallet)
+        .unwrap();
+    let presale: Presale = svm.get(&pool.keys.address());
+    assert_eq!(presale.open_pool_leaves, 1);
+    assert_error(
+        svm.process(&instructions::withdraw_remaining_tokens(&pool.keys)),
+        PresaleError::ClaimsOutstanding,
+    );
+
+    svm.warp_to(presale.finalized_at + 1_001);
+    assert_error(
+        pool.claim(&mut svm, pool.claim_args(&notes[1]), &notes[1].wallet),
+        PresaleError::ClaimWindowClosed,
+    );
+    assert_error(
+        pool.claim_zk(&mut svm, &notes[1], &notes[1].wallet),
+        PresaleError::ClaimWindowClosed,
+    );
+
+    // The leaf has no account to settle, so it goes with the tree
+    svm.process(&instructions::withdraw_remaining_tokens(&pool.keys))
+        .unwrap();
+    svm.process(&instructions::close_presale(&pool.keys))
+        .unwrap();
+    assert!(svm.account(&pool.keys.address()).is_none());
+    assert!(svm
+        .account(&pda::commitment_tree(&pool.keys.address()).0)
+        .is_none());
+}
+
+#[test]
+fn failed_pool_refunds_once_per_leaf() {
+    let mut svm = setup();
+    let mut rng = StdRng::seed_from_u64(8);
+    let mut pool = Pool::new(&mut svm);
+    let (note, other) = (Note::new(&mut rng), Note::new(&mut rng));
+    pool.commit(&mut svm, &note).unwrap();
+
+    assert_error(
+        pool.refund(&mut svm, pool.claim_args(&note), &note.wallet),
+        PresaleError::PresaleStillActive,
+    );
+    end(&mut svm, &pool.keys);
+
+    let args = args::ClaimFromPool {
+        nullifier: other.nullifier,
+        ..pool.claim_args(&note)
+    };
+    assert_error(
+        pool.refund(&mut svm, args, &note.wallet),
+        PresaleError::InvalidNullifier,
+    );
+    assert_error(
+        pool.refund(&mut svm, pool.claim_args(&note), &other.wallet),
+        PresaleError::InvalidMerkleProof,
+    );
+
+    let before = svm.lamports(&note.wallet);
+    pool.refund(&mut svm, pool.claim_args(&note), &note.wallet)
+        .unwrap();
+    assert_eq!(svm.lamports(&note.wallet), before + SOL);
+    let presale: Presale = svm.get(&pool.keys.address());
+    assert_eq!(presale.settled_count, presale.commitment_count);
+
+    // The nullifier already exists
+    assert_error(
+        pool.refund(&mut svm, pool.claim_args(&note), &note.wallet),
+        ProgramError::Custom(0),
+    );
+}
+
+#[test]
+fn pool_refunds_stop_at_the_soft_cap() {
+    let mut svm = setup();
+    let mut rng = StdRng::seed_from_u64(9);
+    let mut pool = Pool::new(&mut svm);
+    let notes: Vec<Note> = (0..2).map(|_| Note::new(&mut rng)).collect();
+    for note in &notes {
+        pool.commit(&mut svm, note).unwrap();
+    }
+    end(&mut svm, &pool.keys);
+
+    let note = &notes[0];
+    assert_error(
+        pool.refund(&mut svm, pool.claim_args(note), &note.wallet),
+        PresaleError::SoftCapReached,
+    );
+    svm.process(&instructions::finalize_presale(&pool.keys))
+        .unwrap();
+    assert_error(
+        pool.refund(&mut svm, pool.claim_args(note), &note.wallet),
+        PresaleError::AlreadyFinalized,
+    );
+}
+
+#[test]
+fn cancelled_pool_refunds_at_any_time() {
+    let mut svm = setup();
+    let mut rng = StdRng::seed_from_u64(10);
+    let mut pool = Pool::new(&mut svm);
+    let notes: Vec<Note> = (0..3).map(|_| Note::new(&mut rng)).collect();
+    for note in &notes {
+        pool.commit(&mut svm, note).unwrap();
+    }
+    svm.process(&instructions::cancel_presale(&pool.keys))
+        .unwrap();
+
+    for note in &notes {
+        pool.refund(&mut svm, pool.claim_args(note), &note.wallet)
+            .unwrap();
+        assert_eq!(svm.lamports(&note.wallet), SOL);
+    }
+    svm.process(&instructions::withdraw_remaining_tokens(&pool.keys))
+        .unwrap();
+    let creator_before = svm.lamports(&pool.keys.creator);
+    svm.process(&instructions::close_presale(&pool.keys))
+        .unwrap();
+
+    // The tree closes with the presale, so a re-created presale starts from an empty one
+    let tree = pda::commitment_tree(&pool.keys.address()).0;
+    assert!(svm.account(&tree).is_none());
+    assert!(svm.lamports(&pool.keys.creator) > creator_before);
+    let args = args::InitializePresale {
+        denomination: SOL,
+        ..presale_args(&svm)
+    };
+    svm.process(&instructions::initialize_presale(&pool.keys, args))
+        .unwrap();
+    svm.process(&instructions::initialize_commitment_tree(&pool.keys))
+        .unwrap();
+}
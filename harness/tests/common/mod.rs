@@ -0,0 +1,212 @@
+//! Fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::sync::OnceLock;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::native_token::LAMPORTS_PER_SOL;
+use anchor_spl::token::spl_token;
+use ark_bn254::Bn254;
+use ark_groth16::{ProvingKey, VerifyingKey};
+use dara_client::{args, instructions, ContributionLimits, Market, PresaleKeys, VestingSchedule};
+use dara_harness::{Failure, Svm};
+use dara_prover::{dev_setup, verifying_key_data};
+
+pub const SOL: u64 = LAMPORTS_PER_SOL;
+pub const TOKENS_FOR_SALE: u64 = 1_000_000_000;
+pub const DECIMALS: u8 = 6;
+/// One whole base token, in base units.
+pub const UNIT: u64 = 1_000_000;
+
+/// A chain with the protocol config created fee-free by the upgrade authority.
+pub fn setup() -> Svm {
+    setup_with_fees(0, 0, 0)
+}
+
+pub fn setup_with_fees(presale_fee_bps: u16, taker_fee_bps: u16, maker_fee_bps: u16) -> Svm {
+    let mut svm = Svm::new();
+    let admin = svm.upgrade_authority();
+    svm.airdrop(&admin, 10 * SOL);
+    let init = instructions::initialize_protocol_config(
+        &admin,
+        args::InitializeProtocolConfig {
+            fee_recipient: admin,
+            presale_fee_bps,
+            taker_fee_bps,
+            maker_fee_bps,
+        },
+    );
+    svm.process(&init).unwrap();
+    svm
+}
+
+/// Presale terms: open from one hour from now for a day, 10 SOL hard cap, 2 SOL soft cap,
+/// no vesting, limits or denomination.
+pub fn presale_args(svm: &Svm) -> args::InitializePresale {
+    let start_time = svm.now() + 3_600;
+    args::InitializePresale {
+        hard_cap: 10 * SOL,
+        soft_cap: 2 * SOL,
+        tokens_for_sale: TOKENS_FOR_SALE,
+        start_time,
+        end_time: start_time + 86_400,
+        claim_window: 0,
+        vesting: VestingSchedule::default(),
+        limits: ContributionLimits::default(),
+        denomination: 0,
+    }
+}
+
+/// A creator holding `TOKENS_FOR_SALE` of a new mint of `token_program`.
+pub fn presale_keys(svm: &mut Svm, token_program: &Pubkey) -> PresaleKeys {
+    let mint = svm.create_mint(token_program, DECIMALS);
+    let creator = svm.new_wallet(10 * SOL);
+    let creator_account = svm.token_account(&creator, &mint);
+    svm.mint_to(&mint, &creator_account, TOKENS_FOR_SALE);
+    PresaleKeys {
+        mint,
+        creator,
+        token_program: *token_program,
+    }
+}
+
+/// A presale initialized with `args`.
+pub fn create_presale(svm: &mut Svm, args: args::InitializePresale) -> PresaleKeys {
+    let keys = presale_keys(svm, &spl_token::ID);
+    svm.process(&instructions::initialize_presale(&keys, args))
+        .unwrap();
+    keys
+}
+
+/// Moves the clock into the presale's commit window.
+pub fn open(svm: &mut Svm, keys: &PresaleKeys) {
+    let presale: anon_presale::Presale = svm.get(&keys.address());
+    svm.warp_to(presale.start_time);
+}
+
+/// Moves the clock past the presale's end.
+pub fn end(svm: &mut Svm, keys: &PresaleKeys) {
+    let presale: anon_presale::Presale = svm.get(&keys.address());
+    svm.warp_to(presale.end_time + 1);
+}
+
+/// Commits `sol_amount` from a fresh burner, claimable by `claim_wallet` with `secret`.
+pub fn commit(
+    svm: &mut Svm,
+    keys: &PresaleKeys,
+    secret: [u8; 32],
+    claim_wallet: &Pubkey,
+    sol_amount: u64,
+) -> Result<(), dara_harness::Failure> {
+    let burner = svm.new_wallet(sol_amount + SOL);
+    let commit = instructions::commit_to_presale(
+        &burner,
+        &keys.address(),
+        args::CommitToPresale {
+            commitment_hash: dara_client::commitment_hash(&secret, claim_wallet),
+            sol_amount,
+            whitelist_nullifier_hash: [0; 32],
+            whitelist_proof: None,
+        },
+    );
+    svm.process(&commit)
+}
+
+/// A market between two new mints of `token_program`, with its pool initialized by a fresh
+/// authority, which is returned alongside.
+pub fn create_market(svm: &mut Svm, token_program: &Pubkey) -> (Market, Pubkey) {
+    let market = Market {
+        base_mint: svm.create_mint(token_program, DECIMALS),
+        quote_mint: svm.create_mint(token_program, DECIMALS),
+        base_token_program: *token_program,
+        quote_token_program: *token_program,
+    };
+    let authority = svm.new_wallet(10 * SOL);
+    svm.process(&instructions::initialize_dark_pool(&authority, &market))
+        .unwrap();
+    // Where `close_dark_pool` sends anything left in the vaults
+    svm.token_account(&authority, &market.base_mint);
+    svm.token_account(&authority, &market.quote_mint);
+    (market, authority)
+}
+
+/// A wallet holding `base` and `quote` in the market's associated token accounts.
+pub fn trader(svm: &mut Svm, market: &Market, base: u64, quote: u64) -> Pubkey {
+    let wallet = svm.new_wallet(10 * SOL);
+    let base_account = svm.token_account(&wallet, &market.base_mint);
+    let quote_account = svm.token_account(&wallet, &market.quote_mint);
+    if base > 0 {
+        svm.mint_to(&market.base_mint, &base_account, base);
+    }
+    if quote > 0 {
+        svm.mint_to(&market.quote_mint, &quote_account, quote);
+    }
+    wallet
+}
+
+/// Points every meta for `from` in `ix` at `to`, keeping its privileges. For passing an
+/// account the builders would never derive.
+pub fn swap_account(
+    mut ix: anchor_lang::solana_program::instruction::Instruction,
+    from: &Pubkey,
+    to: &Pubkey,
+) -> anchor_lang::solana_program::instruction::Instruction {
+    for meta in ix.accounts.iter_mut().filter(|meta| meta.pubkey == *from) {
+        meta.pubkey = *to;
+    }
+    ix
+}
+
+/// Claims a commitment's vested tokens into `claim_wallet`, paid for by a fresh claimer.
+pub fn claim(
+    svm: &mut Svm,
+    keys: &PresaleKeys,
+    secret: [u8; 32],
+    claim_wallet: &Pubkey,
+) -> Result<(), dara_harness::Failure> {
+    let claimer = svm.new_wallet(SOL);
+    let claim = instructions::claim_tokens(
+        &claimer,
+        keys,
+        claim_wallet,
+        &claimer,
+        args::ClaimTokens { secret },
+    );
+    svm.process(&claim)
+}
+
+/// Refunds a commitment to `refund_wallet`, paid for by a fresh claimer.
+pub fn refund(
+    svm: &mut Svm,
+    keys: &PresaleKeys,
+    secret: [u8; 32],
+    refund_wallet: &Pubkey,
+) -> Result<(), dara_harness::Failure> {
+    let claimer = svm.new_wallet(SOL);
+    let refund = instructions::refund_commitment(
+        &claimer,
+        &keys.address(),
+        refund_wallet,
+        &claimer,
+        args::RefundCommitment { secret },
+    );
+    svm.process(&refund)
+}
+
+/// The claim circuit's development key pair, built once per test binary.
+pub fn dev_keys() -> &'static (ProvingKey<Bn254>, VerifyingKey<Bn254>) {
+    static KEYS: OnceLock<(ProvingKey<Bn254>, VerifyingKey<Bn254>)> = OnceLock::new();
+    KEYS.get_or_init(|| dev_setup().unwrap())
+}
+
+/// Installs the development verifying key, signed by `admin`.
+pub fn set_claim_verifying_key(svm: &mut Svm, admin: &Pubkey) -> Result<(), Failure> {
+    let set = instructions::set_claim_verifying_key(
+        admin,
+        args::SetClaimVerifyingKey {
+            key: verifying_key_data(&dev_keys().1),
+        },
+    );
+    svm.process(&set)
+}
@@ -0,0 +1,818 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token::spl_token;
+use anon_presale::{DarkOrder, DarkOrderFilled, DarkPool, DarkPoolError};
+use common::*;
+use dara_client::{
+    args, dark_order_hash, designated_order_hash, instructions, limit_order_hash, pda, Market,
+};
+use dara_harness::{assert_error, Failure, Svm};
+
+const SELL: u8 = 0;
+const BUY: u8 = 1;
+
+fn place(
+    svm: &mut Svm,
+    market: &Market,
+    maker: &Pubkey,
+    order_hash: [u8; 32],
+    escrow_base: u64,
+    escrow_quote: u64,
+    expires_at: i64,
+) -> Result<(), Failure> {
+    let place = instructions::place_dark_order(
+        maker,
+        market,
+        args::PlaceDarkOrder {
+            order_hash,
+            escrow_base,
+            escrow_quote,
+            expires_at,
+        },
+    );
+    svm.process(&place)
+}
+
+fn fill(
+    svm: &mut Svm,
+    market: &Market,
+    taker: &Pubkey,
+    maker: &Pubkey,
+    args: args::FillDarkOrder,
+) -> Result<(), Failure> {
+    svm.process(&instructions::fill_dark_order(taker, market, maker, args))
+}
+
+fn fill_args(
+    secret: [u8; 32],
+    side: u8,
+    base_amount: u64,
+    quote_amount: u64,
+    fill_base: u64,
+) -> args::FillDarkOrder {
+    args::FillDarkOrder {
+        secret,
+        side,
+        base_amount,
+        quote_amount,
+        fill_base,
+    }
+}
+
+fn order_address(market: &Market, order_hash: &[u8; 32]) -> Pubkey {
+    pda::dark_order(&market.dark_pool(), order_hash).0
+}
+
+/// A market with a maker selling 100 base for 250 quote, placed under `[1; 32]`.
+fn sell_order(svm: &mut Svm) -> (Market, Pubkey, [u8; 32]) {
+    let (market, _) = create_market(svm, &spl_token::ID);
+    let maker = trader(svm, &market, 100 * UNIT, 0);
+    let order_hash = dark_order_hash(&[1; 32], SELL, 100 * UNIT, 250 * UNIT, &maker);
+    place(svm, &market, &maker, order_hash, 100 * UNIT, 0, 0).unwrap();
+    (market, maker, order_hash)
+}
+
+// ─── Pool ───
+
+#[test]
+fn initialize_creates_the_vaults() {
+    let mut svm = setup();
+    let (market, authority) = create_market(&mut svm, &spl_token::ID);
+
+    let pool: DarkPool = svm.get(&market.dark_pool());
+    assert_eq!(pool.base_mint, market.base_mint);
+    assert_eq!(pool.quote_mint, market.quote_mint);
+    assert_eq!(pool.authority, authority);
+    for vault in [
+        pda::dp_base_vault(&market.dark_pool()).0,
+        pda::dp_quote_vault(&market.dark_pool()).0,
+        pda::dp_fee_vault(&market.dark_pool()).0,
+    ] {
+        assert_eq!(svm.account(&vault).unwrap().owner, spl_token::ID);
+    }
+
+    let mint = svm.create_mint(&spl_token::ID, DECIMALS);
+    let authority = svm.new_wallet(10 * SOL);
+    let same_mint = Market::new(mint, mint);
+    assert_error(
+        svm.process(&instructions::initialize_dark_pool(&authority, &same_mint)),
+        DarkPoolError::InvalidMarket,
+    );
+}
+
+#[test]
+fn close_requires_no_open_orders_or_fees() {
+    let mut svm = setup_with_fees(0, 30, 0);
+    let (market, authority) = create_market(&mut svm, &spl_token::ID);
+    let maker = trader(&mut svm, &market, 100 * UNIT, 0);
+    let taker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+    let order_hash = dark_order_hash(&[1; 32], SELL, 100 * UNIT, 200 * UNIT, &maker);
+    place(&mut svm, &market, &maker, order_hash, 100 * UNIT, 0, 0).unwrap();
+
+    let impostor = svm.new_wallet(SOL);
+    let as_impostor = swap_account(
+        instructions::close_dark_pool(&authority, &market),
+        &authority,
+        &impostor,
+    );
+    assert_error(svm.process(&as_impostor), DarkPoolError::Unauthorized);
+    assert_error(
+        svm.process(&instructions::close_dark_pool(&authority, &market)),
+        DarkPoolError::OpenOrdersRemaining,
+    );
+
+    fill(
+        &mut svm,
+        &market,
+        &taker,
+        &maker,
+        fill_args([1; 32], SELL, 100 * UNIT, 200 * UNIT, 100 * UNIT),
+    )
+    .unwrap();
+    assert_error(
+        svm.process(&instructions::close_dark_pool(&authority, &market)),
+        DarkPoolError::FeesUncollected,
+    );
+
+    let admin = svm.upgrade_authority();
+    svm.token_account(&admin, &market.quote_mint);
+    svm.process(&instructions::collect_dark_pool_fees(&market, &admin))
+        .unwrap();
+    let before = svm.lamports(&authority);
+    svm.process(&instructions::close_dark_pool(&authority, &market))
+        .unwrap();
+    assert!(svm.account(&market.dark_pool()).is_none());
+    assert!(svm
+        .account(&pda::dp_fee_vault(&market.dark_pool()).0)
+        .is_none());
+    assert!(svm.lamports(&authority) > before);
+}
+
+#[test]
+fn close_sweeps_leftovers_to_the_authority() {
+    let mut svm = setup();
+    let (market, authority) = create_market(&mut svm, &spl_token::ID);
+
+    // Tokens sent straight to a vault belong to no order, but must not block the close
+    let dark_pool = market.dark_pool();
+    svm.mint_to(&market.base_mint, &pda::dp_base_vault(&dark_pool).0, 1);
+    svm.mint_to(&market.quote_mint, &pda::dp_quote_vault(&dark_pool).0, 2);
+    svm.process(&instructions::close_dark_pool(&authority, &market))
+        .unwrap();
+    assert!(svm.account(&pda::dp_base_vault(&dark_pool).0).is_none());
+    assert_eq!(svm.token_balance(&market.base_account(&authority)), 1);
+    assert_eq!(svm.token_balance(&market.quote_account(&authority)), 2);
+}
+
+// ─── Place ───
+
+#[test]
+fn place_escrows_both_sides() {
+    let mut svm = setup();
+    let (market, _) = create_market(&mut svm, &spl_token::ID);
+    let maker = trader(&mut svm, &market, 10 * UNIT, 20 * UNIT);
+    let expires_at = svm.now() + 60;
+    place(
+        &mut svm,
+        &market,
+        &maker,
+        [7; 32],
+        10 * UNIT,
+        20 * UNIT,
+        expires_at,
+    )
+    .unwrap();
+
+    let order: DarkOrder = svm.get(&order_address(&market, &[7; 32]));
+    assert_eq!(order.maker, maker);
+    assert_eq!(
+        (order.escrow_base, order.escrow_quote),
+        (10 * UNIT, 20 * UNIT)
+    );
+    assert_eq!(order.expires_at, expires_at);
+    assert_eq!(order.order_id, 1);
+    assert_eq!(
+        svm.token_balance(&pda::dp_base_vault(&market.dark_pool()).0),
+        10 * UNIT
+    );
+    assert_eq!(
+        svm.token_balance(&pda::dp_quote_vault(&market.dark_pool()).0),
+        20 * UNIT
+    );
+    let pool: DarkPool = svm.get(&market.dark_pool());
+    assert_eq!(pool.open_order_count, 1);
+}
+
+#[test]
+fn place_rejects_empty_or_stale_orders() {
+    let mut svm = setup();
+    let (market, _) = create_market(&mut svm, &spl_token::ID);
+    let maker = trader(&mut svm, &market, 10 * UNIT, 0);
+
+    assert_error(
+        place(&mut svm, &market, &maker, [1; 32], 0, 0, 0),
+        DarkPoolError::InvalidOrderParams,
+    );
+    let now = svm.now();
+    assert_error(
+        place(&mut svm, &market, &maker, [1; 32], UNIT, 0, now),
+        DarkPoolError::InvalidExpiry,
+    );
+}
+
+// ─── Fill ───
+
+#[test]
+fn fill_in_parts_then_close() {
+    let mut svm = setup();
+    let (market, maker, order_hash) = sell_order(&mut svm);
+    let taker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+
+    fill(
+        &mut svm,
+        &market,
+        &taker,
+        &maker,
+        fill_args([1; 32], SELL, 100 * UNIT, 250 * UNIT, 40 * UNIT),
+    )
+    .unwrap();
+    let order: DarkOrder = svm.get(&order_address(&market, &order_hash));
+    assert_eq!(
+        (order.filled_base, order.remaining_base),
+        (40 * UNIT, 60 * UNIT)
+    );
+    assert_eq!(svm.token_balance(&market.base_account(&taker)), 40 * UNIT);
+    assert_eq!(svm.token_balance(&market.quote_account(&maker)), 100 * UNIT);
+
+    assert_error(
+        fill(
+            &mut svm,
+            &market,
+            &taker,
+            &maker,
+            fill_args([1; 32], SELL, 100 * UNIT, 250 * UNIT, 61 * UNIT),
+        ),
+        DarkPoolError::FillExceedsOrder,
+    );
+    assert_error(
+        fill(
+            &mut svm,
+            &market,
+            &taker,
+            &maker,
+            fill_args([1; 32], SELL, 100 * UNIT, 250 * UNIT, 0),
+        ),
+        DarkPoolError::InvalidFillAmount,
+    );
+
+    let maker_lamports = svm.lamports(&maker);
+    fill(
+        &mut svm,
+        &market,
+        &taker,
+        &maker,
+        fill_args([1; 32], SELL, 100 * UNIT, 250 * UNIT, 60 * UNIT),
+    )
+    .unwrap();
+    let filled = svm.events::<DarkOrderFilled>();
+    assert!(filled[0].is_filled);
+    assert_eq!(svm.token_balance(&market.base_account(&taker)), 100 * UNIT);
+    assert_eq!(svm.token_balance(&market.quote_account(&maker)), 250 * UNIT);
+    assert!(svm.account(&order_address(&market, &order_hash)).is_none());
+    assert!(
+        svm.lamports(&maker) > maker_lamports,
+        "order rent returns to the maker"
+    );
+
+    let pool: DarkPool = svm.get(&market.dark_pool());
+    assert_eq!(pool.open_order_count, 0);
+    assert_eq!(pool.total_volume_quote, 250 * UNIT);
+}
+
+#[test]
+fn buy_order_pays_quote_from_escrow() {
+    let mut svm = setup_with_fees(0, 100, 50);
+    let (market, _) = create_market(&mut svm, &spl_token::ID);
+    let maker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+    let taker = trader(&mut svm, &market, 10 * UNIT, 0);
+    let order_hash = dark_order_hash(&[2; 32], BUY, 10 * UNIT, 200 * UNIT, &maker);
+    place(&mut svm, &market, &maker, order_hash, 0, 300 * UNIT, 0).unwrap();
+
+    fill(
+        &mut svm,
+        &market,
+        &taker,
+        &maker,
+        fill_args([2; 32], BUY, 10 * UNIT, 200 * UNIT, 10 * UNIT),
+    )
+    .unwrap();
+    // 1% taker and 0.5% maker fee on 200 quote
+    assert_eq!(svm.token_balance(&market.quote_account(&taker)), 198 * UNIT);
+    assert_eq!(
+        svm.token_balance(&pda::dp_fee_vault(&market.dark_pool()).0),
+        3 * UNIT
+    );
+    assert_eq!(svm.token_balance(&market.base_account(&maker)), 10 * UNIT);
+    // Unused escrow comes back once the order is filled
+    assert_eq!(
+        svm.token_balance(&market.quote_account(&maker)),
+        700 * UNIT + 99 * UNIT
+    );
+}
+
+#[test]
+fn sell_fill_charges_fees_in_quote() {
+    let mut svm = setup_with_fees(0, 100, 50);
+    let (market, maker, _) = sell_order(&mut svm);
+    let taker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+
+    fill(
+        &mut svm,
+        &market,
+        &taker,
+        &maker,
+        fill_args([1; 32], SELL, 100 * UNIT, 250 * UNIT, 100 * UNIT),
+    )
+    .unwrap();
+    let fee_vault = pda::dp_fee_vault(&market.dark_pool()).0;
+    assert_eq!(svm.token_balance(&fee_vault), 2_500_000 + 1_250_000);
+    assert_eq!(
+        svm.token_balance(&market.quote_account(&maker)),
+        250 * UNIT - 1_250_000
+    );
+    assert_eq!(
+        svm.token_balance(&market.quote_account(&taker)),
+        750 * UNIT - 2_500_000
+    );
+}
+
+#[test]
+fn fill_requires_the_order_terms() {
+    let mut svm = setup();
+    let (market, maker, _) = sell_order(&mut svm);
+    let taker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+
+    // The builder finds the order from its terms, so point altered terms at the real order
+    let real = order_address(
+        &market,
+        &dark_order_hash(&[1; 32], SELL, 100 * UNIT, 250 * UNIT, &maker),
+    );
+    let cheap = fill_args([1; 32], SELL, 100 * UNIT, 1, 100 * UNIT);
+    let forged = order_address(
+        &market,
+        &dark_order_hash(&[1; 32], SELL, 100 * UNIT, 1, &maker),
+    );
+    let ix = instructions::fill_dark_order(&taker, &market, &maker, cheap);
+    assert_error(
+        svm.process(&swap_account(ix, &forged, &real)),
+        DarkPoolError::InvalidOrderProof,
+    );
+
+    // The maker wallet is pinned to the order
+    let other = trader(&mut svm, &market, 0, 0);
+    let ix = instructions::fill_dark_order(
+        &taker,
+        &market,
+        &maker,
+        fill_args([1; 32], SELL, 100 * UNIT, 250 * UNIT, UNIT),
+    );
+    assert_error(
+        svm.process(&swap_account(ix, &maker, &other)),
+        DarkPoolError::InvalidMaker,
+    );
+}
+
+#[test]
+fn fill_rejects_an_order_from_another_pool() {
+    let mut svm = setup();
+    let (market, maker, order_hash) = sell_order(&mut svm);
+    let (other_market, _) = create_market(&mut svm, &spl_token::ID);
+    let other_maker = trader(&mut svm, &other_market, 100 * UNIT, 0);
+    let other_hash = dark_order_hash(&[1; 32], SELL, 100 * UNIT, 250 * UNIT, &other_maker);
+    place(
+        &mut svm,
+        &other_market,
+        &other_maker,
+        other_hash,
+        100 * UNIT,
+        0,
+        0,
+    )
+    .unwrap();
+    let taker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+
+    let ix = instructions::fill_dark_order(
+        &taker,
+        &market,
+        &maker,
+        fill_args([1; 32], SELL, 100 * UNIT, 250 * UNIT, UNIT),
+    );
+    let ix = swap_account(
+        ix,
+        &order_address(&market, &order_hash),
+        &order_address(&other_market, &other_hash),
+    );
+    assert_error(svm.process(&ix), DarkPoolError::InvalidOrder);
+}
+
+#[test]
+fn fill_checks_side_and_escrow() {
+    let mut svm = setup();
+    let (market, _) = create_market(&mut svm, &spl_token::ID);
+    let maker = trader(&mut svm, &market, 100 * UNIT, 100 * UNIT);
+    let taker = trader(&mut svm, &market, 100 * UNIT, 1_000 * UNIT);
+
+    let bad_side = dark_order_hash(&[1; 32], 2, 10 * UNIT, 10 * UNIT, &maker);
+    place(&mut svm, &market, &maker, bad_side, UNIT, 0, 0).unwrap();
+    assert_error(
+        fill(
+            &mut svm,
+            &market,
+            &taker,
+            &maker,
+            fill_args([1; 32], 2, 10 * UNIT, 10 * UNIT, UNIT),
+        ),
+        DarkPoolError::InvalidSide,
+    );
+
+    let no_base = dark_order_hash(&[2; 32], SELL, 0, 10 * UNIT, &maker);
+    place(&mut svm, &market, &maker, no_base, UNIT, 0, 0).unwrap();
+    assert_error(
+        fill(
+            &mut svm,
+            &market,
+            &taker,
+            &maker,
+            fill_args([2; 32], SELL, 0, 10 * UNIT, UNIT),
+        ),
+        DarkPoolError::InvalidOrderParams,
+    );
+
+    // Terms promise more base than the maker escrowed
+    let short = dark_order_hash(&[3; 32], SELL, 50 * UNIT, 50 * UNIT, &maker);
+    place(&mut svm, &market, &maker, short, 10 * UNIT, 0, 0).unwrap();
+    assert_error(
+        fill(
+            &mut svm,
+            &market,
+            &taker,
+            &maker,
+            fill_args([3; 32], SELL, 50 * UNIT, 50 * UNIT, 20 * UNIT),
+        ),
+        DarkPoolError::InsufficientEscrow,
+    );
+
+    let short_quote = dark_order_hash(&[4; 32], BUY, 50 * UNIT, 50 * UNIT, &maker);
+    place(&mut svm, &market, &maker, short_quote, 0, 10 * UNIT, 0).unwrap();
+    assert_error(
+        fill(
+            &mut svm,
+            &market,
+            &taker,
+            &maker,
+            fill_args([4; 32], BUY, 50 * UNIT, 50 * UNIT, 20 * UNIT),
+        ),
+        DarkPoolError::InsufficientEscrow,
+    );
+}
+
+#[test]
+fn fill_rejects_settled_orders() {
+    let mut svm = setup();
+    let (market, maker, order_hash) = sell_order(&mut svm);
+    let taker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+    let address = order_address(&market, &order_hash);
+    let args = || fill_args([1; 32], SELL, 100 * UNIT, 250 * UNIT, UNIT);
+
+    // Filled and cancelled orders close, so only a stale account could still say so
+    let mut order: DarkOrder = svm.get(&address);
+    order.is_cancelled = true;
+    svm.set(&address, &order);
+    assert_error(
+        fill(&mut svm, &market, &taker, &maker, args()),
+        DarkPoolError::OrderCancelled,
+    );
+    assert_error(
+        svm.process(&instructions::cancel_dark_order(
+            &maker,
+            &market,
+            &order_hash,
+        )),
+        DarkPoolError::OrderCancelled,
+    );
+
+    order.is_cancelled = false;
+    order.is_filled = true;
+    svm.set(&address, &order);
+    assert_error(
+        fill(&mut svm, &market, &taker, &maker, args()),
+        DarkPoolError::OrderAlreadyFilled,
+    );
+    assert_error(
+        svm.process(&instructions::cancel_dark_order(
+            &maker,
+            &market,
+            &order_hash,
+        )),
+        DarkPoolError::OrderAlreadyFilled,
+    );
+    let cranker = svm.new_wallet(SOL);
+    assert_error(
+        svm.process(&instructions::expire_dark_order(
+            &cranker,
+            &market,
+            &maker,
+            &order_hash,
+        )),
+        DarkPoolError::OrderAlreadyFilled,
+    );
+}
+
+#[test]
+fn designated_order_only_fills_for_its_taker() {
+    let mut svm = setup();
+    let (market, _) = create_market(&mut svm, &spl_token::ID);
+    let maker = trader(&mut svm, &market, 100 * UNIT, 0);
+    let taker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+    let order_hash = designated_order_hash(&[5; 32], SELL, 100 * UNIT, 250 * UNIT, &taker, &maker);
+    place(&mut svm, &market, &maker, order_hash, 100 * UNIT, 0, 0).unwrap();
+    let args = || args::FillDesignatedOrder {
+        secret: [5; 32],
+        side: SELL,
+        base_amount: 100 * UNIT,
+        quote_amount: 250 * UNIT,
+        fill_base: 100 * UNIT,
+    };
+
+    // Someone who copied the secret derives a different order
+    let copycat = trader(&mut svm, &market, 0, 1_000 * UNIT);
+    let copied = designated_order_hash(&[5; 32], SELL, 100 * UNIT, 250 * UNIT, &copycat, &maker);
+    let ix = instructions::fill_designated_order(&copycat, &market, &maker, args());
+    let ix = swap_account(
+        ix,
+        &order_address(&market, &copied),
+        &order_address(&market, &order_hash),
+    );
+    assert_error(svm.process(&ix), DarkPoolError::InvalidOrderProof);
+
+    // Nor does it open as an ordinary dark order
+    let ix = instructions::fill_dark_order(
+        &copycat,
+        &market,
+        &maker,
+        fill_args([5; 32], SELL, 100 * UNIT, 250 * UNIT, 100 * UNIT),
+    );
+    let plain = dark_order_hash(&[5; 32], SELL, 100 * UNIT, 250 * UNIT, &maker);
+    let ix = swap_account(
+        ix,
+        &order_address(&market, &plain),
+        &order_address(&market, &order_hash),
+    );
+    assert_error(svm.process(&ix), DarkPoolError::InvalidOrderProof);
+
+    svm.process(&instructions::fill_designated_order(
+        &taker,
+        &market,
+        &maker,
+        args(),
+    ))
+    .unwrap();
+    assert_eq!(svm.token_balance(&market.base_account(&taker)), 100 * UNIT);
+}
+
+#[test]
+fn limit_order_fills_at_or_better_than_its_price() {
+    let mut svm = setup();
+    let (market, _) = create_market(&mut svm, &spl_token::ID);
+    let maker = trader(&mut svm, &market, 100 * UNIT, 0);
+    let taker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+    // Sell up to 100 base at 2.5 quote per base
+    let price = 2_500_000;
+    let order_hash = limit_order_hash(&[6; 32], SELL, price, 100 * UNIT, &maker);
+    place(&mut svm, &market, &maker, order_hash, 100 * UNIT, 0, 0).unwrap();
+    let args = |base_amount, quote_amount| args::FillLimitOrder {
+        nonce: [6; 32],
+        side: SELL,
+        price,
+        max_size: 100 * UNIT,
+        base_amount,
+        quote_amount,
+    };
+    let limit_fill = |svm: &mut Svm, base_amount, quote_amount| {
+        svm.process(&instructions::fill_limit_order(
+            &taker,
+            &market,
+            &maker,
+            args(base_amount, quote_amount),
+        ))
+    };
+
+    assert_error(
+        limit_fill(&mut svm, 10 * UNIT, 25 * UNIT - 1),
+        DarkPoolError::PriceLimitExceeded,
+    );
+    limit_fill(&mut svm, 10 * UNIT, 25 * UNIT).unwrap();
+    limit_fill(&mut svm, 10 * UNIT, 30 * UNIT).unwrap();
+    assert_eq!(svm.token_balance(&market.quote_account(&maker)), 55 * UNIT);
+    assert_error(
+        limit_fill(&mut svm, 81 * UNIT, 300 * UNIT),
+        DarkPoolError::FillExceedsOrder,
+    );
+
+    // The limit tag keeps the order from opening as a fixed-price one
+    let ix = instructions::fill_dark_order(
+        &taker,
+        &market,
+        &maker,
+        fill_args([6; 32], SELL, price, 100 * UNIT, UNIT),
+    );
+    let plain = dark_order_hash(&[6; 32], SELL, price, 100 * UNIT, &maker);
+    let ix = swap_account(
+        ix,
+        &order_address(&market, &plain),
+        &order_address(&market, &order_hash),
+    );
+    assert_error(svm.process(&ix), DarkPoolError::InvalidOrderProof);
+}
+
+#[test]
+fn limit_buy_must_not_overpay() {
+    let mut svm = setup();
+    let (market, _) = create_market(&mut svm, &spl_token::ID);
+    let maker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+    let taker = trader(&mut svm, &market, 100 * UNIT, 0);
+    let price = 2 * UNIT;
+    let order_hash = limit_order_hash(&[7; 32], BUY, price, 50 * UNIT, &maker);
+    place(&mut svm, &market, &maker, order_hash, 0, 100 * UNIT, 0).unwrap();
+    let args = |side, base_amount, quote_amount| args::FillLimitOrder {
+        nonce: [7; 32],
+        side,
+        price,
+        max_size: 50 * UNIT,
+        base_amount,
+        quote_amount,
+    };
+
+    let ix = instructions::fill_limit_order(
+        &taker,
+        &market,
+        &maker,
+        args(BUY, 10 * UNIT, 20 * UNIT + 1),
+    );
+    assert_error(svm.process(&ix), DarkPoolError::PriceLimitExceeded);
+    let ix =
+        instructions::fill_limit_order(&taker, &market, &maker, args(BUY, 10 * UNIT, 18 * UNIT));
+    svm.process(&ix).unwrap();
+    assert_eq!(svm.token_balance(&market.quote_account(&taker)), 18 * UNIT);
+
+    let bad_side = limit_order_hash(&[8; 32], 2, price, 50 * UNIT, &maker);
+    place(&mut svm, &market, &maker, bad_side, 0, UNIT, 0).unwrap();
+    let ix = instructions::fill_limit_order(
+        &taker,
+        &market,
+        &maker,
+        args::FillLimitOrder {
+            nonce: [8; 32],
+            ..args(2, UNIT, UNIT)
+        },
+    );
+    assert_error(svm.process(&ix), DarkPoolError::InvalidSide);
+}
+
+#[test]
+fn limit_fill_rejects_mints_too_precise_to_price() {
+    let mut svm = setup();
+    // 10^40 base units per whole token does not fit the u128 price arithmetic
+    let market = Market::new(
+        svm.create_mint(&spl_token::ID, 40),
+        svm.create_mint(&spl_token::ID, DECIMALS),
+    );
+    let authority = svm.new_wallet(10 * SOL);
+    svm.process(&instructions::initialize_dark_pool(&authority, &market))
+        .unwrap();
+    let maker = trader(&mut svm, &market, 100, 0);
+    let taker = trader(&mut svm, &market, 0, 100);
+    let order_hash = limit_order_hash(&[9; 32], SELL, 1, 100, &maker);
+    place(&mut svm, &market, &maker, order_hash, 100, 0, 0).unwrap();
+
+    let ix = instructions::fill_limit_order(
+        &taker,
+        &market,
+        &maker,
+        args::FillLimitOrder {
+            nonce: [9; 32],
+            side: SELL,
+            price: 1,
+            max_size: 100,
+            base_amount: 10,
+            quote_amount: 10,
+        },
+    );
+    assert_error(svm.process(&ix), DarkPoolError::InvalidOrderParams);
+}
+
+// ─── Cancel and expire ───
+
+#[test]
+fn cancel_returns_the_remaining_escrow() {
+    let mut svm = setup();
+    let (market, maker, order_hash) = sell_order(&mut svm);
+    let taker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+    fill(
+        &mut svm,
+        &market,
+        &taker,
+        &maker,
+        fill_args([1; 32], SELL, 100 * UNIT, 250 * UNIT, 30 * UNIT),
+    )
+    .unwrap();
+
+    let impostor = trader(&mut svm, &market, 0, 0);
+    let ix = instructions::cancel_dark_order(&maker, &market, &order_hash);
+    assert_error(
+        svm.process(&swap_account(ix, &maker, &impostor)),
+        DarkPoolError::Unauthorized,
+    );
+
+    svm.process(&instructions::cancel_dark_order(
+        &maker,
+        &market,
+        &order_hash,
+    ))
+    .unwrap();
+    assert_eq!(svm.token_balance(&market.base_account(&maker)), 70 * UNIT);
+    assert!(svm.account(&order_address(&market, &order_hash)).is_none());
+    let pool: DarkPool = svm.get(&market.dark_pool());
+    assert_eq!(pool.open_order_count, 0);
+}
+
+#[test]
+fn expired_orders_stop_filling_and_can_be_cranked() {
+    let mut svm = setup();
+    let (market, _) = create_market(&mut svm, &spl_token::ID);
+    let maker = trader(&mut svm, &market, 100 * UNIT, 0);
+    let taker = trader(&mut svm, &market, 0, 1_000 * UNIT);
+    let cranker = svm.new_wallet(SOL);
+    let order_hash = dark_order_hash(&[1; 32], SELL, 100 * UNIT, 250 * UNIT, &maker);
+    let expires_at = svm.now() + 600;
+    place(
+        &mut svm,
+        &market,
+        &maker,
+        order_hash,
+        100 * UNIT,
+        0,
+        expires_at,
+    )
+    .unwrap();
+    let expire = instructions::expire_dark_order(&cranker, &market, &maker, &order_hash);
+
+    assert_error(svm.process(&expire), DarkPoolError::OrderNotExpired);
+    fill(
+        &mut svm,
+        &market,
+        &taker,
+        &maker,
+        fill_args([1; 32], SELL, 100 * UNIT, 250 * UNIT, 10 * UNIT),
+    )
+    .unwrap();
+
+    svm.warp_to(expires_at);
+    assert_error(
+        fill(
+            &mut svm,
+            &market,
+            &taker,
+            &maker,
+            fill_args([1; 32], SELL, 100 * UNIT, 250 * UNIT, 10 * UNIT),
+        ),
+        DarkPoolError::OrderExpired,
+    );
+    let other = trader(&mut svm, &market, 0, 0);
+    assert_error(
+        svm.process(&swap_account(expire.clone(), &maker, &other)),
+        DarkPoolError::InvalidMaker,
+    );
+
+    svm.process(&expire).unwrap();
+    assert_eq!(svm.token_balance(&market.base_account(&maker)), 90 * UNIT);
+    assert!(svm.account(&order_address(&market, &order_hash)).is_none());
+}
+
+#[test]
+fn resting_orders_never_expire() {
+    let mut svm = setup();
+    let (market, maker, order_hash) = sell_order(&mut svm);
+    let cranker = svm.new_wallet(SOL);
+    svm.warp(365 * 86_400);
+    assert_error(
+        svm.process(&instructions::expire_dark_order(
+            &cranker,
+            &market,
+            &maker,
+            &order_hash,
+        )),
+        DarkPoolError::OrderNotExpired,
+    );
+}
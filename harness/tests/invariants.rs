@@ -0,0 +1,486 @@
+//! Property tests: random operation sequences against a presale and a dark pool, checking
+//! that SOL and tokens are conserved after every step. Failed operations are expected and
+//! ignored; the invariants must hold either way. Set `PROPTEST_CASES` for longer runs.
+
+mod common;
+
+use anchor_lang::prelude::{Pubkey, Rent};
+use anchor_spl::token::spl_token;
+use anon_presale::{Commitment, DarkOrder, DarkPool, Presale, ProtocolConfig};
+use common::*;
+use dara_client::{
+    args, commitment_hash, dark_order_hash, instructions, pda, Market, PresaleKeys, VestingSchedule,
+};
+use dara_harness::Svm;
+use proptest::prelude::*;
+
+const CLAIM_WALLETS: usize = 4;
+const TRADERS: usize = 4;
+const TRADER_BALANCE: u64 = 1_000 * UNIT;
+
+// ─── Presale ───
+
+#[derive(Clone, Debug)]
+enum PresaleOp {
+    Commit { wallet: usize, lamports: u64 },
+    Warp(i64),
+    Finalize,
+    Claim(usize),
+    Refund(usize),
+    Cancel,
+}
+
+fn presale_op() -> impl Strategy<Value = PresaleOp> {
+    prop_oneof![
+        5 => (0..CLAIM_WALLETS, 1..300u64).prop_map(|(wallet, centisol)| PresaleOp::Commit {
+            wallet,
+            lamports: centisol * SOL / 100,
+        }),
+        2 => (0..12 * 3_600i64).prop_map(PresaleOp::Warp),
+        1 => Just(PresaleOp::Finalize),
+        4 => any::<usize>().prop_map(PresaleOp::Claim),
+        1 => any::<usize>().prop_map(PresaleOp::Refund),
+    ]
+}
+
+/// Operation sequences, a quarter of which cancel the sale at some point.
+fn presale_ops() -> impl Strategy<Value = Vec<PresaleOp>> {
+    (
+        prop::collection::vec(presale_op(), 1..60),
+        prop::option::weighted(0.25, any::<prop::sample::Index>()),
+    )
+        .prop_map(|(mut ops, cancel)| {
+            if let Some(at) = cancel {
+                ops.insert(at.index(ops.len() + 1), PresaleOp::Cancel);
+            }
+            ops
+        })
+}
+
+#[derive(Debug)]
+struct PresaleTerms {
+    presale_fee_bps: u16,
+    hard_cap: u64,
+    soft_cap: u64,
+    tokens_for_sale: u64,
+    /// TGE unlock, cliff and vesting duration.
+    vesting: (u16, i64, i64),
+}
+
+fn presale_terms() -> impl Strategy<Value = PresaleTerms> {
+    (
+        0..=1_000u16,
+        1..20u64,
+        0..=50u64,
+        1..=TOKENS_FOR_SALE,
+        (0..=10_000u16, 0..86_400i64, 0..5 * 86_400i64),
+    )
+        .prop_map(
+            |(presale_fee_bps, hard_cap_sol, soft_cap_pct, tokens_for_sale, vesting)| {
+                let hard_cap = hard_cap_sol * SOL;
+                PresaleTerms {
+                    presale_fee_bps,
+                    hard_cap,
+                    soft_cap: hard_cap / 100 * soft_cap_pct,
+                    tokens_for_sale,
+                    vesting,
+                }
+            },
+        )
+}
+
+struct PresaleModel {
+    keys: PresaleKeys,
+    wallets: Vec<Pubkey>,
+    /// Secret, claim wallet and amount of every accepted commitment.
+    commitments: Vec<([u8; 32], Pubkey, u64)>,
+    refunded: u64,
+}
+
+impl PresaleModel {
+    fn presale(&self, svm: &Svm) -> Presale {
+        svm.get(&self.keys.address())
+    }
+
+    fn commitment(&self, index: usize) -> Pubkey {
+        let (secret, wallet, _) = &self.commitments[index];
+        pda::commitment(&self.keys.address(), &commitment_hash(secret, wallet)).0
+    }
+
+    fn apply(&mut self, svm: &mut Svm, op: &PresaleOp) -> Result<(), TestCaseError> {
+        match *op {
+            PresaleOp::Commit { wallet, lamports } => {
+                let secret = (self.commitments.len() as u64 + 1).to_le_bytes().repeat(4);
+                let secret: [u8; 32] = secret.try_into().unwrap();
+                let wallet = self.wallets[wallet];
+                if commit(svm, &self.keys, secret, &wallet, lamports).is_ok() {
+                    self.commitments.push((secret, wallet, lamports));
+                }
+            }
+            PresaleOp::Warp(seconds) => svm.warp(seconds),
+            PresaleOp::Finalize => {
+                let creator_before = svm.lamports(&self.keys.creator);
+                let fees_before = svm.lamports(&pda::protocol_config().0);
+                if svm
+                    .process(&instructions::finalize_presale(&self.keys))
+                    .is_ok()
+                {
+                    // Everything raised goes to the creator or the protocol, split at the fee rate
+                    let presale = self.presale(svm);
+                    let config: ProtocolConfig = svm.get(&pda::protocol_config().0);
+                    let fee = svm.lamports(&pda::protocol_config().0) - fees_before;
+                    let paid = svm.lamports(&self.keys.creator) - creator_before;
+                    prop_assert_eq!(fee + paid, presale.total_sol_committed);
+                    prop_assert_eq!(
+                        fee,
+                        ProtocolConfig::fee(presale.total_sol_committed, config.presale_fee_bps)
+                    );
+                }
+            }
+            PresaleOp::Claim(index) if !self.commitments.is_empty() => {
+                let (secret, wallet, _) = self.commitments[index % self.commitments.len()];
+                let _ = claim(svm, &self.keys, secret, &wallet);
+            }
+            PresaleOp::Refund(index) if !self.commitments.is_empty() => {
+                let (secret, wallet, lamports) = self.commitments[index % self.commitments.len()];
+                if refund(svm, &self.keys, secret, &wallet).is_ok() {
+                    self.refunded += lamports;
+                }
+            }
+            PresaleOp::Cancel => {
+                let _ = svm.process(&instructions::cancel_presale(&self.keys));
+            }
+            PresaleOp::Claim(_) | PresaleOp::Refund(_) => {}
+        }
+        Ok(())
+    }
+
+    fn check(&self, svm: &Svm) -> Result<(), TestCaseError> {
+        let presale = self.presale(svm);
+        prop_assert!(presale.total_sol_committed <= presale.hard_cap);
+        prop_assert_eq!(presale.commitment_count as usize, self.commitments.len());
+        prop_assert!(presale.settled_count <= presale.commitment_count);
+
+        // SOL: until finalized the PDA holds every unrefunded commitment on top of its rent
+        let address = self.keys.address();
+        let rent = Rent::default().minimum_balance(svm.account(&address).unwrap().data.len());
+        let held = if presale.is_finalized {
+            0
+        } else {
+            presale.total_sol_committed - self.refunded
+        };
+        prop_assert_eq!(svm.lamports(&address), rent + held);
+
+        // Tokens: nothing is minted or burned, and claims only ever reach the bound wallet
+        let vault = svm.token_balance(&pda::token_vault(&address).0);
+        let creator = svm.token_balance(&self.keys.token_account(&self.keys.creator));
+        let mut claimed_by_wallet = vec![0u64; self.wallets.len()];
+        let mut total_claimed = 0u64;
+        let mut total_owed = 0u64;
+        for (index, (_, wallet, lamports)) in self.commitments.iter().enumerate() {
+            let owed = if presale.is_finalized {
+                presale.tokens_owed(*lamports)
+            } else {
+                0
+            };
+            // Fully claimed commitments are closed
+            let claimed = match svm.account(&self.commitment(index)) {
+                Some(_) => {
+                    svm.get::<Commitment>(&self.commitment(index))
+                        .claimed_amount
+                }
+                None => owed,
+            };
+            prop_assert!(claimed <= owed);
+            let slot = self.wallets.iter().position(|w| w == wallet).unwrap();
+            claimed_by_wallet[slot] += claimed;
+            total_claimed += claimed;
+            total_owed += owed;
+        }
+        for (wallet, claimed) in self.wallets.iter().zip(&claimed_by_wallet) {
+            prop_assert_eq!(
+                svm.token_balance(&self.keys.token_account(wallet)),
+                *claimed
+            );
+        }
+        prop_assert_eq!(vault + creator + total_claimed, TOKENS_FOR_SALE);
+
+        // Allocations at the hard-cap price never sell more than the share raised, and lose
+        // under a token each to rounding; the rest of the allocation stays unsold
+        if presale.is_finalized {
+            let sold = (presale.tokens_for_sale as u128 * presale.total_sol_committed as u128
+                / presale.hard_cap as u128) as u64;
+            prop_assert!(total_owed <= sold);
+            prop_assert!(sold - total_owed <= self.commitments.len() as u64);
+        }
+        Ok(())
+    }
+}
+
+proptest! {
+    #[test]
+    fn presale_conserves_sol_and_tokens(
+        terms in presale_terms(),
+        ops in presale_ops(),
+    ) {
+        let mut svm = setup_with_fees(terms.presale_fee_bps, 0, 0);
+        let keys = presale_keys(&mut svm, &spl_token::ID);
+        let args = args::InitializePresale {
+            hard_cap: terms.hard_cap,
+            soft_cap: terms.soft_cap,
+            tokens_for_sale: terms.tokens_for_sale,
+            vesting: VestingSchedule {
+                tge_unlock_bps: terms.vesting.0,
+                cliff_duration: terms.vesting.1,
+                vesting_duration: terms.vesting.2,
+            },
+            ..presale_args(&svm)
+        };
+        svm.process(&instructions::initialize_presale(&keys, args)).unwrap();
+        open(&mut svm, &keys);
+        let wallets = (0..CLAIM_WALLETS).map(|_| svm.new_wallet(SOL)).collect();
+        let mut model = PresaleModel { keys, wallets, commitments: vec![], refunded: 0 };
+
+        for op in &ops {
+            model.apply(&mut svm, op)?;
+            model.check(&svm)?;
+        }
+    }
+}
+
+// ─── Dark pool ───
+
+#[derive(Clone, Debug)]
+enum DarkPoolOp {
+    Place {
+        maker: usize,
+        side: u8,
+        base_amount: u64,
+        quote_amount: u64,
+        /// Percentage of what the order needs that the maker escrows.
+        escrow_pct: u64,
+        expires_in: Option<i64>,
+    },
+    Fill {
+        order: usize,
+        taker: usize,
+        fill_bps: u64,
+    },
+    Cancel(usize),
+    Expire(usize),
+    Warp(i64),
+}
+
+fn dark_pool_op() -> impl Strategy<Value = DarkPoolOp> {
+    prop_oneof![
+        3 => (
+            0..TRADERS,
+            0..2u8,
+            1..=50 * UNIT,
+            1..=100 * UNIT,
+            50..=150u64,
+            prop::option::of(1..3_600i64),
+        )
+            .prop_map(|(maker, side, base_amount, quote_amount, escrow_pct, expires_in)| {
+                DarkPoolOp::Place { maker, side, base_amount, quote_amount, escrow_pct, expires_in }
+            }),
+        5 => (any::<usize>(), 0..TRADERS, 1..=10_000u64)
+            .prop_map(|(order, taker, fill_bps)| DarkPoolOp::Fill { order, taker, fill_bps }),
+        1 => any::<usize>().prop_map(DarkPoolOp::Cancel),
+        1 => any::<usize>().prop_map(DarkPoolOp::Expire),
+        1 => (0..1_800i64).prop_map(DarkPoolOp::Warp),
+    ]
+}
+
+/// The terms an order was placed under, known only to its maker and takers.
+struct PlacedOrder {
+    maker: Pubkey,
+    secret: [u8; 32],
+    side: u8,
+    base_amount: u64,
+    quote_amount: u64,
+    order_hash: [u8; 32],
+}
+
+struct DarkPoolModel {
+    market: Market,
+    traders: Vec<Pubkey>,
+    orders: Vec<PlacedOrder>,
+    fees: u64,
+}
+
+impl DarkPoolModel {
+    fn address(&self, order: &PlacedOrder) -> Pubkey {
+        pda::dark_order(&self.market.dark_pool(), &order.order_hash).0
+    }
+
+    fn apply(&mut self, svm: &mut Svm, op: &DarkPoolOp) {
+        match *op {
+            DarkPoolOp::Place {
+                maker,
+                side,
+                base_amount,
+                quote_amount,
+                escrow_pct,
+                expires_in,
+            } => {
+                let maker = self.traders[maker];
+                let secret: [u8; 32] = (self.orders.len() as u64 + 1)
+                    .to_le_bytes()
+                    .repeat(4)
+                    .try_into()
+                    .unwrap();
+                let order_hash = dark_order_hash(&secret, side, base_amount, quote_amount, &maker);
+                // Buyers also escrow the largest maker fee they could be charged
+                let (escrow_base, escrow_quote) = match side {
+                    0 => (base_amount * escrow_pct / 100, 0),
+                    _ => (0, quote_amount * 11 / 10 * escrow_pct / 100),
+                };
+                let place = instructions::place_dark_order(
+                    &maker,
+                    &self.market,
+                    args::PlaceDarkOrder {
+                        order_hash,
+                        escrow_base,
+                        escrow_quote,
+                        expires_at: expires_in.map_or(0, |seconds| svm.now() + seconds),
+                    },
+                );
+                if svm.process(&place).is_ok() {
+                    self.orders.push(PlacedOrder {
+                        maker,
+                        secret,
+                        side,
+                        base_amount,
+                        quote_amount,
+                        order_hash,
+                    });
+                }
+            }
+            DarkPoolOp::Fill {
+                order,
+                taker,
+                fill_bps,
+            } if !self.orders.is_empty() => {
+                let order = &self.orders[order % self.orders.len()];
+                let fill_base = (order.base_amount * fill_bps / 10_000).max(1);
+                let fill = instructions::fill_dark_order(
+                    &self.traders[taker],
+                    &self.market,
+                    &order.maker,
+                    args::FillDarkOrder {
+                        secret: order.secret,
+                        side: order.side,
+                        base_amount: order.base_amount,
+                        quote_amount: order.quote_amount,
+                        fill_base,
+                    },
+                );
+                if svm.process(&fill).is_ok() {
+                    let config: ProtocolConfig = svm.get(&pda::protocol_config().0);
+                    let fill_quote = DarkOrder::fill_quote(
+                        order.side,
+                        order.base_amount,
+                        order.quote_amount,
+                        fill_base,
+                    )
+                    .unwrap();
+                    self.fees += ProtocolConfig::fee(fill_quote, config.taker_fee_bps)
+                        + ProtocolConfig::fee(fill_quote, config.maker_fee_bps);
+                }
+            }
+            DarkPoolOp::Cancel(order) if !self.orders.is_empty() => {
+                let order = &self.orders[order % self.orders.len()];
+                let cancel =
+                    instructions::cancel_dark_order(&order.maker, &self.market, &order.order_hash);
+                let _ = svm.process(&cancel);
+            }
+            DarkPoolOp::Expire(order) if !self.orders.is_empty() => {
+                let order = &self.orders[order % self.orders.len()];
+                let cranker = self.traders[0];
+                let expire = instructions::expire_dark_order(
+                    &cranker,
+                    &self.market,
+                    &order.maker,
+                    &order.order_hash,
+                );
+                let _ = svm.process(&expire);
+            }
+            DarkPoolOp::Warp(seconds) => svm.warp(seconds),
+            DarkPoolOp::Fill { .. } | DarkPoolOp::Cancel(_) | DarkPoolOp::Expire(_) => {}
+        }
+    }
+
+    fn check(&self, svm: &Svm) -> Result<(), TestCaseError> {
+        let dark_pool = self.market.dark_pool();
+        let base_vault = svm.token_balance(&pda::dp_base_vault(&dark_pool).0);
+        let quote_vault = svm.token_balance(&pda::dp_quote_vault(&dark_pool).0);
+        let fee_vault = svm.token_balance(&pda::dp_fee_vault(&dark_pool).0);
+
+        // Tokens: nothing is minted or burned
+        let supply = TRADER_BALANCE * self.traders.len() as u64;
+        let base: u64 = self
+            .traders
+            .iter()
+            .map(|trader| svm.token_balance(&self.market.base_account(trader)))
+            .sum();
+        let quote: u64 = self
+            .traders
+            .iter()
+            .map(|trader| svm.token_balance(&self.market.quote_account(trader)))
+            .sum();
+        prop_assert_eq!(base + base_vault, supply);
+        prop_assert_eq!(quote + quote_vault + fee_vault, supply);
+        prop_assert_eq!(fee_vault, self.fees);
+
+        // The vaults hold exactly the escrow of live orders, which closed orders have returned
+        let mut live = 0;
+        let (mut escrowed_base, mut escrowed_quote) = (0u64, 0u64);
+        for order in &self.orders {
+            if svm.account(&self.address(order)).is_none() {
+                continue;
+            }
+            let state: DarkOrder = svm.get(&self.address(order));
+            live += 1;
+            escrowed_base += state.remaining_base;
+            escrowed_quote += state.remaining_quote;
+            prop_assert!(state.remaining_base <= state.escrow_base);
+            prop_assert!(state.remaining_quote <= state.escrow_quote);
+            prop_assert!(state.filled_base <= order.base_amount);
+
+            // Makers always trade at their price or better
+            let paid = state.filled_quote as u128 * order.base_amount as u128;
+            let at_price = state.filled_base as u128 * order.quote_amount as u128;
+            match order.side {
+                0 => prop_assert!(paid >= at_price),
+                _ => prop_assert!(paid <= at_price),
+            }
+        }
+        prop_assert_eq!(base_vault, escrowed_base);
+        prop_assert_eq!(quote_vault, escrowed_quote);
+        let pool: DarkPool = svm.get(&dark_pool);
+        prop_assert_eq!(pool.open_order_count, live);
+        Ok(())
+    }
+}
+
+proptest! {
+    #[test]
+    fn dark_pool_conserves_escrow_and_fees(
+        taker_fee_bps in 0..=1_000u16,
+        maker_fee_bps in 0..=1_000u16,
+        ops in prop::collection::vec(dark_pool_op(), 1..40),
+    ) {
+        let mut svm = setup_with_fees(0, taker_fee_bps, maker_fee_bps);
+        let (market, _) = create_market(&mut svm, &spl_token::ID);
+        let traders = (0..TRADERS)
+            .map(|_| trader(&mut svm, &market, TRADER_BALANCE, TRADER_BALANCE))
+            .collect();
+        let mut model = DarkPoolModel { market, traders, orders: vec![], fees: 0 };
+
+        for op in &ops {
+            model.apply(&mut svm, op);
+            model.check(&svm)?;
+        }
+    }
+}
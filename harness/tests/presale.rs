@@ -0,0 +1,984 @@
+mod common;
+
+use anchor_lang::prelude::{Pubkey, Rent};
+use anchor_spl::token::spl_token;
+use anon_presale::merkle;
+use anon_presale::{Commitment, Presale, PresaleError, WhitelistNullifier};
+use ark_std::rand::rngs::StdRng;
+use ark_std::rand::SeedableRng;
+use common::*;
+use dara_client::{
+    args, instructions, pda, ContributionLimits, Groth16Proof, VestingSchedule, Whitelist,
+};
+use dara_harness::{assert_error, Svm};
+use dara_prover::{prove, ClaimWitness};
+
+fn started_presale(svm: &mut Svm, args: args::InitializePresale) -> dara_client::PresaleKeys {
+    let keys = create_presale(svm, args);
+    open(svm, &keys);
+    keys
+}
+
+// ─── Initialize ───
+
+#[test]
+fn initialize_escrows_the_allocation() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = create_presale(&mut svm, args);
+
+    let presale: Presale = svm.get(&keys.address());
+    assert_eq!(presale.creator, keys.creator);
+    assert_eq!(presale.tokens_for_sale, TOKENS_FOR_SALE);
+    assert_eq!(presale.hard_cap, 10 * SOL);
+    assert!(!presale.is_finalized);
+    assert_eq!(
+        svm.token_balance(&pda::token_vault(&keys.address()).0),
+        TOKENS_FOR_SALE
+    );
+    assert_eq!(svm.token_balance(&keys.token_account(&keys.creator)), 0);
+}
+
+#[test]
+fn initialize_rejects_invalid_terms() {
+    let mut svm = setup();
+    let base = presale_args(&svm);
+    let cases = [
+        (
+            args::InitializePresale {
+                end_time: base.start_time,
+                ..presale_args(&svm)
+            },
+            PresaleError::InvalidTimeRange,
+        ),
+        (
+            args::InitializePresale {
+                claim_window: -1,
+                ..presale_args(&svm)
+            },
+            PresaleError::InvalidTimeRange,
+        ),
+        (
+            // The creator could sweep the last second of vesting
+            args::InitializePresale {
+                claim_window: 4_999,
+                vesting: VestingSchedule {
+                    tge_unlock_bps: 0,
+                    cliff_duration: 1_000,
+                    vesting_duration: 4_000,
+                },
+                ..presale_args(&svm)
+            },
+            PresaleError::ClaimWindowTooShort,
+        ),
+        (
+            args::InitializePresale {
+                vesting: VestingSchedule {
+                    tge_unlock_bps: 10_001,
+                    ..VestingSchedule::default()
+                },
+                ..presale_args(&svm)
+            },
+            PresaleError::InvalidVestingSchedule,
+        ),
+        (
+            args::InitializePresale {
+                vesting: VestingSchedule {
+                    cliff_duration: -1,
+                    ..VestingSchedule::default()
+                },
+                ..presale_args(&svm)
+            },
+            PresaleError::InvalidVestingSchedule,
+        ),
+        (
+            args::InitializePresale {
+                limits: ContributionLimits {
+                    min_commitment: 2 * SOL,
+                    max_commitment: SOL,
+                    max_commitments: 0,
+                },
+                ..presale_args(&svm)
+            },
+            PresaleError::InvalidContributionLimits,
+        ),
+        (
+            args::InitializePresale {
+                denomination: 11 * SOL,
+                ..presale_args(&svm)
+            },
+            PresaleError::InvalidDenomination,
+        ),
+        (
+            args::InitializePresale {
+                hard_cap: 0,
+                soft_cap: 0,
+                ..presale_args(&svm)
+            },
+            PresaleError::InvalidAmount,
+        ),
+        (
+            args::InitializePresale {
+                soft_cap: 11 * SOL,
+                ..presale_args(&svm)
+            },
+            PresaleError::InvalidSoftCap,
+        ),
+        (
+            args::InitializePresale {
+                tokens_for_sale: 0,
+                ..presale_args(&svm)
+            },
+            PresaleError::InvalidAmount,
+        ),
+    ];
+
+    for (args, expected) in cases {
+        let keys = presale_keys(&mut svm, &spl_token::ID);
+        assert_error(
+            svm.process(&instructions::initialize_presale(&keys, args)),
+            expected,
+        );
+    }
+}
+
+// ─── Commit ───
+
+#[test]
+fn commit_moves_sol_to_the_presale() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let before = svm.lamports(&keys.address());
+
+    commit(&mut svm, &keys, [1; 32], &Pubkey::new_unique(), 3 * SOL).unwrap();
+    commit(&mut svm, &keys, [2; 32], &Pubkey::new_unique(), 2 * SOL).unwrap();
+
+    let presale: Presale = svm.get(&keys.address());
+    assert_eq!(presale.total_sol_committed, 5 * SOL);
+    assert_eq!(presale.commitment_count, 2);
+    assert_eq!(svm.lamports(&keys.address()), before + 5 * SOL);
+}
+
+#[test]
+fn commit_only_inside_the_window() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = create_presale(&mut svm, args);
+
+    assert_error(
+        commit(&mut svm, &keys, [1; 32], &Pubkey::new_unique(), SOL),
+        PresaleError::NotStarted,
+    );
+
+    let presale: Presale = svm.get(&keys.address());
+    svm.warp_to(presale.end_time);
+    commit(&mut svm, &keys, [1; 32], &Pubkey::new_unique(), SOL).unwrap();
+
+    svm.warp(1);
+    assert_error(
+        commit(&mut svm, &keys, [2; 32], &Pubkey::new_unique(), SOL),
+        PresaleError::Ended,
+    );
+}
+
+#[test]
+fn commit_enforces_caps_and_limits() {
+    let mut svm = setup();
+    let args = args::InitializePresale {
+        limits: ContributionLimits {
+            min_commitment: SOL,
+            max_commitment: 4 * SOL,
+            max_commitments: 3,
+        },
+        ..presale_args(&svm)
+    };
+    let keys = started_presale(&mut svm, args);
+    let wallet = Pubkey::new_unique();
+
+    assert_error(
+        commit(&mut svm, &keys, [1; 32], &wallet, 0),
+        PresaleError::InvalidAmount,
+    );
+    assert_error(
+        commit(&mut svm, &keys, [1; 32], &wallet, SOL - 1),
+        PresaleError::BelowMinCommitment,
+    );
+    assert_error(
+        commit(&mut svm, &keys, [1; 32], &wallet, 4 * SOL + 1),
+        PresaleError::AboveMaxCommitment,
+    );
+
+    commit(&mut svm, &keys, [1; 32], &wallet, 4 * SOL).unwrap();
+    commit(&mut svm, &keys, [2; 32], &wallet, 4 * SOL).unwrap();
+    assert_error(
+        commit(&mut svm, &keys, [3; 32], &wallet, 3 * SOL),
+        PresaleError::HardCapExceeded,
+    );
+    commit(&mut svm, &keys, [3; 32], &wallet, 2 * SOL).unwrap();
+    assert_error(
+        commit(&mut svm, &keys, [4; 32], &wallet, SOL),
+        PresaleError::MaxCommitmentsReached,
+    );
+}
+
+#[test]
+fn denominated_commits_are_exactly_one_unit() {
+    let mut svm = setup();
+    let args = args::InitializePresale {
+        denomination: SOL,
+        ..presale_args(&svm)
+    };
+    let keys = started_presale(&mut svm, args);
+
+    assert_error(
+        commit(
+            &mut svm,
+            &keys,
+            [1; 32],
+            &Pubkey::new_unique(),
+            SOL + SOL / 2,
+        ),
+        PresaleError::InvalidDenomination,
+    );
+    // Several units in one commitment would stand out from everyone else's
+    assert_error(
+        commit(&mut svm, &keys, [1; 32], &Pubkey::new_unique(), 2 * SOL),
+        PresaleError::InvalidDenomination,
+    );
+    commit(&mut svm, &keys, [1; 32], &Pubkey::new_unique(), SOL).unwrap();
+}
+
+#[test]
+fn commit_after_finalize_or_cancel() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+
+    // Reaching the hard cap allows finalizing before the end
+    commit(&mut svm, &keys, [1; 32], &Pubkey::new_unique(), 10 * SOL).unwrap();
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+    assert_error(
+        commit(&mut svm, &keys, [2; 32], &Pubkey::new_unique(), SOL),
+        PresaleError::AlreadyFinalized,
+    );
+
+    let args = presale_args(&svm);
+    let keys = create_presale(&mut svm, args);
+    svm.process(&instructions::cancel_presale(&keys)).unwrap();
+    open(&mut svm, &keys);
+    assert_error(
+        commit(&mut svm, &keys, [1; 32], &Pubkey::new_unique(), SOL),
+        PresaleError::Cancelled,
+    );
+}
+
+// ─── Whitelist ───
+
+#[test]
+fn whitelisted_presale_admits_each_leaf_once() {
+    let mut svm = setup();
+    let admin = svm.upgrade_authority();
+    set_claim_verifying_key(&mut svm, &admin).unwrap();
+    let args = presale_args(&svm);
+    let keys = create_presale(&mut svm, args);
+    // The holder lists a leaf bound to the burner they will commit from, never the burner itself
+    let burner = svm.new_wallet(10 * SOL);
+    let (nullifier, secret) = ([3; 32], [4; 32]);
+    let leaf = merkle::leaf_hash(&nullifier, &secret, &burner).unwrap();
+    let whitelist = Whitelist::new(&[[1; 32], leaf, [2; 32]]).unwrap();
+
+    let set_root = instructions::set_whitelist_root(
+        &keys,
+        args::SetWhitelistRoot {
+            whitelist_root: whitelist.root(),
+        },
+    );
+    svm.process(&set_root).unwrap();
+    open(&mut svm, &keys);
+
+    let (leaf_index, path) = whitelist.path(&leaf).unwrap();
+    let witness = ClaimWitness {
+        nullifier,
+        secret,
+        claim_wallet: burner,
+        leaf_index,
+        path,
+        root: whitelist.root(),
+    };
+    let membership = prove(&dev_keys().0, &witness, &mut StdRng::seed_from_u64(1)).unwrap();
+    let commit_as = |participant: &Pubkey, secret: [u8; 32], proof: Option<Groth16Proof>| {
+        instructions::commit_to_presale(
+            participant,
+            &keys.address(),
+            args::CommitToPresale {
+                commitment_hash: dara_client::commitment_hash(&secret, &Pubkey::new_unique()),
+                sol_amount: SOL,
+                whitelist_nullifier_hash: membership.nullifier_hash,
+                whitelist_proof: proof,
+            },
+        )
+    };
+
+    assert_error(
+        svm.process(&commit_as(&burner, [1; 32], None)),
+        PresaleError::NotWhitelisted,
+    );
+    // The proof is bound to the burner, so it cannot be lifted into someone else's commit
+    let front_runner = svm.new_wallet(10 * SOL);
+    assert_error(
+        svm.process(&commit_as(
+            &front_runner,
+            [1; 32],
+            Some(membership.proof.clone()),
+        )),
+        PresaleError::InvalidZkProof,
+    );
+    svm.process(&commit_as(&burner, [1; 32], Some(membership.proof.clone())))
+        .unwrap();
+    let spent = pda::whitelist_nullifier(&keys.address(), &membership.nullifier_hash).0;
+    let record: WhitelistNullifier = svm.get(&spent);
+    assert_eq!(record.payer, burner);
+    assert_error(
+        svm.process(&commit_as(&burner, [2; 32], Some(membership.proof))),
+        PresaleError::WhitelistNullifierUsed,
+    );
+
+    // Spent nullifiers outlive the commit window, and must be gone before the presale closes
+    let close_nullifier = instructions::close_whitelist_nullifier(
+        &keys.address(),
+        &burner,
+        args::CloseWhitelistNullifier {
+            nullifier_hash: membership.nullifier_hash,
+        },
+    );
+    assert_error(
+        svm.process(&close_nullifier),
+        PresaleError::PresaleStillActive,
+    );
+    svm.process(&instructions::cancel_presale(&keys)).unwrap();
+    let mut presale: Presale = svm.get(&keys.address());
+    assert_eq!(presale.whitelist_nullifier_count, 1);
+    presale.vault_closed = true;
+    presale.settled_count = presale.commitment_count;
+    svm.set(&keys.address(), &presale);
+    assert_error(
+        svm.process(&instructions::close_presale(&keys)),
+        PresaleError::WhitelistNullifiersOutstanding,
+    );
+
+    let rent = svm.lamports(&spent);
+    let before = svm.lamports(&burner);
+    svm.process(&close_nullifier).unwrap();
+    assert!(svm.account(&spent).is_none());
+    assert_eq!(svm.lamports(&burner), before + rent);
+    svm.process(&instructions::close_presale(&keys)).unwrap();
+}
+
+#[test]
+fn open_presale_takes_no_whitelist_nullifier() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let burner = svm.new_wallet(10 * SOL);
+    let commit = instructions::commit_to_presale(
+        &burner,
+        &keys.address(),
+        args::CommitToPresale {
+            commitment_hash: [1; 32],
+            sol_amount: SOL,
+            whitelist_nullifier_hash: [2; 32],
+            whitelist_proof: Some(Groth16Proof {
+                a: [0; 64],
+                b: [0; 128],
+                c: [0; 64],
+            }),
+        },
+    );
+    assert_error(svm.process(&commit), PresaleError::NoWhitelist);
+}
+
+#[test]
+fn whitelist_locks_at_start() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = create_presale(&mut svm, args);
+    let set_root = |root| {
+        instructions::set_whitelist_root(
+            &keys,
+            args::SetWhitelistRoot {
+                whitelist_root: root,
+            },
+        )
+    };
+
+    let impostor = svm.new_wallet(SOL);
+    let as_impostor = swap_account(set_root([1; 32]), &keys.creator, &impostor);
+    assert_error(svm.process(&as_impostor), PresaleError::Unauthorized);
+
+    svm.process(&set_root([1; 32])).unwrap();
+    open(&mut svm, &keys);
+    assert_error(
+        svm.process(&set_root([0; 32])),
+        PresaleError::WhitelistLocked,
+    );
+
+    let args = presale_args(&svm);
+    let keys = create_presale(&mut svm, args);
+    svm.process(&instructions::cancel_presale(&keys)).unwrap();
+    let set_root = instructions::set_whitelist_root(
+        &keys,
+        args::SetWhitelistRoot {
+            whitelist_root: [1; 32],
+        },
+    );
+    assert_error(svm.process(&set_root), PresaleError::Cancelled);
+}
+
+// ─── Finalize ───
+
+#[test]
+fn finalize_pays_the_creator() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    commit(&mut svm, &keys, [1; 32], &Pubkey::new_unique(), 3 * SOL).unwrap();
+    end(&mut svm, &keys);
+
+    let creator_before = svm.lamports(&keys.creator);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+
+    let presale: Presale = svm.get(&keys.address());
+    assert!(presale.is_finalized);
+    assert_eq!(presale.finalized_at, svm.now());
+    assert_eq!(svm.lamports(&keys.creator), creator_before + 3 * SOL);
+    let rent = Rent::default().minimum_balance(svm.account(&keys.address()).unwrap().data.len());
+    assert_eq!(svm.lamports(&keys.address()), rent);
+}
+
+#[test]
+fn finalize_waits_for_the_end_and_soft_cap() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    commit(&mut svm, &keys, [1; 32], &Pubkey::new_unique(), SOL).unwrap();
+
+    let impostor = svm.new_wallet(SOL);
+    let as_impostor = swap_account(
+        instructions::finalize_presale(&keys),
+        &keys.creator,
+        &impostor,
+    );
+    assert_error(svm.process(&as_impostor), PresaleError::Unauthorized);
+
+    assert_error(
+        svm.process(&instructions::finalize_presale(&keys)),
+        PresaleError::PresaleStillActive,
+    );
+    end(&mut svm, &keys);
+    assert_error(
+        svm.process(&instructions::finalize_presale(&keys)),
+        PresaleError::SoftCapNotReached,
+    );
+}
+
+#[test]
+fn finalize_only_once() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    commit(&mut svm, &keys, [1; 32], &Pubkey::new_unique(), 2 * SOL).unwrap();
+    end(&mut svm, &keys);
+
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+    svm.warp(1);
+    assert_error(
+        svm.process(&instructions::finalize_presale(&keys)),
+        PresaleError::AlreadyFinalized,
+    );
+    assert_error(
+        svm.process(&instructions::cancel_presale(&keys)),
+        PresaleError::AlreadyFinalized,
+    );
+
+    let args = presale_args(&svm);
+    let keys = create_presale(&mut svm, args);
+    svm.process(&instructions::cancel_presale(&keys)).unwrap();
+    end(&mut svm, &keys);
+    assert_error(
+        svm.process(&instructions::finalize_presale(&keys)),
+        PresaleError::Cancelled,
+    );
+}
+
+// ─── Claim ───
+
+#[test]
+fn claims_are_priced_against_the_hard_cap() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+    commit(&mut svm, &keys, [1; 32], &alice, 3 * SOL).unwrap();
+    commit(&mut svm, &keys, [2; 32], &bob, SOL).unwrap();
+
+    assert_error(
+        claim(&mut svm, &keys, [1; 32], &alice),
+        PresaleError::NotFinalized,
+    );
+
+    end(&mut svm, &keys);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+    claim(&mut svm, &keys, [1; 32], &alice).unwrap();
+    claim(&mut svm, &keys, [2; 32], &bob).unwrap();
+
+    assert_eq!(
+        svm.token_balance(&keys.token_account(&alice)),
+        TOKENS_FOR_SALE * 3 / 10
+    );
+    assert_eq!(
+        svm.token_balance(&keys.token_account(&bob)),
+        TOKENS_FOR_SALE / 10
+    );
+    let commitment = pda::commitment(
+        &keys.address(),
+        &dara_client::commitment_hash(&[1; 32], &alice),
+    );
+    assert!(
+        svm.account(&commitment.0).is_none(),
+        "settled commitment closes"
+    );
+    let presale: Presale = svm.get(&keys.address());
+    assert_eq!(presale.settled_count, 2);
+}
+
+#[test]
+fn claim_requires_the_commitment_preimage() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let wallet = Pubkey::new_unique();
+    commit(&mut svm, &keys, [1; 32], &wallet, 2 * SOL).unwrap();
+    end(&mut svm, &keys);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+
+    // Wrong secret, pointed at the real commitment
+    let real = pda::commitment(
+        &keys.address(),
+        &dara_client::commitment_hash(&[1; 32], &wallet),
+    )
+    .0;
+    let forged = pda::commitment(
+        &keys.address(),
+        &dara_client::commitment_hash(&[9; 32], &wallet),
+    )
+    .0;
+    let claimer = svm.new_wallet(SOL);
+    let ix = instructions::claim_tokens(
+        &claimer,
+        &keys,
+        &wallet,
+        &claimer,
+        args::ClaimTokens { secret: [9; 32] },
+    );
+    assert_error(
+        svm.process(&swap_account(ix, &forged, &real)),
+        PresaleError::InvalidProof,
+    );
+
+    // Right secret, wrong wallet
+    let thief = Pubkey::new_unique();
+    let stolen = pda::commitment(
+        &keys.address(),
+        &dara_client::commitment_hash(&[1; 32], &thief),
+    )
+    .0;
+    let ix = instructions::claim_tokens(
+        &claimer,
+        &keys,
+        &thief,
+        &claimer,
+        args::ClaimTokens { secret: [1; 32] },
+    );
+    assert_error(
+        svm.process(&swap_account(ix, &stolen, &real)),
+        PresaleError::InvalidProof,
+    );
+}
+
+#[test]
+fn claim_rejects_another_presales_commitment() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let args = presale_args(&svm);
+    let other = create_presale(&mut svm, args);
+    open(&mut svm, &other);
+    let wallet = Pubkey::new_unique();
+    commit(&mut svm, &keys, [1; 32], &wallet, 2 * SOL).unwrap();
+    commit(&mut svm, &other, [1; 32], &wallet, 2 * SOL).unwrap();
+    end(&mut svm, &keys);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+
+    let hash = dara_client::commitment_hash(&[1; 32], &wallet);
+    let ours = pda::commitment(&keys.address(), &hash).0;
+    let theirs = pda::commitment(&other.address(), &hash).0;
+    let claimer = svm.new_wallet(SOL);
+    let ix = instructions::claim_tokens(
+        &claimer,
+        &keys,
+        &wallet,
+        &claimer,
+        args::ClaimTokens { secret: [1; 32] },
+    );
+    assert_error(
+        svm.process(&swap_account(ix, &ours, &theirs)),
+        PresaleError::InvalidCommitment,
+    );
+}
+
+#[test]
+fn claim_rejects_a_settled_commitment() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let wallet = Pubkey::new_unique();
+    commit(&mut svm, &keys, [1; 32], &wallet, 2 * SOL).unwrap();
+    end(&mut svm, &keys);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+
+    // Settled commitments close, so only a stale account could still say so
+    let address = pda::commitment(
+        &keys.address(),
+        &dara_client::commitment_hash(&[1; 32], &wallet),
+    )
+    .0;
+    let mut commitment: Commitment = svm.get(&address);
+    commitment.is_claimed = true;
+    svm.set(&address, &commitment);
+    assert_error(
+        claim(&mut svm, &keys, [1; 32], &wallet),
+        PresaleError::AlreadyClaimed,
+    );
+}
+
+#[test]
+fn vesting_releases_over_time() {
+    let mut svm = setup();
+    let args = args::InitializePresale {
+        vesting: VestingSchedule {
+            tge_unlock_bps: 2_000,
+            cliff_duration: 1_000,
+            vesting_duration: 4_000,
+        },
+        ..presale_args(&svm)
+    };
+    let keys = started_presale(&mut svm, args);
+    let wallet = Pubkey::new_unique();
+    let account = keys.token_account(&wallet);
+    commit(&mut svm, &keys, [1; 32], &wallet, 2 * SOL).unwrap();
+    end(&mut svm, &keys);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+
+    claim(&mut svm, &keys, [1; 32], &wallet).unwrap();
+    assert_eq!(svm.token_balance(&account), TOKENS_FOR_SALE / 25);
+    assert_error(
+        claim(&mut svm, &keys, [1; 32], &wallet),
+        PresaleError::NothingToClaim,
+    );
+
+    // Halfway through the linear part
+    svm.warp(3_000);
+    claim(&mut svm, &keys, [1; 32], &wallet).unwrap();
+    assert_eq!(svm.token_balance(&account), TOKENS_FOR_SALE * 3 / 25);
+
+    svm.warp(10_000);
+    claim(&mut svm, &keys, [1; 32], &wallet).unwrap();
+    assert_eq!(svm.token_balance(&account), TOKENS_FOR_SALE / 5);
+    let presale: Presale = svm.get(&keys.address());
+    assert_eq!(presale.settled_count, 1);
+}
+
+#[test]
+fn nothing_vests_before_the_cliff_without_a_tge_unlock() {
+    let mut svm = setup();
+    let args = args::InitializePresale {
+        vesting: VestingSchedule {
+            tge_unlock_bps: 0,
+            cliff_duration: 1_000,
+            vesting_duration: 0,
+        },
+        ..presale_args(&svm)
+    };
+    let keys = started_presale(&mut svm, args);
+    let wallet = Pubkey::new_unique();
+    commit(&mut svm, &keys, [1; 32], &wallet, 2 * SOL).unwrap();
+    end(&mut svm, &keys);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+
+    assert_error(
+        claim(&mut svm, &keys, [1; 32], &wallet),
+        PresaleError::NothingToClaim,
+    );
+    svm.warp(1_000);
+    claim(&mut svm, &keys, [1; 32], &wallet).unwrap();
+    assert_eq!(
+        svm.token_balance(&keys.token_account(&wallet)),
+        TOKENS_FOR_SALE / 5
+    );
+}
+
+// ─── Refund ───
+
+#[test]
+fn failed_presale_refunds() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let wallet = Pubkey::new_unique();
+    commit(&mut svm, &keys, [1; 32], &wallet, SOL).unwrap();
+
+    assert_error(
+        refund(&mut svm, &keys, [1; 32], &wallet),
+        PresaleError::PresaleStillActive,
+    );
+    end(&mut svm, &keys);
+    let impostor = Pubkey::new_unique();
+    let real = pda::commitment(
+        &keys.address(),
+        &dara_client::commitment_hash(&[1; 32], &wallet),
+    )
+    .0;
+    let claimer = svm.new_wallet(SOL);
+    let ix = instructions::refund_commitment(
+        &claimer,
+        &keys.address(),
+        &impostor,
+        &claimer,
+        args::RefundCommitment { secret: [1; 32] },
+    );
+    let forged = pda::commitment(
+        &keys.address(),
+        &dara_client::commitment_hash(&[1; 32], &impostor),
+    )
+    .0;
+    assert_error(
+        svm.process(&swap_account(ix, &forged, &real)),
+        PresaleError::InvalidProof,
+    );
+
+    refund(&mut svm, &keys, [1; 32], &wallet).unwrap();
+    assert_eq!(svm.lamports(&wallet), SOL);
+    assert!(svm.account(&real).is_none(), "refunded commitment closes");
+    let presale: Presale = svm.get(&keys.address());
+    assert_eq!(presale.settled_count, 1);
+}
+
+#[test]
+fn no_refunds_once_the_soft_cap_is_met() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let wallet = Pubkey::new_unique();
+    commit(&mut svm, &keys, [1; 32], &wallet, 2 * SOL).unwrap();
+    end(&mut svm, &keys);
+
+    assert_error(
+        refund(&mut svm, &keys, [1; 32], &wallet),
+        PresaleError::SoftCapReached,
+    );
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+    assert_error(
+        refund(&mut svm, &keys, [1; 32], &wallet),
+        PresaleError::AlreadyFinalized,
+    );
+}
+
+#[test]
+fn refund_rejects_a_settled_commitment() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let wallet = Pubkey::new_unique();
+    commit(&mut svm, &keys, [1; 32], &wallet, SOL).unwrap();
+    end(&mut svm, &keys);
+
+    let address = pda::commitment(
+        &keys.address(),
+        &dara_client::commitment_hash(&[1; 32], &wallet),
+    )
+    .0;
+    let mut commitment: Commitment = svm.get(&address);
+    commitment.is_claimed = true;
+    svm.set(&address, &commitment);
+    assert_error(
+        refund(&mut svm, &keys, [1; 32], &wallet),
+        PresaleError::AlreadyClaimed,
+    );
+}
+
+// ─── Cancel, withdraw and close ───
+
+#[test]
+fn cancelled_presale_refunds_at_any_time() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let wallet = Pubkey::new_unique();
+    commit(&mut svm, &keys, [1; 32], &wallet, 5 * SOL).unwrap();
+
+    let impostor = svm.new_wallet(SOL);
+    let as_impostor = swap_account(
+        instructions::cancel_presale(&keys),
+        &keys.creator,
+        &impostor,
+    );
+    assert_error(svm.process(&as_impostor), PresaleError::Unauthorized);
+
+    svm.process(&instructions::cancel_presale(&keys)).unwrap();
+    assert_eq!(
+        svm.token_balance(&keys.token_account(&keys.creator)),
+        TOKENS_FOR_SALE
+    );
+    assert_error(
+        svm.process(&instructions::cancel_presale(&keys)),
+        PresaleError::Cancelled,
+    );
+
+    refund(&mut svm, &keys, [1; 32], &wallet).unwrap();
+    assert_eq!(svm.lamports(&wallet), 5 * SOL);
+}
+
+#[test]
+fn close_after_every_commitment_settles() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+    commit(&mut svm, &keys, [1; 32], &alice, 2 * SOL).unwrap();
+    commit(&mut svm, &keys, [2; 32], &bob, SOL).unwrap();
+    end(&mut svm, &keys);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+
+    assert_error(
+        svm.process(&instructions::close_presale(&keys)),
+        PresaleError::VaultNotClosed,
+    );
+    claim(&mut svm, &keys, [1; 32], &alice).unwrap();
+    assert_error(
+        svm.process(&instructions::withdraw_remaining_tokens(&keys)),
+        PresaleError::ClaimsOutstanding,
+    );
+
+    claim(&mut svm, &keys, [2; 32], &bob).unwrap();
+    let creator_before = svm.lamports(&keys.creator);
+    svm.process(&instructions::withdraw_remaining_tokens(&keys))
+        .unwrap();
+    // 3 of the 10 SOL cap sold, so the creator gets the other 7/10 back
+    let unsold = TOKENS_FOR_SALE - TOKENS_FOR_SALE * 2 / 10 - TOKENS_FOR_SALE / 10;
+    assert_eq!(svm.token_balance(&keys.token_account(&keys.creator)), unsold);
+    assert!(svm.account(&pda::token_vault(&keys.address()).0).is_none());
+    assert!(svm.lamports(&keys.creator) > creator_before);
+
+    svm.process(&instructions::close_presale(&keys)).unwrap();
+    assert!(svm.account(&keys.address()).is_none());
+}
+
+#[test]
+fn claim_window_releases_unclaimed_tokens() {
+    let mut svm = setup();
+    let base = presale_args(&svm);
+    let args = args::InitializePresale {
+        claim_window: 1_000,
+        ..base
+    };
+    let keys = started_presale(&mut svm, args);
+    let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+    commit(&mut svm, &keys, [1; 32], &alice, 2 * SOL).unwrap();
+    commit(&mut svm, &keys, [2; 32], &bob, SOL).unwrap();
+    let stale = pda::commitment(&keys.address(), &dara_client::commitment_hash(&[2; 32], &bob)).0;
+
+    // Finalizing long after the sale ended still leaves participants the whole window
+    svm.warp_to(base.end_time + 5_000);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+    assert_error(
+        svm.process(&instructions::withdraw_remaining_tokens(&keys)),
+        PresaleError::ClaimsOutstanding,
+    );
+    svm.warp_to(base.end_time + 6_000);
+    claim(&mut svm, &keys, [1; 32], &alice).unwrap();
+    let sweeper = svm.new_wallet(SOL);
+    assert_error(
+        svm.process(&instructions::close_stale_commitment(&keys.address(), &stale, &sweeper)),
+        PresaleError::ClaimWindowOpen,
+    );
+    assert_error(
+        svm.process(&instructions::withdraw_remaining_tokens(&keys)),
+        PresaleError::ClaimsOutstanding,
+    );
+
+    svm.warp_to(base.end_time + 6_001);
+    assert_error(
+        claim(&mut svm, &keys, [2; 32], &bob),
+        PresaleError::ClaimWindowClosed,
+    );
+    svm.process(&instructions::withdraw_remaining_tokens(&keys))
+        .unwrap();
+    assert_eq!(
+        svm.token_balance(&keys.token_account(&keys.creator)),
+        TOKENS_FOR_SALE - TOKENS_FOR_SALE * 2 / 10
+    );
+    // The unclaimed commitment keeps the presale open, so it cannot be re-created under it
+    assert_error(
+        svm.process(&instructions::close_presale(&keys)),
+        PresaleError::ClaimsOutstanding,
+    );
+
+    // Anyone can settle it once the window has closed, taking its rent
+    let rent = svm.lamports(&stale);
+    let sweeper_before = svm.lamports(&sweeper);
+    svm.process(&instructions::close_stale_commitment(&keys.address(), &stale, &sweeper))
+        .unwrap();
+    assert!(svm.account(&stale).is_none());
+    assert_eq!(svm.lamports(&sweeper), sweeper_before + rent);
+    let presale: Presale = svm.get(&keys.address());
+    assert_eq!(presale.settled_count, presale.commitment_count);
+
+    svm.process(&instructions::close_presale(&keys)).unwrap();
+    assert!(svm.account(&keys.address()).is_none());
+}
+
+#[test]
+fn withdraw_from_an_unfinished_presale() {
+    let mut svm = setup();
+    let args = presale_args(&svm);
+    let keys = started_presale(&mut svm, args);
+    let wallet = Pubkey::new_unique();
+    commit(&mut svm, &keys, [1; 32], &wallet, SOL).unwrap();
+
+    assert_error(
+        svm.process(&instructions::withdraw_remaining_tokens(&keys)),
+        PresaleError::NotFinalized,
+    );
+    let impostor = svm.new_wallet(SOL);
+    let as_impostor = swap_account(
+        instructions::withdraw_remaining_tokens(&keys),
+        &keys.creator,
+        &impostor,
+    );
+    assert_error(svm.process(&as_impostor), PresaleError::Unauthorized);
+
+    // Ended below the soft cap: the tokens are free, the SOL is not
+    end(&mut svm, &keys);
+    svm.process(&instructions::withdraw_remaining_tokens(&keys))
+        .unwrap();
+    assert_error(
+        svm.process(&instructions::close_presale(&keys)),
+        PresaleError::ClaimsOutstanding,
+    );
+    let as_impostor = swap_account(instructions::close_presale(&keys), &keys.creator, &impostor);
+    assert_error(svm.process(&as_impostor), PresaleError::Unauthorized);
+
+    refund(&mut svm, &keys, [1; 32], &wallet).unwrap();
+    svm.process(&instructions::close_presale(&keys)).unwrap();
+}
@@ -0,0 +1,401 @@
+mod common;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_spl::token::spl_token;
+use anon_presale::{ProtocolConfig, ProtocolError, MAX_FEE_BPS};
+use common::*;
+use dara_client::{args, dark_order_hash, instructions, pda, Groth16Proof, PauseFlags};
+use dara_harness::{assert_error, Svm};
+
+fn config(svm: &Svm) -> ProtocolConfig {
+    svm.get(&pda::protocol_config().0)
+}
+
+fn update_fees(
+    presale_fee_bps: u16,
+    taker_fee_bps: u16,
+    maker_fee_bps: u16,
+    fee_recipient: Pubkey,
+) -> args::UpdateProtocolConfig {
+    args::UpdateProtocolConfig {
+        fee_recipient,
+        presale_fee_bps,
+        taker_fee_bps,
+        maker_fee_bps,
+    }
+}
+
+fn pause(svm: &mut Svm, paused: PauseFlags) {
+    let admin = config(svm).admin;
+    svm.process(&instructions::set_pause_flags(
+        &admin,
+        args::SetPauseFlags { paused },
+    ))
+    .unwrap();
+}
+
+#[test]
+fn only_the_upgrade_authority_initializes() {
+    let mut svm = Svm::new();
+    let init = |admin: &Pubkey, presale_fee_bps| {
+        instructions::initialize_protocol_config(
+            admin,
+            args::InitializeProtocolConfig {
+                fee_recipient: *admin,
+                presale_fee_bps,
+                taker_fee_bps: 0,
+                maker_fee_bps: 0,
+            },
+        )
+    };
+
+    let impostor = svm.new_wallet(10 * SOL);
+    assert_error(
+        svm.process(&init(&impostor, 0)),
+        ProtocolError::Unauthorized,
+    );
+
+    let admin = svm.upgrade_authority();
+    svm.airdrop(&admin, 10 * SOL);
+    assert_error(
+        svm.process(&init(&admin, MAX_FEE_BPS + 1)),
+        ProtocolError::FeeTooHigh,
+    );
+    svm.process(&init(&admin, MAX_FEE_BPS)).unwrap();
+    let config = config(&svm);
+    assert_eq!(config.admin, admin);
+    assert_eq!(config.presale_fee_bps, MAX_FEE_BPS);
+}
+
+#[test]
+fn admin_updates_fees_within_the_cap() {
+    let mut svm = setup();
+    let admin = svm.upgrade_authority();
+    let recipient = svm.new_wallet(SOL);
+
+    let impostor = svm.new_wallet(SOL);
+    assert_error(
+        svm.process(&instructions::update_protocol_config(
+            &impostor,
+            update_fees(0, 0, 0, impostor),
+        )),
+        ProtocolError::Unauthorized,
+    );
+    for fees in [
+        (MAX_FEE_BPS + 1, 0, 0),
+        (0, MAX_FEE_BPS + 1, 0),
+        (0, 0, MAX_FEE_BPS + 1),
+    ] {
+        assert_error(
+            svm.process(&instructions::update_protocol_config(
+                &admin,
+                update_fees(fees.0, fees.1, fees.2, recipient),
+            )),
+            ProtocolError::FeeTooHigh,
+        );
+    }
+
+    svm.process(&instructions::update_protocol_config(
+        &admin,
+        update_fees(100, 20, 10, recipient),
+    ))
+    .unwrap();
+    let config = config(&svm);
+    assert_eq!(config.fee_recipient, recipient);
+    assert_eq!(
+        (
+            config.presale_fee_bps,
+            config.taker_fee_bps,
+            config.maker_fee_bps
+        ),
+        (100, 20, 10)
+    );
+}
+
+#[test]
+fn admin_handover_takes_two_steps() {
+    let mut svm = setup();
+    let admin = svm.upgrade_authority();
+    let successor = svm.new_wallet(SOL);
+
+    // Nobody can accept before a proposal, including the would-be successor
+    assert_error(
+        svm.process(&instructions::accept_admin(&successor)),
+        ProtocolError::Unauthorized,
+    );
+    assert_error(
+        svm.process(&instructions::propose_admin(
+            &successor,
+            args::ProposeAdmin {
+                new_admin: successor,
+            },
+        )),
+        ProtocolError::Unauthorized,
+    );
+
+    svm.process(&instructions::propose_admin(
+        &admin,
+        args::ProposeAdmin {
+            new_admin: successor,
+        },
+    ))
+    .unwrap();
+    let bystander = svm.new_wallet(SOL);
+    assert_error(
+        svm.process(&instructions::accept_admin(&bystander)),
+        ProtocolError::Unauthorized,
+    );
+
+    // Proposing the default key withdraws the offer
+    svm.process(&instructions::propose_admin(
+        &admin,
+        args::ProposeAdmin {
+            new_admin: Pubkey::default(),
+        },
+    ))
+    .unwrap();
+    assert_error(
+        svm.process(&instructions::accept_admin(&successor)),
+        ProtocolError::Unauthorized,
+    );
+
+    svm.process(&instructions::propose_admin(
+        &admin,
+        args::ProposeAdmin {
+            new_admin: successor,
+        },
+    ))
+    .unwrap();
+    svm.process(&instructions::accept_admin(&successor))
+        .unwrap();
+    let config = config(&svm);
+    assert_eq!(config.admin, successor);
+    assert_eq!(config.pending_admin, Pubkey::default());
+    assert_error(
+        svm.process(&instructions::set_pause_flags(
+            &admin,
+            args::SetPauseFlags {
+                paused: PauseFlags::default(),
+            },
+        )),
+        ProtocolError::Unauthorized,
+    );
+}
+
+#[test]
+fn presale_fee_accrues_until_collected() {
+    let mut svm = setup_with_fees(250, 0, 0);
+    let admin = svm.upgrade_authority();
+    let recipient = svm.new_wallet(0);
+    assert_error(
+        svm.process(&instructions::collect_protocol_fees(&admin)),
+        ProtocolError::NothingToCollect,
+    );
+
+    let args = presale_args(&svm);
+    let keys = create_presale(&mut svm, args);
+    open(&mut svm, &keys);
+    commit(&mut svm, &keys, [1; 32], &keys.creator, 4 * SOL).unwrap();
+    end(&mut svm, &keys);
+    let creator_before = svm.lamports(&keys.creator);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+    assert_eq!(svm.lamports(&keys.creator) - creator_before, 3_900_000_000);
+
+    // Collection goes to whoever the config names when it runs
+    svm.process(&instructions::update_protocol_config(
+        &admin,
+        update_fees(250, 0, 0, recipient),
+    ))
+    .unwrap();
+    assert_error(
+        svm.process(&instructions::collect_protocol_fees(&admin)),
+        anchor_lang::error::ErrorCode::ConstraintAddress,
+    );
+    svm.process(&instructions::collect_protocol_fees(&recipient))
+        .unwrap();
+    assert_eq!(svm.lamports(&recipient), 100_000_000);
+    assert_error(
+        svm.process(&instructions::collect_protocol_fees(&recipient)),
+        ProtocolError::NothingToCollect,
+    );
+}
+
+#[test]
+fn dark_pool_fees_need_something_to_collect() {
+    let mut svm = setup();
+    let (market, _) = create_market(&mut svm, &spl_token::ID);
+    let admin = svm.upgrade_authority();
+    svm.token_account(&admin, &market.quote_mint);
+    assert_error(
+        svm.process(&instructions::collect_dark_pool_fees(&market, &admin)),
+        ProtocolError::NothingToCollect,
+    );
+}
+
+#[test]
+fn pause_flags_gate_their_instructions() {
+    let mut svm = setup();
+    let args = args::InitializePresale {
+        denomination: SOL,
+        ..presale_args(&svm)
+    };
+    let keys = create_presale(&mut svm, args);
+    svm.process(&instructions::initialize_commitment_tree(&keys))
+        .unwrap();
+    let (market, authority) = create_market(&mut svm, &spl_token::ID);
+    let maker = trader(&mut svm, &market, 10 * UNIT, 0);
+    let taker = trader(&mut svm, &market, 0, 100 * UNIT);
+    let order_hash = dark_order_hash(&[1; 32], 0, 10 * UNIT, 10 * UNIT, &maker);
+    let place = |order_hash| {
+        instructions::place_dark_order(
+            &maker,
+            &market,
+            args::PlaceDarkOrder {
+                order_hash,
+                escrow_base: UNIT,
+                escrow_quote: 0,
+                expires_at: 0,
+            },
+        )
+    };
+    svm.process(&place(order_hash)).unwrap();
+    let commit_end = svm.now() + 60;
+    svm.process(&instructions::open_batch(
+        &authority,
+        &market,
+        0,
+        args::OpenBatch {
+            commit_end,
+            reveal_end: commit_end + 60,
+        },
+    ))
+    .unwrap();
+    let batch = pda::batch(&market.dark_pool(), 0).0;
+    open(&mut svm, &keys);
+
+    // `claim_tokens` loads the commitment before the config
+    let claim_wallet = svm.new_wallet(SOL);
+    commit(&mut svm, &keys, [3; 32], &claim_wallet, SOL).unwrap();
+    let burner = svm.new_wallet(2 * SOL);
+    let gated = [
+        (
+            PauseFlags {
+                commits: true,
+                ..Default::default()
+            },
+            vec![
+                instructions::commit_to_presale(
+                    &burner,
+                    &keys.address(),
+                    args::CommitToPresale {
+                        commitment_hash: [1; 32],
+                        sol_amount: SOL,
+                        whitelist_nullifier_hash: [0; 32],
+                        whitelist_proof: None,
+                    },
+                ),
+                instructions::commit_to_pool(
+                    &burner,
+                    &keys.address(),
+                    args::CommitToPool {
+                        leaf: [1; 32],
+                        whitelist_nullifier_hash: [0; 32],
+                        whitelist_proof: None,
+                    },
+                ),
+            ],
+        ),
+        (
+            PauseFlags {
+                claims: true,
+                ..Default::default()
+            },
+            vec![
+                instructions::claim_tokens(
+                    &burner,
+                    &keys,
+                    &claim_wallet,
+                    &burner,
+                    args::ClaimTokens { secret: [3; 32] },
+                ),
+                instructions::claim_from_pool(
+                    &burner,
+                    &keys,
+                    &claim_wallet,
+                    args::ClaimFromPool {
+                        nullifier_hash: [1; 32],
+                        nullifier: [1; 32],
+                        secret: [1; 32],
+                        leaf_index: 0,
+                        path: vec![],
+                    },
+                ),
+                instructions::claim_tokens_zk(
+                    &burner,
+                    &keys,
+                    &claim_wallet,
+                    args::ClaimTokensZk {
+                        nullifier_hash: [1; 32],
+                        proof: Groth16Proof {
+                            a: [0; 64],
+                            b: [0; 128],
+                            c: [0; 64],
+                        },
+                    },
+                ),
+            ],
+        ),
+        (
+            PauseFlags {
+                placement: true,
+                ..Default::default()
+            },
+            vec![
+                place([2; 32]),
+                instructions::commit_batch_order(
+                    &maker,
+                    &market,
+                    &batch,
+                    args::CommitBatchOrder {
+                        order_hash: [2; 32],
+                        escrow_base: UNIT,
+                        escrow_quote: 0,
+                    },
+                ),
+            ],
+        ),
+        (
+            PauseFlags {
+                fills: true,
+                ..Default::default()
+            },
+            vec![
+                instructions::fill_dark_order(
+                    &taker,
+                    &market,
+                    &maker,
+                    args::FillDarkOrder {
+                        secret: [1; 32],
+                        side: 0,
+                        base_amount: 10 * UNIT,
+                        quote_amount: 10 * UNIT,
+                        fill_base: UNIT,
+                    },
+                ),
+                instructions::settle_batch(&taker, &market, &batch, &[]),
+            ],
+        ),
+    ];
+
+    for (paused, instructions) in &gated {
+        pause(&mut svm, *paused);
+        for ix in instructions {
+            assert_error(svm.process(ix), ProtocolError::Paused);
+        }
+    }
+
+    pause(&mut svm, PauseFlags::default());
+    svm.process(&gated[0].1[0]).unwrap();
+    svm.process(&gated[2].1[0]).unwrap();
+    svm.process(&gated[3].1[0]).unwrap();
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use anon_presale::{DarkOrder, Presale};
+use common::*;
+use dara_client::{args, dark_order_hash, instructions, pda, Market, PresaleKeys};
+
+/// 1% on every transfer, uncapped.
+const FEE_BPS: u16 = 100;
+
+#[test]
+fn presale_with_a_plain_token_2022_mint() {
+    let mut svm = setup();
+    let keys = presale_keys(&mut svm, &spl_token_2022::ID);
+    let args = presale_args(&svm);
+    svm.process(&instructions::initialize_presale(&keys, args))
+        .unwrap();
+    open(&mut svm, &keys);
+    let wallet = svm.new_wallet(SOL);
+    commit(&mut svm, &keys, [1; 32], &wallet, 4 * SOL).unwrap();
+    end(&mut svm, &keys);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+
+    claim(&mut svm, &keys, [1; 32], &wallet).unwrap();
+    assert_eq!(
+        svm.token_balance(&keys.token_account(&wallet)),
+        TOKENS_FOR_SALE * 4 / 10
+    );
+}
+
+#[test]
+fn presale_accounts_for_transfer_fees() {
+    let mut svm = setup();
+    let mint = svm.create_transfer_fee_mint(DECIMALS, FEE_BPS, u64::MAX);
+    let creator = svm.new_wallet(10 * SOL);
+    let creator_account = svm.token_account(&creator, &mint);
+    svm.mint_to(&mint, &creator_account, TOKENS_FOR_SALE);
+    let keys = PresaleKeys {
+        mint,
+        creator,
+        token_program: spl_token_2022::ID,
+    };
+    let args = presale_args(&svm);
+    svm.process(&instructions::initialize_presale(&keys, args))
+        .unwrap();
+
+    // The sale is sized by what reached the vault
+    let presale: Presale = svm.get(&keys.address());
+    assert_eq!(presale.tokens_for_sale, 990_000_000);
+
+    open(&mut svm, &keys);
+    let wallet = svm.new_wallet(SOL);
+    commit(&mut svm, &keys, [1; 32], &wallet, 4 * SOL).unwrap();
+    end(&mut svm, &keys);
+    svm.process(&instructions::finalize_presale(&keys)).unwrap();
+    claim(&mut svm, &keys, [1; 32], &wallet).unwrap();
+    // 4 of the 10 SOL cap buys 396_000_000, less the fee on the way out
+    assert_eq!(svm.token_balance(&keys.token_account(&wallet)), 392_040_000);
+
+    // The emptied vault still holds withheld fees, which are harvested before it closes
+    svm.process(&instructions::withdraw_remaining_tokens(&keys))
+        .unwrap();
+    assert!(svm.account(&pda::token_vault(&keys.address()).0).is_none());
+    svm.process(&instructions::close_presale(&keys)).unwrap();
+    assert!(svm.account(&keys.address()).is_none());
+}
+
+#[test]
+fn dark_pool_escrows_net_of_transfer_fees() {
+    let mut svm = setup();
+    let market = Market {
+        base_mint: svm.create_transfer_fee_mint(DECIMALS, FEE_BPS, u64::MAX),
+        quote_mint: svm.create_mint(&spl_token::ID, DECIMALS),
+        base_token_program: spl_token_2022::ID,
+        quote_token_program: spl_token::ID,
+    };
+    let authority = trader(&mut svm, &market, 0, 0);
+    svm.process(&instructions::initialize_dark_pool(&authority, &market))
+        .unwrap();
+    let maker = trader(&mut svm, &market, 100 * UNIT, 0);
+    let taker = trader(&mut svm, &market, 0, 100 * UNIT);
+
+    // The maker commits to what the vault will actually hold
+    let order_hash = dark_order_hash(&[1; 32], 0, 99 * UNIT, 99 * UNIT, &maker);
+    svm.process(&instructions::place_dark_order(
+        &maker,
+        &market,
+        args::PlaceDarkOrder {
+            order_hash,
+            escrow_base: 100 * UNIT,
+            escrow_quote: 0,
+            expires_at: 0,
+        },
+    ))
+    .unwrap();
+    let order: DarkOrder = svm.get(&pda::dark_order(&market.dark_pool(), &order_hash).0);
+    assert_eq!(order.escrow_base, 99 * UNIT);
+
+    svm.process(&instructions::fill_dark_order(
+        &taker,
+        &market,
+        &maker,
+        args::FillDarkOrder {
+            secret: [1; 32],
+            side: 0,
+            base_amount: 99 * UNIT,
+            quote_amount: 99 * UNIT,
+            fill_base: 50 * UNIT,
+        },
+    ))
+    .unwrap();
+    assert_eq!(svm.token_balance(&market.base_account(&taker)), 49_500_000);
+    assert_eq!(svm.token_balance(&market.quote_account(&maker)), 50 * UNIT);
+
+    svm.process(&instructions::cancel_dark_order(
+        &maker,
+        &market,
+        &order_hash,
+    ))
+    .unwrap();
+    assert_eq!(svm.token_balance(&market.base_account(&maker)), 48_510_000);
+    svm.process(&instructions::close_dark_pool(&authority, &market))
+        .unwrap();
+    assert!(svm
+        .account(&pda::dp_base_vault(&market.dark_pool()).0)
+        .is_none());
+}
@@ -0,0 +1,163 @@
+//! Uniform-price clearing for dark pool batch auctions.
+//!
+//! Every revealed order is a limit order for `size` base units at `price` quote units per
+//! whole base token (10^decimals base units). The batch clears at the single price that
+//! maximises matched volume; the smaller side fills completely and the larger side is filled
+//! pro rata, so every participant trades at the same price.
+
+use anchor_lang::prelude::*;
+
+use crate::DarkPoolError;
+
+/// Upper bound on orders per batch, so `settle_batch` can take them all in one legacy
+/// transaction. Each revealed order adds 33 bytes to the ~310 the instruction needs anyway,
+/// and 24 of them leave room under the 1232-byte limit for compute budget instructions.
+pub const MAX_BATCH_ORDERS: u32 = 24;
+
+/// How long after `reveal_end` a batch can be settled. Past it an unsettled batch can never
+/// clear, and `claim_batch_order` returns every order's escrow instead.
+pub const SETTLE_WINDOW: i64 = 24 * 60 * 60;
+
+pub const SIDE_SELL: u8 = 0;
+pub const SIDE_BUY: u8 = 1;
+
+#[derive(Clone, Copy)]
+pub struct RevealedOrder {
+    pub side: u8,
+    pub price: u64,
+    pub size: u64,
+}
+
+/// Volumes are sums over up to `MAX_BATCH_ORDERS` sizes, so they are kept in u128 rather
+/// than risk an overflow that would leave the batch unsettleable.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Clearing {
+    pub price: u64,
+    /// Base units matched on each side.
+    pub volume: u128,
+    /// Base units bid at or above `price`.
+    pub bid_volume: u128,
+    /// Base units offered at or below `price`.
+    pub ask_volume: u128,
+}
+
+/// Base and quote amounts an order trades at the clearing price.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Fill {
+    pub base: u64,
+    pub quote: u64,
+}
+
+impl RevealedOrder {
+    pub fn crosses(&self, price: u64) -> bool {
+        match self.side {
+            SIDE_SELL => self.price <= price,
+            _ => self.price >= price,
+        }
+    }
+}
+
+fn volumes_at(orders: &[RevealedOrder], price: u64) -> (u128, u128) {
+    orders
+        .iter()
+        .filter(|order| order.crosses(price))
+        .fold((0u128, 0u128), |(bids, asks), order| match order.side {
+            SIDE_SELL => (bids, asks + order.size as u128),
+            _ => (bids + order.size as u128, asks),
+        })
+}
+
+/// Finds the volume-maximising price. When a range of prices clears the same volume, the
+/// midpoint of the range is used so neither side captures the whole spread.
+pub fn clear(orders: &[RevealedOrder]) -> Clearing {
+    let mut best_volume = 0u128;
+    let mut range: Option<(u64, u64)> = None;
+    for candidate in orders.iter().map(|order| order.price) {
+        let (bids, asks) = volumes_at(orders, candidate);
+        let volume = bids.min(asks);
+        if volume == 0 || volume < best_volume {
+            continue;
+        }
+        range = match range {
+            Some((lo, hi)) if volume == best_volume => Some((lo.min(candidate), hi.max(candidate))),
+            _ => Some((candidate, candidate)),
+        };
+        best_volume = volume;
+    }
+
+    let Some((lo, hi)) = range else {
+        return Clearing::default();
+    };
+    let price = lo + (hi - lo) / 2;
+    let (bids, asks) = volumes_at(orders, price);
+    Clearing {
+        price,
+        volume: bids.min(asks),
+        bid_volume: bids,
+        ask_volume: asks,
+    }
+}
+
+/// Splits the clearing volume across crossing orders. Base amounts on each side sum to
+/// exactly `clearing.volume`; buyers pay the quote cost rounded up and sellers share that
+/// total, so the vaults end the batch holding nothing but unclaimed escrow.
+pub fn allocate(orders: &[RevealedOrder], clearing: &Clearing, decimals: u8) -> Result<Vec<Fill>> {
+    let mut fills = vec![Fill::default(); orders.len()];
+    if clearing.volume == 0 {
+        return Ok(fills);
+    }
+    let unit = 10u128
+        .checked_pow(decimals as u32)
+        .ok_or(DarkPoolError::InvalidOrderParams)?;
+
+    for (side, total) in [
+        (SIDE_SELL, clearing.ask_volume),
+        (SIDE_BUY, clearing.bid_volume),
+    ] {
+        let crossing = |order: &RevealedOrder| order.side == side && order.crosses(clearing.price);
+
+        // Pro rata, then hand the rounding remainder out one unit at a time in reveal order.
+        // The volume never exceeds `total`, so each share fits within the order's size.
+        let mut allocated = 0u128;
+        for (fill, order) in fills.iter_mut().zip(orders) {
+            if crossing(order) {
+                fill.base = (order.size as u128 * clearing.volume / total) as u64;
+                allocated += fill.base as u128;
+            }
+        }
+        let mut remainder = clearing.volume - allocated;
+        for (fill, order) in fills.iter_mut().zip(orders) {
+            if remainder == 0 {
+                break;
+            }
+            if crossing(order) && fill.base < order.size {
+                fill.base += 1;
+                remainder -= 1;
+            }
+        }
+    }
+
+    let mut paid = 0u128;
+    for (fill, order) in fills.iter_mut().zip(orders) {
+        if order.side == SIDE_BUY && fill.base > 0 {
+            let cost = (fill.base as u128 * clearing.price as u128).div_ceil(unit);
+            fill.quote = u64::try_from(cost).map_err(|_| DarkPoolError::InvalidOrderParams)?;
+            paid += cost;
+        }
+    }
+    let mut first_seller = None;
+    for (index, (fill, order)) in fills.iter_mut().zip(orders).enumerate() {
+        if order.side == SIDE_SELL && fill.base > 0 {
+            let proceeds = fill.base as u128 * clearing.price as u128 / unit;
+            fill.quote = proceeds as u64;
+            paid -= proceeds;
+            first_seller.get_or_insert(index);
+        }
+    }
+    // Rounding leftovers from the buyers' side go to the earliest-revealed seller
+    if let Some(index) = first_seller {
+        fills[index].quote += paid as u64;
+    }
+
+    Ok(fills)
+}
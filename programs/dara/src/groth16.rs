@@ -0,0 +1,148 @@
+//! Groth16 verification over BN254 using the `alt_bn128` syscalls.
+//!
+//! Points use the EIP-197 big-endian encoding expected by the syscalls: G1 is `x || y` and
+//! G2 is `x.c1 || x.c0 || y.c1 || y.c0`.
+
+use anchor_lang::prelude::*;
+use solana_bn254::prelude::{alt_bn128_addition, alt_bn128_multiplication, alt_bn128_pairing};
+
+use crate::PresaleError;
+
+/// BN254 base field modulus q, big-endian.
+const BASE_FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x97, 0x81, 0x6a, 0x91, 0x68, 0x71, 0xca, 0x8d, 0x3c, 0x20, 0x8c, 0x16, 0xd8, 0x7c, 0xfd, 0x47,
+];
+
+/// BN254 scalar field modulus r, big-endian.
+const SCALAR_FIELD_MODULUS: [u8; 32] = [
+    0x30, 0x64, 0x4e, 0x72, 0xe1, 0x31, 0xa0, 0x29, 0xb8, 0x50, 0x45, 0xb6, 0x81, 0x81, 0x58, 0x5d,
+    0x28, 0x33, 0xe8, 0x48, 0x79, 0xb9, 0x70, 0x91, 0x43, 0xe1, 0xf5, 0x93, 0xf0, 0x00, 0x00, 0x01,
+];
+
+pub struct VerifyingKey<'a> {
+    pub alpha_g1: [u8; 64],
+    pub beta_g2: [u8; 128],
+    pub gamma_g2: [u8; 128],
+    pub delta_g2: [u8; 128],
+    /// `[IC_0, IC_1, ..., IC_n]` for `n` public inputs.
+    pub ic: &'a [[u8; 64]],
+}
+
+/// An owned verifying key for a circuit with four public inputs, the shape of the claim
+/// circuit, as `set_claim_verifying_key` takes and stores it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VerifyingKeyData {
+    pub alpha_g1: [u8; 64],
+    pub beta_g2: [u8; 128],
+    pub gamma_g2: [u8; 128],
+    pub delta_g2: [u8; 128],
+    pub ic: [[u8; 64]; 5],
+}
+
+impl VerifyingKeyData {
+    pub const LEN: usize = 64 + 128 * 3 + 64 * 5;
+
+    pub fn verifying_key(&self) -> VerifyingKey<'_> {
+        VerifyingKey {
+            alpha_g1: self.alpha_g1,
+            beta_g2: self.beta_g2,
+            gamma_g2: self.gamma_g2,
+            delta_g2: self.delta_g2,
+            ic: &self.ic,
+        }
+    }
+
+    /// True if every G1 point is on the curve and `alpha` is not the point at infinity.
+    /// G2 points are only checked by the pairing, so a bad one makes every proof fail.
+    pub fn is_valid(&self) -> bool {
+        self.alpha_g1 != [0u8; 64]
+            && std::iter::once(&self.alpha_g1)
+                .chain(&self.ic)
+                .all(is_g1_point)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Groth16Proof {
+    pub a: [u8; 64],
+    pub b: [u8; 128],
+    pub c: [u8; 64],
+}
+
+/// True if `value` is a canonical scalar field element, i.e. strictly below r.
+pub fn is_canonical_scalar(value: &[u8; 32]) -> bool {
+    value < &SCALAR_FIELD_MODULUS
+}
+
+/// True if `point` is a G1 point the syscalls accept, i.e. on the curve or at infinity.
+fn is_g1_point(point: &[u8; 64]) -> bool {
+    let mut add_input = [0u8; 128];
+    add_input[..64].copy_from_slice(point);
+    alt_bn128_addition(&add_input).is_ok()
+}
+
+/// Checks `e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1` where
+/// `vk_x = IC_0 + sum(input_i * IC_{i+1})`.
+pub fn verify(vk: &VerifyingKey, proof: &Groth16Proof, public_inputs: &[[u8; 32]]) -> Result<()> {
+    require!(
+        public_inputs.len() + 1 == vk.ic.len(),
+        PresaleError::InvalidZkProof
+    );
+
+    let mut vk_x = vk.ic[0];
+    for (input, ic) in public_inputs.iter().zip(&vk.ic[1..]) {
+        // Non-canonical inputs would let the same proof verify under several encodings
+        require!(is_canonical_scalar(input), PresaleError::InvalidFieldElement);
+
+        let mut mul_input = [0u8; 96];
+        mul_input[..64].copy_from_slice(ic);
+        mul_input[64..].copy_from_slice(input);
+        let term = alt_bn128_multiplication(&mul_input).map_err(|_| PresaleError::InvalidZkProof)?;
+
+        let mut add_input = [0u8; 128];
+        add_input[..64].copy_from_slice(&vk_x);
+        add_input[64..].copy_from_slice(&term);
+        let sum = alt_bn128_addition(&add_input).map_err(|_| PresaleError::InvalidZkProof)?;
+        vk_x.copy_from_slice(&sum);
+    }
+
+    let mut pairing_input = [0u8; 192 * 4];
+    pairing_input[..64].copy_from_slice(&negate_g1(&proof.a)?);
+    pairing_input[64..192].copy_from_slice(&proof.b);
+    pairing_input[192..256].copy_from_slice(&vk.alpha_g1);
+    pairing_input[256..384].copy_from_slice(&vk.beta_g2);
+    pairing_input[384..448].copy_from_slice(&vk_x);
+    pairing_input[448..576].copy_from_slice(&vk.gamma_g2);
+    pairing_input[576..640].copy_from_slice(&proof.c);
+    pairing_input[640..].copy_from_slice(&vk.delta_g2);
+
+    let result = alt_bn128_pairing(&pairing_input).map_err(|_| PresaleError::InvalidZkProof)?;
+    require!(
+        result.len() == 32 && result[31] == 1 && result[..31].iter().all(|b| *b == 0),
+        PresaleError::InvalidZkProof
+    );
+    Ok(())
+}
+
+/// Negates a G1 point by mapping `(x, y)` to `(x, q - y)`.
+fn negate_g1(point: &[u8; 64]) -> Result<[u8; 64]> {
+    let mut negated = *point;
+    let y: [u8; 32] = point[32..].try_into().unwrap();
+    require!(y < BASE_FIELD_MODULUS, PresaleError::InvalidZkProof);
+
+    // The point at infinity is its own negation
+    if y == [0u8; 32] {
+        return Ok(negated);
+    }
+
+    let mut borrow = 0u16;
+    for i in (0..32).rev() {
+        let diff = (BASE_FIELD_MODULUS[i] as u16)
+            .wrapping_sub(y[i] as u16)
+            .wrapping_sub(borrow);
+        negated[32 + i] = diff as u8;
+        borrow = (diff >> 8) & 1;
+    }
+    Ok(negated)
+}
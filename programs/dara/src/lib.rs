@@ -1,7 +1,16 @@
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token_interface::spl_token_2022::{self, extension::transfer_fee::TransferFeeConfig};
+use anchor_spl::token_interface::{
+    self, CloseAccount, HarvestWithheldTokensToMint, Mint, TokenAccount, TokenInterface,
+};
+
+pub mod batch;
+pub mod groth16;
+pub mod merkle;
 
 declare_id!("Hd5LcuhcSQ7aHqoyGhJSS6dokyptfBhNJXTvDQhfhxkj");
 
@@ -9,15 +18,45 @@ declare_id!("Hd5LcuhcSQ7aHqoyGhJSS6dokyptfBhNJXTvDQhfhxkj");
 pub mod anon_presale {
     use super::*;
 
-    pub fn initialize_presale(
-        ctx: Context<InitializePresale>,
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_presale<'info>(
+        ctx: Context<'_, '_, 'info, 'info, InitializePresale<'info>>,
         hard_cap: u64,
+        soft_cap: u64,
         tokens_for_sale: u64,
         start_time: i64,
         end_time: i64,
+        claim_window: i64,
+        vesting: VestingSchedule,
+        limits: ContributionLimits,
+        denomination: u64,
     ) -> Result<()> {
         require!(end_time > start_time, PresaleError::InvalidTimeRange);
+        // The claim window runs from finalization, so finalizing late cannot shorten it. Zero
+        // means unclaimed tokens stay in the vault until every commitment settles.
+        require!(claim_window >= 0, PresaleError::InvalidTimeRange);
+        require!(
+            vesting.tge_unlock_bps <= BPS_DENOMINATOR
+                && vesting.cliff_duration >= 0
+                && vesting.vesting_duration >= 0,
+            PresaleError::InvalidVestingSchedule
+        );
+        // Otherwise the creator could sweep tokens that have not finished vesting
+        let vesting_end = vesting
+            .cliff_duration
+            .checked_add(vesting.vesting_duration)
+            .ok_or(PresaleError::InvalidVestingSchedule)?;
+        require!(
+            claim_window == 0 || claim_window >= vesting_end,
+            PresaleError::ClaimWindowTooShort
+        );
+        require!(
+            limits.max_commitment == 0 || limits.min_commitment <= limits.max_commitment,
+            PresaleError::InvalidContributionLimits
+        );
+        require!(denomination <= hard_cap, PresaleError::InvalidDenomination);
         require!(hard_cap > 0, PresaleError::InvalidAmount);
+        require!(soft_cap <= hard_cap, PresaleError::InvalidSoftCap);
         require!(tokens_for_sale > 0, PresaleError::InvalidAmount);
 
         let presale = &mut ctx.accounts.presale;
@@ -25,28 +64,51 @@ pub mod anon_presale {
         presale.mint = ctx.accounts.mint.key();
         presale.total_sol_committed = 0;
         presale.hard_cap = hard_cap;
+        presale.soft_cap = soft_cap;
         presale.tokens_for_sale = tokens_for_sale;
         presale.start_time = start_time;
         presale.end_time = end_time;
+        presale.claim_window = claim_window;
+        presale.vesting = vesting;
+        presale.limits = limits;
+        presale.denomination = denomination;
+        presale.whitelist_root = [0u8; 32];
+        presale.finalized_at = 0;
         presale.is_finalized = false;
+        presale.is_cancelled = false;
+        presale.vault_closed = false;
         presale.commitment_count = 0;
+        presale.settled_count = 0;
+        presale.whitelist_nullifier_count = 0;
+        presale.open_pool_leaves = 0;
         presale.bump = ctx.bumps.presale;
         presale.vault_auth_bump = ctx.bumps.vault_authority;
 
-        // Transfer tokens from creator to token vault
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.creator_token_account.to_account_info(),
-                    to: ctx.accounts.token_vault.to_account_info(),
-                    authority: ctx.accounts.creator.to_account_info(),
-                },
-            ),
+        // Transfer tokens from creator to token vault. With a transfer fee mint the vault gets
+        // less than was sent, and claims are priced against what actually arrived.
+        let tokens_for_sale = deposit_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.mint,
+            &ctx.accounts.creator_token_account,
+            &mut ctx.accounts.token_vault,
+            ctx.accounts.creator.to_account_info(),
+            ctx.remaining_accounts,
             tokens_for_sale,
         )?;
+        require!(tokens_for_sale > 0, PresaleError::InvalidAmount);
+        let presale = &mut ctx.accounts.presale;
+        presale.tokens_for_sale = tokens_for_sale;
 
-        msg!("Presale initialized: hard_cap={}, tokens={}", hard_cap, tokens_for_sale);
+        emit!(PresaleInitialized {
+            presale: presale.key(),
+            creator: presale.creator,
+            mint: presale.mint,
+            hard_cap,
+            soft_cap,
+            tokens_for_sale,
+            start_time,
+            end_time,
+        });
         Ok(())
     }
 
@@ -54,18 +116,21 @@ pub mod anon_presale {
         ctx: Context<CommitToPresale>,
         commitment_hash: [u8; 32],
         sol_amount: u64,
+        whitelist_nullifier_hash: [u8; 32],
+        whitelist_proof: Option<groth16::Groth16Proof>,
     ) -> Result<()> {
         let clock = Clock::get()?;
-        let presale = &ctx.accounts.presale;
-
-        require!(clock.unix_timestamp >= presale.start_time, PresaleError::NotStarted);
-        require!(clock.unix_timestamp <= presale.end_time, PresaleError::Ended);
-        require!(!presale.is_finalized, PresaleError::AlreadyFinalized);
-        require!(sol_amount > 0, PresaleError::InvalidAmount);
-        require!(
-            presale.total_sol_committed.checked_add(sol_amount).unwrap() <= presale.hard_cap,
-            PresaleError::HardCapExceeded
-        );
+        ctx.accounts
+            .presale
+            .validate_commitment(sol_amount, clock.unix_timestamp)?;
+        let accounts = &mut *ctx.accounts;
+        accounts.presale.check_whitelist(
+            &accounts.participant.key(),
+            whitelist_nullifier_hash,
+            whitelist_proof.as_ref(),
+            accounts.claim_verifying_key.as_ref(),
+            accounts.whitelist_nullifier.as_mut(),
+        )?;
 
         // Transfer SOL from participant (burner wallet) to the presale PDA
         // The presale PDA is program-owned, so we can debit it later
@@ -90,10 +155,274 @@ pub mod anon_presale {
         commitment.presale = presale.key();
         commitment.commitment_hash = commitment_hash;
         commitment.sol_amount = sol_amount;
+        commitment.claimed_amount = 0;
         commitment.is_claimed = false;
         commitment.bump = ctx.bumps.commitment;
 
-        msg!("Commitment added: sol={}, count={}", sol_amount, presale.commitment_count);
+        // Denominated commitments are only as anonymous as their amount, so keep it out of the logs
+        emit!(CommitmentAdded {
+            presale: presale.key(),
+            commitment: commitment.key(),
+            sol_amount: (presale.denomination == 0).then_some(sol_amount),
+            commitment_count: presale.commitment_count,
+        });
+        Ok(())
+    }
+
+    /// Gates commits behind an allowlist. Only the root of its Poseidon tree goes on chain.
+    /// Each leaf is `merkle::leaf_hash(nullifier, secret, burner)`, which its holder hands the
+    /// creator without the preimage. A commit from that burner proves membership with a
+    /// claim circuit proof and spends only `Poseidon(nullifier)`, so nobody can tell which
+    /// leaf was used. Leaves must be fresh for every presale, as a reused nullifier links the
+    /// commits. The list is fixed once the presale starts; a zero root reopens it to anyone.
+    pub fn set_whitelist_root(ctx: Context<SetWhitelistRoot>, whitelist_root: [u8; 32]) -> Result<()> {
+        let presale = &mut ctx.accounts.presale;
+        require!(!presale.is_cancelled, PresaleError::Cancelled);
+        require!(
+            Clock::get()?.unix_timestamp < presale.start_time,
+            PresaleError::WhitelistLocked
+        );
+
+        presale.whitelist_root = whitelist_root;
+
+        emit!(WhitelistRootSet {
+            presale: presale.key(),
+            whitelist_root,
+        });
+        Ok(())
+    }
+
+    /// Permissionless: closes a spent allowlist nullifier once the presale takes no more
+    /// commits, returning its rent to the burner that paid for it.
+    pub fn close_whitelist_nullifier(
+        ctx: Context<CloseWhitelistNullifier>,
+        nullifier_hash: [u8; 32],
+    ) -> Result<()> {
+        let presale = &mut ctx.accounts.presale;
+        require!(
+            presale.is_finalized
+                || presale.is_cancelled
+                || Clock::get()?.unix_timestamp > presale.end_time,
+            PresaleError::PresaleStillActive
+        );
+        presale.whitelist_nullifier_count = presale.whitelist_nullifier_count.checked_sub(1).unwrap();
+
+        emit!(WhitelistNullifierClosed {
+            presale: presale.key(),
+            nullifier_hash,
+        });
+        Ok(())
+    }
+
+    // ─── Commitment Pool Instructions ───
+
+    pub fn initialize_commitment_tree(ctx: Context<InitializeCommitmentTree>) -> Result<()> {
+        let presale = &ctx.accounts.presale;
+        require!(!presale.is_finalized, PresaleError::AlreadyFinalized);
+        require!(!presale.is_cancelled, PresaleError::Cancelled);
+        // Pooled leaves are only indistinguishable if they all carry the same amount
+        require!(presale.denomination > 0, PresaleError::PoolRequiresDenomination);
+
+        let tree = &mut ctx.accounts.commitment_tree;
+        tree.presale = presale.key();
+        tree.next_index = 0;
+        tree.root = merkle::empty_root()?;
+        tree.filled_subtrees = [[0u8; 32]; merkle::TREE_DEPTH];
+        tree.bump = ctx.bumps.commitment_tree;
+
+        emit!(CommitmentTreeInitialized {
+            presale: tree.presale,
+            commitment_tree: tree.key(),
+            root: tree.root,
+        });
+        Ok(())
+    }
+
+    pub fn commit_to_pool(
+        ctx: Context<CommitToPool>,
+        leaf: [u8; 32],
+        whitelist_nullifier_hash: [u8; 32],
+        whitelist_proof: Option<groth16::Groth16Proof>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let sol_amount = ctx.accounts.presale.denomination;
+        ctx.accounts
+            .presale
+            .validate_commitment(sol_amount, clock.unix_timestamp)?;
+        let accounts = &mut *ctx.accounts;
+        accounts.presale.check_whitelist(
+            &accounts.participant.key(),
+            whitelist_nullifier_hash,
+            whitelist_proof.as_ref(),
+            accounts.claim_verifying_key.as_ref(),
+            accounts.whitelist_nullifier.as_mut(),
+        )?;
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.participant.to_account_info(),
+                    to: ctx.accounts.presale.to_account_info(),
+                },
+            ),
+            sol_amount,
+        )?;
+
+        let leaf_index = ctx.accounts.commitment_tree.insert(leaf)?;
+
+        let presale = &mut ctx.accounts.presale;
+        presale.total_sol_committed = presale.total_sol_committed.checked_add(sol_amount).unwrap();
+        presale.commitment_count = presale.commitment_count.checked_add(1).unwrap();
+        presale.open_pool_leaves = presale.open_pool_leaves.checked_add(1).unwrap();
+
+        // Indexers replay these leaves to rebuild claim paths off-chain
+        emit!(PoolCommitmentAdded {
+            presale: presale.key(),
+            leaf,
+            leaf_index,
+            root: ctx.accounts.commitment_tree.root,
+            commitment_count: presale.commitment_count,
+        });
+        Ok(())
+    }
+
+    /// Claims a pooled commitment by opening its leaf in the clear. This gives NO
+    /// unlinkability: the nullifier, secret, leaf index and path are all in the transaction, so
+    /// anyone can match the claim to the `PoolCommitmentAdded` event that inserted the leaf.
+    /// It exists as a test path for the tree and payout logic; private claims go through
+    /// `claim_tokens_zk`.
+    pub fn claim_from_pool<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimFromPool<'info>>,
+        nullifier_hash: [u8; 32],
+        nullifier: [u8; 32],
+        secret: [u8; 32],
+        leaf_index: u32,
+        path: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let presale = &ctx.accounts.presale;
+        require!(presale.is_finalized, PresaleError::NotFinalized);
+        require!(
+            merkle::nullifier_hash(&nullifier)? == nullifier_hash,
+            PresaleError::InvalidNullifier
+        );
+
+        // Membership: Poseidon(nullifier, secret, claim_wallet) is a leaf under the tree root
+        let claim_wallet_key = ctx.accounts.claim_wallet.key();
+        let leaf = merkle::leaf_hash(&nullifier, &secret, &claim_wallet_key)?;
+        require!(
+            merkle::root_from_path(leaf, leaf_index, &path)? == ctx.accounts.commitment_tree.root,
+            PresaleError::InvalidMerkleProof
+        );
+
+        let (releasable, tokens_owed) = ctx
+            .accounts
+            .release_vested(ctx.bumps.nullifier, clock.unix_timestamp, ctx.remaining_accounts)?;
+
+        emit!(TokensClaimed {
+            presale: ctx.accounts.presale.key(),
+            claim_wallet: claim_wallet_key,
+            amount: releasable,
+            total_owed: tokens_owed,
+            nullifier_hash: Some(nullifier_hash),
+        });
+        Ok(())
+    }
+
+    /// Same payout as `claim_from_pool`, but membership is proven with a Groth16 proof so the
+    /// claim never reveals the leaf, its index or the secret behind it. Disabled until the
+    /// admin installs the circuit's verifying key with `set_claim_verifying_key`.
+    pub fn claim_tokens_zk<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimFromPool<'info>>,
+        nullifier_hash: [u8; 32],
+        proof: groth16::Groth16Proof,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(ctx.accounts.presale.is_finalized, PresaleError::NotFinalized);
+
+        // Public inputs: [root, nullifier_hash, claim_wallet_hi, claim_wallet_lo]
+        let claim_wallet_key = ctx.accounts.claim_wallet.key();
+        let [wallet_hi, wallet_lo] = merkle::wallet_halves(&claim_wallet_key);
+        let public_inputs = [
+            ctx.accounts.commitment_tree.root,
+            nullifier_hash,
+            wallet_hi,
+            wallet_lo,
+        ];
+        verify_claim_proof(
+            ctx.accounts.claim_verifying_key.as_ref(),
+            &proof,
+            &public_inputs,
+        )?;
+
+        let (releasable, tokens_owed) = ctx
+            .accounts
+            .release_vested(ctx.bumps.nullifier, clock.unix_timestamp, ctx.remaining_accounts)?;
+
+        emit!(TokensClaimed {
+            presale: ctx.accounts.presale.key(),
+            claim_wallet: claim_wallet_key,
+            amount: releasable,
+            total_owed: tokens_owed,
+            nullifier_hash: Some(nullifier_hash),
+        });
+        Ok(())
+    }
+
+    pub fn refund_from_pool(
+        ctx: Context<RefundFromPool>,
+        nullifier_hash: [u8; 32],
+        nullifier: [u8; 32],
+        secret: [u8; 32],
+        leaf_index: u32,
+        path: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let presale = &ctx.accounts.presale;
+
+        require!(!presale.is_finalized, PresaleError::AlreadyFinalized);
+        if !presale.is_cancelled {
+            require!(clock.unix_timestamp > presale.end_time, PresaleError::PresaleStillActive);
+            require!(
+                presale.total_sol_committed < presale.soft_cap,
+                PresaleError::SoftCapReached
+            );
+        }
+        require!(
+            merkle::nullifier_hash(&nullifier)? == nullifier_hash,
+            PresaleError::InvalidNullifier
+        );
+
+        let refund_wallet_key = ctx.accounts.refund_wallet.key();
+        let leaf = merkle::leaf_hash(&nullifier, &secret, &refund_wallet_key)?;
+        require!(
+            merkle::root_from_path(leaf, leaf_index, &path)? == ctx.accounts.commitment_tree.root,
+            PresaleError::InvalidMerkleProof
+        );
+
+        let refund_amount = presale.denomination;
+        let presale_info = ctx.accounts.presale.to_account_info();
+        let refund_info = ctx.accounts.refund_wallet.to_account_info();
+        **presale_info.try_borrow_mut_lamports()? -= refund_amount;
+        **refund_info.try_borrow_mut_lamports()? += refund_amount;
+
+        let spent = &mut ctx.accounts.nullifier;
+        spent.presale = ctx.accounts.presale.key();
+        spent.claimed_amount = 0;
+        spent.is_settled = true;
+        spent.bump = ctx.bumps.nullifier;
+
+        let presale = &mut ctx.accounts.presale;
+        presale.settled_count = presale.settled_count.checked_add(1).unwrap();
+        presale.open_pool_leaves = presale.open_pool_leaves.checked_sub(1).unwrap();
+
+        emit!(CommitmentRefunded {
+            presale: presale.key(),
+            refund_wallet: refund_wallet_key,
+            amount: refund_amount,
+            nullifier_hash: Some(nullifier_hash),
+        });
         Ok(())
     }
 
@@ -102,11 +431,16 @@ pub mod anon_presale {
         let presale = &ctx.accounts.presale;
 
         require!(!presale.is_finalized, PresaleError::AlreadyFinalized);
+        require!(!presale.is_cancelled, PresaleError::Cancelled);
         require!(
             clock.unix_timestamp > presale.end_time
                 || presale.total_sol_committed >= presale.hard_cap,
             PresaleError::PresaleStillActive
         );
+        require!(
+            presale.total_sol_committed >= presale.soft_cap,
+            PresaleError::SoftCapNotReached
+        );
 
         // Calculate how much SOL to transfer (total committed minus rent-exempt minimum)
         let presale_info = ctx.accounts.presale.to_account_info();
@@ -114,8 +448,20 @@ pub mod anon_presale {
         let rent = Rent::get()?;
         let data_len = presale_info.data_len();
         let rent_exempt = rent.minimum_balance(data_len);
-        let transfer_amount = presale_lamports.saturating_sub(rent_exempt);
+        let raised = presale_lamports.saturating_sub(rent_exempt);
+
+        // The protocol fee accrues on the config account until `collect_protocol_fees`
+        let fee = ProtocolConfig::fee(
+            presale.total_sol_committed,
+            ctx.accounts.protocol_config.presale_fee_bps,
+        )
+        .min(raised);
+        let transfer_amount = raised - fee;
 
+        if fee > 0 {
+            **presale_info.try_borrow_mut_lamports()? -= fee;
+            **ctx.accounts.protocol_config.to_account_info().try_borrow_mut_lamports()? += fee;
+        }
         if transfer_amount > 0 {
             **presale_info.try_borrow_mut_lamports()? -= transfer_amount;
             **ctx.accounts.creator.to_account_info().try_borrow_mut_lamports()? += transfer_amount;
@@ -123,39 +469,51 @@ pub mod anon_presale {
 
         let presale = &mut ctx.accounts.presale;
         presale.is_finalized = true;
+        presale.finalized_at = clock.unix_timestamp;
 
-        msg!("Presale finalized: total_sol={}, transferred={}", presale.total_sol_committed, transfer_amount);
+        emit!(PresaleFinalized {
+            presale: presale.key(),
+            total_sol_committed: presale.total_sol_committed,
+            transferred: transfer_amount,
+            protocol_fee: fee,
+        });
         Ok(())
     }
 
-    pub fn claim_tokens(ctx: Context<ClaimTokens>, secret: [u8; 32]) -> Result<()> {
+    pub fn claim_tokens<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimTokens<'info>>,
+        secret: [u8; 32],
+    ) -> Result<()> {
+        let clock = Clock::get()?;
         let presale = &ctx.accounts.presale;
         let commitment = &ctx.accounts.commitment;
 
         require!(presale.is_finalized, PresaleError::NotFinalized);
+        require!(
+            !presale.claim_window_closed(clock.unix_timestamp),
+            PresaleError::ClaimWindowClosed
+        );
         require!(!commitment.is_claimed, PresaleError::AlreadyClaimed);
 
         // Verify: hash(secret || claim_wallet) == commitment_hash
         let claim_wallet_key = ctx.accounts.claim_wallet.key();
-        let mut hash_input = Vec::with_capacity(64);
-        hash_input.extend_from_slice(&secret);
-        hash_input.extend_from_slice(claim_wallet_key.as_ref());
-        let computed = anchor_lang::solana_program::hash::hash(&hash_input);
-
         require!(
-            computed.to_bytes() == commitment.commitment_hash,
+            commitment_hash(&secret, &claim_wallet_key) == commitment.commitment_hash,
             PresaleError::InvalidProof
         );
 
-        // Calculate pro-rata token amount
-        let tokens_owed = (commitment.sol_amount as u128)
-            .checked_mul(presale.tokens_for_sale as u128)
-            .unwrap()
-            .checked_div(presale.total_sol_committed as u128)
-            .unwrap() as u64;
+        // Allocation at the presale price
+        let tokens_owed = presale.tokens_owed(commitment.sol_amount);
 
         require!(tokens_owed > 0, PresaleError::InvalidAmount);
 
+        // Release whatever has vested since the last claim
+        let vested = presale
+            .vesting
+            .vested_amount(tokens_owed, presale.finalized_at, clock.unix_timestamp);
+        let releasable = vested.checked_sub(commitment.claimed_amount).unwrap();
+        require!(releasable > 0, PresaleError::NothingToClaim);
+
         // Transfer tokens from vault to claim wallet's token account
         let presale_key = ctx.accounts.presale.key();
         let seeds = &[
@@ -165,24 +523,251 @@ pub mod anon_presale {
         ];
         let signer_seeds = &[&seeds[..]];
 
-        token::transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.token_vault.to_account_info(),
-                    to: ctx.accounts.claim_token_account.to_account_info(),
-                    authority: ctx.accounts.vault_authority.to_account_info(),
-                },
-                signer_seeds,
-            ),
-            tokens_owed,
+        transfer_tokens(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_vault,
+            &ctx.accounts.mint,
+            &ctx.accounts.claim_token_account,
+            ctx.accounts.vault_authority.to_account_info(),
+            ctx.remaining_accounts,
+            releasable,
+            signer_seeds,
         )?;
 
-        // Mark claimed
+        let commitment = &mut ctx.accounts.commitment;
+        commitment.claimed_amount = commitment.claimed_amount.checked_add(releasable).unwrap();
+
+        // Fully vested and claimed: settle and close the commitment
+        if commitment.claimed_amount == tokens_owed {
+            commitment.is_claimed = true;
+
+            let presale = &mut ctx.accounts.presale;
+            presale.settled_count = presale.settled_count.checked_add(1).unwrap();
+
+            ctx.accounts
+                .commitment
+                .close(ctx.accounts.rent_recipient.to_account_info())?;
+        }
+
+        emit!(TokensClaimed {
+            presale: ctx.accounts.presale.key(),
+            claim_wallet: claim_wallet_key,
+            amount: releasable,
+            total_owed: tokens_owed,
+            nullifier_hash: None,
+        });
+        Ok(())
+    }
+
+    /// Permissionless: settles a commitment left unclaimed past the claim window. Its tokens
+    /// went back to the creator with the vault, so the account is closed to the caller's
+    /// chosen wallet and stops holding the presale open.
+    pub fn close_stale_commitment(ctx: Context<CloseStaleCommitment>) -> Result<()> {
+        let presale = &mut ctx.accounts.presale;
+        require!(
+            presale.claim_window_closed(Clock::get()?.unix_timestamp),
+            PresaleError::ClaimWindowOpen
+        );
+        presale.settled_count = presale.settled_count.checked_add(1).unwrap();
+
+        emit!(StaleCommitmentClosed {
+            presale: presale.key(),
+            commitment: ctx.accounts.commitment.key(),
+            claimed_amount: ctx.accounts.commitment.claimed_amount,
+        });
+        Ok(())
+    }
+
+    pub fn refund_commitment(ctx: Context<RefundCommitment>, secret: [u8; 32]) -> Result<()> {
+        let clock = Clock::get()?;
+        let presale = &ctx.accounts.presale;
+        let commitment = &ctx.accounts.commitment;
+
+        require!(!presale.is_finalized, PresaleError::AlreadyFinalized);
+        // A cancelled presale refunds unconditionally; otherwise it must have ended below the soft cap
+        if !presale.is_cancelled {
+            require!(clock.unix_timestamp > presale.end_time, PresaleError::PresaleStillActive);
+            require!(
+                presale.total_sol_committed < presale.soft_cap,
+                PresaleError::SoftCapReached
+            );
+        }
+        require!(!commitment.is_claimed, PresaleError::AlreadyClaimed);
+
+        // Verify: hash(secret || refund_wallet) == commitment_hash
+        let refund_wallet_key = ctx.accounts.refund_wallet.key();
+        require!(
+            commitment_hash(&secret, &refund_wallet_key) == commitment.commitment_hash,
+            PresaleError::InvalidProof
+        );
+
+        // SOL: presale PDA -> refund wallet (direct lamport manipulation since presale is program-owned)
+        let refund_amount = commitment.sol_amount;
+        let presale_info = ctx.accounts.presale.to_account_info();
+        let refund_info = ctx.accounts.refund_wallet.to_account_info();
+        **presale_info.try_borrow_mut_lamports()? -= refund_amount;
+        **refund_info.try_borrow_mut_lamports()? += refund_amount;
+
+        // Mark claimed so the commitment cannot be refunded twice
         let commitment = &mut ctx.accounts.commitment;
         commitment.is_claimed = true;
 
-        msg!("Claimed {} tokens to {}", tokens_owed, claim_wallet_key);
+        let presale = &mut ctx.accounts.presale;
+        presale.settled_count = presale.settled_count.checked_add(1).unwrap();
+
+        emit!(CommitmentRefunded {
+            presale: presale.key(),
+            refund_wallet: refund_wallet_key,
+            amount: refund_amount,
+            nullifier_hash: None,
+        });
+        Ok(())
+    }
+
+    pub fn cancel_presale<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelPresale<'info>>,
+    ) -> Result<()> {
+        let presale = &ctx.accounts.presale;
+        require!(!presale.is_finalized, PresaleError::AlreadyFinalized);
+        require!(!presale.is_cancelled, PresaleError::Cancelled);
+
+        // Return all vault tokens to the creator
+        let vault_balance = ctx.accounts.token_vault.amount;
+        if vault_balance > 0 {
+            let presale_key = ctx.accounts.presale.key();
+            let seeds = &[
+                b"vault_auth".as_ref(),
+                presale_key.as_ref(),
+                &[presale.vault_auth_bump],
+            ];
+            let signer_seeds = &[&seeds[..]];
+
+            transfer_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.token_vault,
+                &ctx.accounts.mint,
+                &ctx.accounts.creator_token_account,
+                ctx.accounts.vault_authority.to_account_info(),
+                ctx.remaining_accounts,
+                vault_balance,
+                signer_seeds,
+            )?;
+        }
+
+        // Committed SOL stays on the presale PDA until each commitment is refunded
+        let presale = &mut ctx.accounts.presale;
+        presale.is_cancelled = true;
+
+        emit!(PresaleCancelled {
+            presale: presale.key(),
+            tokens_returned: vault_balance,
+            commitment_count: presale.commitment_count,
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_remaining_tokens<'info>(
+        ctx: Context<'_, '_, 'info, 'info, WithdrawRemainingTokens<'info>>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let presale = &ctx.accounts.presale;
+
+        if presale.is_finalized {
+            // Unsold allocation and rounding dust are only free once nothing more can be claimed
+            require!(
+                presale.settled_count == presale.commitment_count
+                    || presale.claim_window_closed(clock.unix_timestamp),
+                PresaleError::ClaimsOutstanding
+            );
+        } else {
+            // A cancelled or failed presale never owes tokens to participants
+            let failed = clock.unix_timestamp > presale.end_time
+                && presale.total_sol_committed < presale.soft_cap;
+            require!(presale.is_cancelled || failed, PresaleError::NotFinalized);
+        }
+
+        let presale_key = ctx.accounts.presale.key();
+        let seeds = &[
+            b"vault_auth".as_ref(),
+            presale_key.as_ref(),
+            &[presale.vault_auth_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        let remaining = ctx.accounts.token_vault.amount;
+        if remaining > 0 {
+            transfer_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.token_vault,
+                &ctx.accounts.mint,
+                &ctx.accounts.creator_token_account,
+                ctx.accounts.vault_authority.to_account_info(),
+                ctx.remaining_accounts,
+                remaining,
+                signer_seeds,
+            )?;
+        }
+
+        // Close the empty vault and return its rent to the creator
+        harvest_withheld_fees(
+            &ctx.accounts.token_program,
+            &ctx.accounts.mint,
+            &ctx.accounts.token_vault,
+        )?;
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.token_vault.to_account_info(),
+                destination: ctx.accounts.creator.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            signer_seeds,
+        ))?;
+
+        let presale = &mut ctx.accounts.presale;
+        presale.vault_closed = true;
+
+        emit!(RemainingTokensWithdrawn {
+            presale: presale.key(),
+            amount: remaining,
+        });
+        Ok(())
+    }
+
+    pub fn close_presale(ctx: Context<ClosePresale>) -> Result<()> {
+        let presale = &ctx.accounts.presale;
+
+        require!(presale.vault_closed, PresaleError::VaultNotClosed);
+
+        // The creator could re-initialize a presale at the same address, so nothing may outlive
+        // this one: every commitment account must be settled, which closes it, past the claim
+        // window through `close_stale_commitment`, every allowlist nullifier must be closed,
+        // and the commitment tree goes with the presale. Unclaimed pooled leaves have no
+        // account of their own, so once the window has closed they lapse with the tree.
+        let outstanding = presale.commitment_count - presale.settled_count;
+        let lapsed = if presale.claim_window_closed(Clock::get()?.unix_timestamp) {
+            presale.open_pool_leaves
+        } else {
+            0
+        };
+        require!(outstanding == lapsed, PresaleError::ClaimsOutstanding);
+        require!(
+            presale.whitelist_nullifier_count == 0,
+            PresaleError::WhitelistNullifiersOutstanding
+        );
+        let tree = ctx.accounts.commitment_tree.to_account_info();
+        if tree.owner == &crate::ID {
+            let creator = ctx.accounts.creator.to_account_info();
+            **creator.try_borrow_mut_lamports()? += tree.lamports();
+            **tree.try_borrow_mut_lamports()? = 0;
+            tree.assign(&system_program::ID);
+            tree.resize(0)?;
+        }
+
+        emit!(PresaleClosed {
+            presale: presale.key(),
+            commitment_count: presale.commitment_count,
+        });
         Ok(())
     }
 
@@ -190,281 +775,1496 @@ pub mod anon_presale {
 
     pub fn initialize_dark_pool(ctx: Context<InitializeDarkPool>) -> Result<()> {
         let pool = &mut ctx.accounts.dark_pool;
-        pool.mint = ctx.accounts.mint.key();
+        pool.base_mint = ctx.accounts.base_mint.key();
+        pool.quote_mint = ctx.accounts.quote_mint.key();
         pool.authority = ctx.accounts.authority.key();
         pool.order_count = 0;
-        pool.total_volume_sol = 0;
+        pool.open_order_count = 0;
+        pool.total_volume_quote = 0;
+        pool.batch_count = 0;
+        pool.open_batch_count = 0;
         pool.bump = ctx.bumps.dark_pool;
 
-        msg!("Dark pool initialized for mint {}", pool.mint);
+        emit!(DarkPoolInitialized {
+            dark_pool: pool.key(),
+            base_mint: pool.base_mint,
+            quote_mint: pool.quote_mint,
+            authority: pool.authority,
+        });
         Ok(())
     }
 
-    pub fn place_dark_order(
-        ctx: Context<PlaceDarkOrder>,
+    pub fn place_dark_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PlaceDarkOrder<'info>>,
         order_hash: [u8; 32],
-        escrow_sol: u64,
-        escrow_tokens: u64,
+        escrow_base: u64,
+        escrow_quote: u64,
+        expires_at: i64,
     ) -> Result<()> {
         // At least one side must have value
         require!(
-            escrow_sol > 0 || escrow_tokens > 0,
+            escrow_base > 0 || escrow_quote > 0,
             DarkPoolError::InvalidOrderParams
         );
+        // Zero means the order rests until filled or cancelled
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            expires_at == 0 || expires_at > now,
+            DarkPoolError::InvalidExpiry
+        );
 
-        // Escrow SOL if provided
-        if escrow_sol > 0 {
-            system_program::transfer(
-                CpiContext::new(
-                    ctx.accounts.system_program.to_account_info(),
-                    system_program::Transfer {
-                        from: ctx.accounts.maker.to_account_info(),
-                        to: ctx.accounts.dark_pool.to_account_info(),
-                    },
-                ),
-                escrow_sol,
-            )?;
-        }
-
-        // Escrow tokens if provided
-        if escrow_tokens > 0 {
-            token::transfer(
-                CpiContext::new(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.maker_token_account.to_account_info(),
-                        to: ctx.accounts.dp_token_vault.to_account_info(),
-                        authority: ctx.accounts.maker.to_account_info(),
-                    },
-                ),
-                escrow_tokens,
-            )?;
-        }
+        // Escrow is credited with what the vaults actually receive, net of any transfer fee
+        let escrow_base = deposit_to_vault(
+            &ctx.accounts.base_token_program,
+            &ctx.accounts.base_mint,
+            &ctx.accounts.maker_base_account,
+            &mut ctx.accounts.dp_base_vault,
+            ctx.accounts.maker.to_account_info(),
+            ctx.remaining_accounts,
+            escrow_base,
+        )?;
+        let escrow_quote = deposit_to_vault(
+            &ctx.accounts.quote_token_program,
+            &ctx.accounts.quote_mint,
+            &ctx.accounts.maker_quote_account,
+            &mut ctx.accounts.dp_quote_vault,
+            ctx.accounts.maker.to_account_info(),
+            ctx.remaining_accounts,
+            escrow_quote,
+        )?;
 
         let pool = &mut ctx.accounts.dark_pool;
         pool.order_count = pool.order_count.checked_add(1).unwrap();
+        pool.open_order_count = pool.open_order_count.checked_add(1).unwrap();
 
         let order = &mut ctx.accounts.dark_order;
         order.pool = pool.key();
         order.maker = ctx.accounts.maker.key();
         order.order_hash = order_hash;
-        order.escrow_sol = escrow_sol;
-        order.escrow_tokens = escrow_tokens;
+        order.escrow_base = escrow_base;
+        order.escrow_quote = escrow_quote;
+        order.remaining_base = escrow_base;
+        order.remaining_quote = escrow_quote;
+        order.filled_base = 0;
+        order.filled_quote = 0;
         order.is_filled = false;
         order.is_cancelled = false;
-        order.created_at = Clock::get()?.unix_timestamp;
+        order.created_at = now;
+        order.expires_at = expires_at;
         order.order_id = pool.order_count;
         order.bump = ctx.bumps.dark_order;
 
-        msg!("Dark order placed: id={}", order.order_id);
+        emit!(DarkOrderPlaced {
+            dark_pool: order.pool,
+            dark_order: order.key(),
+            order_id: order.order_id,
+            maker: order.maker,
+            escrow_base,
+            escrow_quote,
+            expires_at,
+        });
         Ok(())
     }
 
-    pub fn fill_dark_order(
-        ctx: Context<FillDarkOrder>,
+    /// Fills `fill_base` of an order for `base_amount` base tokens at `quote_amount` quote
+    /// tokens. An order can be filled in several parts; it closes once fully filled or its
+    /// escrow runs out. Anyone holding the secret can fill, so a fill seen in flight can be
+    /// copied; use `fill_designated_order` when that matters.
+    pub fn fill_dark_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FillDarkOrder<'info>>,
         secret: [u8; 32],
         side: u8,
-        token_amount: u64,
-        sol_amount: u64,
+        base_amount: u64,
+        quote_amount: u64,
+        fill_base: u64,
     ) -> Result<()> {
         let order = &ctx.accounts.dark_order;
-        require!(!order.is_filled, DarkPoolError::OrderAlreadyFilled);
-        require!(!order.is_cancelled, DarkPoolError::OrderCancelled);
+        require!(
+            dark_order_hash(&secret, side, base_amount, quote_amount, &order.maker)
+                == order.order_hash,
+            DarkPoolError::InvalidOrderProof
+        );
 
-        // Verify hash: SHA256(secret || side || token_amount_le || sol_amount_le || maker_pubkey)
-        let mut hash_input = Vec::with_capacity(81);
-        hash_input.extend_from_slice(&secret);
-        hash_input.push(side);
-        hash_input.extend_from_slice(&token_amount.to_le_bytes());
-        hash_input.extend_from_slice(&sol_amount.to_le_bytes());
-        hash_input.extend_from_slice(order.maker.as_ref());
-        let computed = anchor_lang::solana_program::hash::hash(&hash_input);
+        let fill_quote = DarkOrder::fill_quote(side, base_amount, quote_amount, fill_base)?;
+        ctx.accounts.settle_fill(
+            side,
+            fill_base,
+            fill_quote,
+            base_amount,
+            ctx.bumps.dp_vault_authority,
+            ctx.remaining_accounts,
+        )
+    }
 
+    /// Same terms as `fill_dark_order`, but the order hash also commits to the taker, so only
+    /// the signing taker can open it. A mempool observer who copies the secret derives a
+    /// different hash and cannot hijack the fill.
+    pub fn fill_designated_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FillDarkOrder<'info>>,
+        secret: [u8; 32],
+        side: u8,
+        base_amount: u64,
+        quote_amount: u64,
+        fill_base: u64,
+    ) -> Result<()> {
+        let order = &ctx.accounts.dark_order;
+        let taker = ctx.accounts.taker.key();
         require!(
-            computed.to_bytes() == order.order_hash,
+            designated_order_hash(&secret, side, base_amount, quote_amount, &taker, &order.maker)
+                == order.order_hash,
             DarkPoolError::InvalidOrderProof
         );
 
-        let pool_key = ctx.accounts.dark_pool.key();
-        let vault_auth_seeds = &[
-            b"dp_vault_auth".as_ref(),
-            pool_key.as_ref(),
-            &[ctx.bumps.dp_vault_authority],
-        ];
-        let signer_seeds = &[&vault_auth_seeds[..]];
+        let fill_quote = DarkOrder::fill_quote(side, base_amount, quote_amount, fill_base)?;
+        ctx.accounts.settle_fill(
+            side,
+            fill_base,
+            fill_quote,
+            base_amount,
+            ctx.bumps.dp_vault_authority,
+            ctx.remaining_accounts,
+        )
+    }
 
-        if side == 0 {
-            // Maker sells tokens: vault sends tokens to taker, taker sends SOL to maker
-            require!(order.escrow_tokens >= token_amount, DarkPoolError::InsufficientEscrow);
-
-            // Tokens: vault -> taker
-            token::transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.dp_token_vault.to_account_info(),
-                        to: ctx.accounts.taker_token_account.to_account_info(),
-                        authority: ctx.accounts.dp_vault_authority.to_account_info(),
-                    },
-                    signer_seeds,
-                ),
-                token_amount,
-            )?;
+    /// Fills a hidden limit order for up to `max_size` base tokens at `price` quote units per
+    /// whole base token. The taker picks the size and quote amount; any fill at or better than
+    /// the limit price is accepted.
+    #[allow(clippy::too_many_arguments)]
+    pub fn fill_limit_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FillDarkOrder<'info>>,
+        nonce: [u8; 32],
+        side: u8,
+        price: u64,
+        max_size: u64,
+        base_amount: u64,
+        quote_amount: u64,
+    ) -> Result<()> {
+        let order = &ctx.accounts.dark_order;
+        require!(
+            limit_order_hash(&nonce, side, price, max_size, &order.maker) == order.order_hash,
+            DarkPoolError::InvalidOrderProof
+        );
 
-            // SOL: taker -> maker
-            system_program::transfer(
-                CpiContext::new(
-                    ctx.accounts.system_program.to_account_info(),
-                    system_program::Transfer {
-                        from: ctx.accounts.taker.to_account_info(),
-                        to: ctx.accounts.maker.to_account_info(),
-                    },
-                ),
-                sol_amount,
-            )?;
-        } else if side == 1 {
-            // Maker buys tokens: pool sends escrowed SOL to taker, taker sends tokens to maker
-            require!(order.escrow_sol >= sol_amount, DarkPoolError::InsufficientEscrow);
-
-            // SOL: pool PDA -> taker (direct lamport manipulation since pool is program-owned)
-            let pool_info = ctx.accounts.dark_pool.to_account_info();
-            let taker_info = ctx.accounts.taker.to_account_info();
-            **pool_info.try_borrow_mut_lamports()? -= sol_amount;
-            **taker_info.try_borrow_mut_lamports()? += sol_amount;
-
-            // Tokens: taker -> maker token account
-            token::transfer(
-                CpiContext::new(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.taker_token_account.to_account_info(),
-                        to: ctx.accounts.maker_token_account.to_account_info(),
-                        authority: ctx.accounts.taker.to_account_info(),
-                    },
-                ),
-                token_amount,
+        // Compare quote / base_amount against price per 10^decimals base units
+        let unit = 10u128
+            .checked_pow(ctx.accounts.base_mint.decimals as u32)
+            .ok_or(DarkPoolError::InvalidOrderParams)?;
+        let scaled_quote = (quote_amount as u128)
+            .checked_mul(unit)
+            .ok_or(DarkPoolError::InvalidOrderParams)?;
+        let scaled_price = (price as u128).checked_mul(base_amount as u128).unwrap();
+        let within_limit = match side {
+            0 => scaled_quote >= scaled_price,
+            1 => scaled_quote <= scaled_price,
+            _ => return Err(DarkPoolError::InvalidSide.into()),
+        };
+        require!(within_limit, DarkPoolError::PriceLimitExceeded);
+
+        ctx.accounts.settle_fill(
+            side,
+            base_amount,
+            quote_amount,
+            max_size,
+            ctx.bumps.dp_vault_authority,
+            ctx.remaining_accounts,
+        )
+    }
+
+    /// Cancels the unfilled remainder of an order, returning whatever escrow is left.
+    pub fn cancel_dark_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CancelDarkOrder<'info>>,
+    ) -> Result<()> {
+        let order = &ctx.accounts.dark_order;
+        require!(!order.is_filled, DarkPoolError::OrderAlreadyFilled);
+        require!(!order.is_cancelled, DarkPoolError::OrderCancelled);
+
+        let pool_key = ctx.accounts.dark_pool.key();
+        let bump = ctx.bumps.dp_vault_authority;
+        if order.remaining_base > 0 {
+            dp_vault_transfer(
+                &ctx.accounts.base_token_program,
+                &ctx.accounts.base_mint,
+                &ctx.accounts.dp_base_vault,
+                &ctx.accounts.maker_base_account,
+                &ctx.accounts.dp_vault_authority,
+                ctx.remaining_accounts,
+                &pool_key,
+                bump,
+                order.remaining_base,
+            )?;
+        }
+        if order.remaining_quote > 0 {
+            dp_vault_transfer(
+                &ctx.accounts.quote_token_program,
+                &ctx.accounts.quote_mint,
+                &ctx.accounts.dp_quote_vault,
+                &ctx.accounts.maker_quote_account,
+                &ctx.accounts.dp_vault_authority,
+                ctx.remaining_accounts,
+                &pool_key,
+                bump,
+                order.remaining_quote,
             )?;
-        } else {
-            return Err(DarkPoolError::InvalidSide.into());
         }
 
         let order = &mut ctx.accounts.dark_order;
-        order.is_filled = true;
+        order.is_cancelled = true;
 
         let pool = &mut ctx.accounts.dark_pool;
-        pool.total_volume_sol = pool.total_volume_sol.checked_add(sol_amount).unwrap();
+        pool.open_order_count = pool.open_order_count.checked_sub(1).unwrap();
 
-        msg!("Dark order filled: id={}, side={}", order.order_id, side);
+        emit!(DarkOrderCancelled {
+            dark_pool: pool.key(),
+            dark_order: order.key(),
+            order_id: order.order_id,
+            filled_base: order.filled_base,
+            returned_base: order.remaining_base,
+            returned_quote: order.remaining_quote,
+        });
         Ok(())
     }
 
-    pub fn cancel_dark_order(ctx: Context<CancelDarkOrder>) -> Result<()> {
+    /// Permissionless: once an order has expired anyone can return its remaining escrow to
+    /// the maker and close it.
+    pub fn expire_dark_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExpireDarkOrder<'info>>,
+    ) -> Result<()> {
         let order = &ctx.accounts.dark_order;
         require!(!order.is_filled, DarkPoolError::OrderAlreadyFilled);
         require!(!order.is_cancelled, DarkPoolError::OrderCancelled);
+        require!(
+            order.is_expired(Clock::get()?.unix_timestamp),
+            DarkPoolError::OrderNotExpired
+        );
 
-        // Return escrowed SOL from pool PDA to maker
-        if order.escrow_sol > 0 {
-            let pool_info = ctx.accounts.dark_pool.to_account_info();
-            let maker_info = ctx.accounts.maker.to_account_info();
-            **pool_info.try_borrow_mut_lamports()? -= order.escrow_sol;
-            **maker_info.try_borrow_mut_lamports()? += order.escrow_sol;
+        let pool_key = ctx.accounts.dark_pool.key();
+        let bump = ctx.bumps.dp_vault_authority;
+        if order.remaining_base > 0 {
+            dp_vault_transfer(
+                &ctx.accounts.base_token_program,
+                &ctx.accounts.base_mint,
+                &ctx.accounts.dp_base_vault,
+                &ctx.accounts.maker_base_account,
+                &ctx.accounts.dp_vault_authority,
+                ctx.remaining_accounts,
+                &pool_key,
+                bump,
+                order.remaining_base,
+            )?;
+        }
+        if order.remaining_quote > 0 {
+            dp_vault_transfer(
+                &ctx.accounts.quote_token_program,
+                &ctx.accounts.quote_mint,
+                &ctx.accounts.dp_quote_vault,
+                &ctx.accounts.maker_quote_account,
+                &ctx.accounts.dp_vault_authority,
+                ctx.remaining_accounts,
+                &pool_key,
+                bump,
+                order.remaining_quote,
+            )?;
         }
 
-        // Return escrowed tokens from vault to maker
-        if order.escrow_tokens > 0 {
-            let pool_key = ctx.accounts.dark_pool.key();
-            let vault_auth_seeds = &[
-                b"dp_vault_auth".as_ref(),
-                pool_key.as_ref(),
-                &[ctx.bumps.dp_vault_authority],
-            ];
-            let signer_seeds = &[&vault_auth_seeds[..]];
-
-            token::transfer(
-                CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.dp_token_vault.to_account_info(),
-                        to: ctx.accounts.maker_token_account.to_account_info(),
-                        authority: ctx.accounts.dp_vault_authority.to_account_info(),
-                    },
-                    signer_seeds,
-                ),
-                order.escrow_tokens,
+        let pool = &mut ctx.accounts.dark_pool;
+        pool.open_order_count = pool.open_order_count.checked_sub(1).unwrap();
+
+        let order = &ctx.accounts.dark_order;
+        emit!(DarkOrderExpired {
+            dark_pool: pool.key(),
+            dark_order: order.key(),
+            order_id: order.order_id,
+            filled_base: order.filled_base,
+            returned_base: order.remaining_base,
+            returned_quote: order.remaining_quote,
+        });
+        Ok(())
+    }
+
+    /// Opens the next batch auction on a pool. Orders are committed until `commit_end`,
+    /// revealed until `reveal_end`, then cleared by `settle_batch`.
+    pub fn open_batch(ctx: Context<OpenBatch>, commit_end: i64, reveal_end: i64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now < commit_end && commit_end < reveal_end,
+            DarkPoolError::InvalidBatchWindow
+        );
+
+        let pool = &mut ctx.accounts.dark_pool;
+        let batch = &mut ctx.accounts.batch;
+        batch.pool = pool.key();
+        batch.batch_id = pool.batch_count;
+        batch.commit_end = commit_end;
+        batch.reveal_end = reveal_end;
+        batch.order_count = 0;
+        batch.revealed_count = 0;
+        batch.claimed_count = 0;
+        batch.is_settled = false;
+        batch.clearing_price = 0;
+        batch.clearing_volume = 0;
+        batch.bump = ctx.bumps.batch;
+        pool.batch_count = pool.batch_count.checked_add(1).unwrap();
+        pool.open_batch_count = pool.open_batch_count.checked_add(1).unwrap();
+
+        emit!(BatchOpened {
+            dark_pool: batch.pool,
+            batch: batch.key(),
+            batch_id: batch.batch_id,
+            commit_end,
+            reveal_end,
+        });
+        Ok(())
+    }
+
+    /// Commits a hidden batch order, escrowing enough to cover it. Escrow may exceed what the
+    /// order needs, and may include both mints, so it does not give away side or size.
+    pub fn commit_batch_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CommitBatchOrder<'info>>,
+        order_hash: [u8; 32],
+        escrow_base: u64,
+        escrow_quote: u64,
+    ) -> Result<()> {
+        let batch = &ctx.accounts.batch;
+        require!(
+            Clock::get()?.unix_timestamp < batch.commit_end,
+            DarkPoolError::BatchCommitClosed
+        );
+        require!(
+            batch.order_count < batch::MAX_BATCH_ORDERS,
+            DarkPoolError::BatchFull
+        );
+        require!(
+            escrow_base > 0 || escrow_quote > 0,
+            DarkPoolError::InvalidOrderParams
+        );
+
+        // Escrow is credited with what the vaults actually receive, net of any transfer fee
+        let escrow_base = deposit_to_vault(
+            &ctx.accounts.base_token_program,
+            &ctx.accounts.base_mint,
+            &ctx.accounts.owner_base_account,
+            &mut ctx.accounts.dp_base_vault,
+            ctx.accounts.owner.to_account_info(),
+            ctx.remaining_accounts,
+            escrow_base,
+        )?;
+        let escrow_quote = deposit_to_vault(
+            &ctx.accounts.quote_token_program,
+            &ctx.accounts.quote_mint,
+            &ctx.accounts.owner_quote_account,
+            &mut ctx.accounts.dp_quote_vault,
+            ctx.accounts.owner.to_account_info(),
+            ctx.remaining_accounts,
+            escrow_quote,
+        )?;
+
+        // Batch escrow sits in the pool vaults, so it counts as an open order until claimed
+        let pool = &mut ctx.accounts.dark_pool;
+        pool.open_order_count = pool.open_order_count.checked_add(1).unwrap();
+
+        let batch = &mut ctx.accounts.batch;
+        batch.order_count = batch.order_count.checked_add(1).unwrap();
+
+        let order = &mut ctx.accounts.batch_order;
+        order.batch = batch.key();
+        order.owner = ctx.accounts.owner.key();
+        order.order_hash = order_hash;
+        order.escrow_base = escrow_base;
+        order.escrow_quote = escrow_quote;
+        order.is_revealed = false;
+        order.side = 0;
+        order.price = 0;
+        order.size = 0;
+        order.reveal_index = 0;
+        order.fill_base = 0;
+        order.fill_quote = 0;
+        order.fee_quote = 0;
+        order.bump = ctx.bumps.batch_order;
+
+        emit!(BatchOrderCommitted {
+            batch: order.batch,
+            batch_order: order.key(),
+            owner: order.owner,
+            escrow_base,
+            escrow_quote,
+        });
+        Ok(())
+    }
+
+    /// Opens a committed batch order during the reveal window. Unrevealed orders sit out the
+    /// auction and get their escrow back when claimed.
+    pub fn reveal_batch_order(
+        ctx: Context<RevealBatchOrder>,
+        nonce: [u8; 32],
+        side: u8,
+        price: u64,
+        size: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let batch = &ctx.accounts.batch;
+        require!(
+            now >= batch.commit_end && now < batch.reveal_end,
+            DarkPoolError::BatchNotInReveal
+        );
+
+        let order = &ctx.accounts.batch_order;
+        require!(!order.is_revealed, DarkPoolError::AlreadyRevealed);
+        require!(
+            batch_order_hash(&nonce, side, price, size, &order.owner) == order.order_hash,
+            DarkPoolError::InvalidOrderProof
+        );
+        require!(price > 0 && size > 0, DarkPoolError::InvalidOrderParams);
+
+        // The escrow must cover the order at its limit price, plus a buyer's fee on that cost
+        match side {
+            batch::SIDE_SELL => {
+                require!(order.escrow_base >= size, DarkPoolError::InsufficientEscrow)
+            }
+            batch::SIDE_BUY => {
+                let unit = 10u128
+                    .checked_pow(ctx.accounts.base_mint.decimals as u32)
+                    .ok_or(DarkPoolError::InvalidOrderParams)?;
+                let max_cost = (size as u128 * price as u128).div_ceil(unit);
+                let max_fee = max_cost * ctx.accounts.protocol_config.taker_fee_bps as u128
+                    / BPS_DENOMINATOR as u128;
+                require!(
+                    order.escrow_quote as u128 >= max_cost + max_fee,
+                    DarkPoolError::InsufficientEscrow
+                );
+            }
+            _ => return Err(DarkPoolError::InvalidSide.into()),
+        }
+
+        let batch = &mut ctx.accounts.batch;
+        let order = &mut ctx.accounts.batch_order;
+        order.is_revealed = true;
+        order.side = side;
+        order.price = price;
+        order.size = size;
+        order.reveal_index = batch.revealed_count;
+        batch.revealed_count = batch.revealed_count.checked_add(1).unwrap();
+
+        emit!(BatchOrderRevealed {
+            batch: order.batch,
+            batch_order: order.key(),
+            reveal_index: order.reveal_index,
+            side,
+            price,
+            size,
+        });
+        Ok(())
+    }
+
+    /// Permissionless: clears a batch once its reveal window has closed. Every revealed order
+    /// must be passed in `remaining_accounts`, in reveal order; each is stamped with its fill
+    /// and paid out by `claim_batch_order`. A batch not settled within `batch::SETTLE_WINDOW`
+    /// of its reveal window closing is abandoned and refunds its orders instead.
+    pub fn settle_batch<'info>(ctx: Context<'_, '_, 'info, 'info, SettleBatch<'info>>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let batch = &ctx.accounts.batch;
+        require!(!batch.is_settled, DarkPoolError::BatchAlreadySettled);
+        require!(now >= batch.reveal_end, DarkPoolError::BatchRevealOpen);
+        require!(!batch.is_abandoned(now), DarkPoolError::BatchAbandoned);
+        require!(
+            ctx.remaining_accounts.len() == batch.revealed_count as usize,
+            DarkPoolError::IncompleteBatch
+        );
+
+        let batch_key = batch.key();
+        let mut orders = Vec::with_capacity(ctx.remaining_accounts.len());
+        for (index, info) in ctx.remaining_accounts.iter().enumerate() {
+            let order = Account::<BatchOrder>::try_from(info)?;
+            require!(
+                info.is_writable
+                    && order.batch == batch_key
+                    && order.is_revealed
+                    && order.reveal_index as usize == index,
+                DarkPoolError::IncompleteBatch
+            );
+            orders.push(order);
+        }
+
+        let revealed: Vec<batch::RevealedOrder> = orders
+            .iter()
+            .map(|order| batch::RevealedOrder {
+                side: order.side,
+                price: order.price,
+                size: order.size,
+            })
+            .collect();
+        let clearing = batch::clear(&revealed);
+        let fills = batch::allocate(&revealed, &clearing, ctx.accounts.base_mint.decimals)?;
+        // Matched volume is bounded by the sellers' escrowed base, so it fits a token amount
+        let clearing_volume =
+            u64::try_from(clearing.volume).map_err(|_| DarkPoolError::InvalidOrderParams)?;
+
+        // Protocol fees are charged in quote on top of the buyer's side and out of the seller's,
+        // at the taker and maker rates respectively. A buyer's fee is capped by its leftover
+        // escrow in case the rate rose after it revealed.
+        let config = &ctx.accounts.protocol_config;
+        let mut volume_quote = 0u64;
+        let mut total_fee = 0u64;
+        for (order, fill) in orders.iter_mut().zip(fills) {
+            order.fill_base = fill.base;
+            order.fill_quote = fill.quote;
+            order.fee_quote = if order.side == batch::SIDE_BUY {
+                volume_quote = volume_quote.checked_add(fill.quote).unwrap();
+                ProtocolConfig::fee(fill.quote, config.taker_fee_bps)
+                    .min(order.escrow_quote.saturating_sub(fill.quote))
+            } else {
+                ProtocolConfig::fee(fill.quote, config.maker_fee_bps)
+            };
+            total_fee = total_fee.checked_add(order.fee_quote).unwrap();
+            order.exit(&crate::ID)?;
+        }
+
+        let pool = &mut ctx.accounts.dark_pool;
+        pool.total_volume_quote = pool.total_volume_quote.checked_add(volume_quote).unwrap();
+
+        let batch = &mut ctx.accounts.batch;
+        batch.is_settled = true;
+        batch.clearing_price = clearing.price;
+        batch.clearing_volume = clearing_volume;
+
+        emit!(BatchSettled {
+            dark_pool: batch.pool,
+            batch: batch.key(),
+            batch_id: batch.batch_id,
+            clearing_price: clearing.price,
+            clearing_volume,
+            volume_quote,
+            total_fee,
+            revealed_count: batch.revealed_count,
+        });
+        Ok(())
+    }
+
+    /// Permissionless: pays a settled batch order its fill plus unused escrow, less its protocol
+    /// fee, and closes it. Orders in an abandoned batch were never filled, so they get their
+    /// escrow back whole.
+    pub fn claim_batch_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimBatchOrder<'info>>,
+    ) -> Result<()> {
+        let batch = &ctx.accounts.batch;
+        require!(
+            batch.is_settled || batch.is_abandoned(Clock::get()?.unix_timestamp),
+            DarkPoolError::BatchNotSettled
+        );
+
+        // Fills stay zero unless the batch settled, so an abandoned batch pays back the escrow
+        let order = &ctx.accounts.batch_order;
+        let (base_out, quote_out) = match (order.is_revealed, order.side) {
+            (true, batch::SIDE_BUY) => (
+                order.escrow_base.checked_add(order.fill_base).unwrap(),
+                order
+                    .escrow_quote
+                    .checked_sub(order.fill_quote)
+                    .and_then(|left| left.checked_sub(order.fee_quote))
+                    .unwrap(),
+            ),
+            (true, _) => (
+                order.escrow_base.checked_sub(order.fill_base).unwrap(),
+                order
+                    .escrow_quote
+                    .checked_add(order.fill_quote)
+                    .and_then(|total| total.checked_sub(order.fee_quote))
+                    .unwrap(),
+            ),
+            (false, _) => (order.escrow_base, order.escrow_quote),
+        };
+
+        let pool_key = ctx.accounts.dark_pool.key();
+        let bump = ctx.bumps.dp_vault_authority;
+        if base_out > 0 {
+            dp_vault_transfer(
+                &ctx.accounts.base_token_program,
+                &ctx.accounts.base_mint,
+                &ctx.accounts.dp_base_vault,
+                &ctx.accounts.owner_base_account,
+                &ctx.accounts.dp_vault_authority,
+                ctx.remaining_accounts,
+                &pool_key,
+                bump,
+                base_out,
+            )?;
+        }
+        if quote_out > 0 {
+            dp_vault_transfer(
+                &ctx.accounts.quote_token_program,
+                &ctx.accounts.quote_mint,
+                &ctx.accounts.dp_quote_vault,
+                &ctx.accounts.owner_quote_account,
+                &ctx.accounts.dp_vault_authority,
+                ctx.remaining_accounts,
+                &pool_key,
+                bump,
+                quote_out,
+            )?;
+        }
+        let fee_quote = order.fee_quote;
+        if fee_quote > 0 {
+            dp_vault_transfer(
+                &ctx.accounts.quote_token_program,
+                &ctx.accounts.quote_mint,
+                &ctx.accounts.dp_quote_vault,
+                &ctx.accounts.dp_fee_vault,
+                &ctx.accounts.dp_vault_authority,
+                ctx.remaining_accounts,
+                &pool_key,
+                bump,
+                fee_quote,
             )?;
         }
 
-        let order = &mut ctx.accounts.dark_order;
-        order.is_cancelled = true;
+        let pool = &mut ctx.accounts.dark_pool;
+        pool.open_order_count = pool.open_order_count.checked_sub(1).unwrap();
+
+        let batch = &mut ctx.accounts.batch;
+        batch.claimed_count = batch.claimed_count.checked_add(1).unwrap();
+
+        emit!(BatchOrderClaimed {
+            batch: batch.key(),
+            batch_order: ctx.accounts.batch_order.key(),
+            owner: ctx.accounts.owner.key(),
+            fill_base: ctx.accounts.batch_order.fill_base,
+            fill_quote: ctx.accounts.batch_order.fill_quote,
+            fee_quote,
+            base_out,
+            quote_out,
+        });
+        Ok(())
+    }
+
+    /// Permissionless: closes a batch once every order in it has been claimed, returning its
+    /// rent to the pool authority. Until then the pool cannot be closed.
+    pub fn close_batch(ctx: Context<CloseBatch>) -> Result<()> {
+        let batch = &ctx.accounts.batch;
+        require!(
+            batch.is_settled || batch.is_abandoned(Clock::get()?.unix_timestamp),
+            DarkPoolError::BatchNotSettled
+        );
+        require!(
+            batch.claimed_count == batch.order_count,
+            DarkPoolError::OpenOrdersRemaining
+        );
+
+        let pool = &mut ctx.accounts.dark_pool;
+        pool.open_batch_count = pool.open_batch_count.checked_sub(1).unwrap();
+
+        emit!(BatchClosed {
+            dark_pool: pool.key(),
+            batch: batch.key(),
+            batch_id: batch.batch_id,
+        });
+        Ok(())
+    }
+
+    pub fn close_dark_pool<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseDarkPool<'info>>,
+    ) -> Result<()> {
+        let pool = &ctx.accounts.dark_pool;
+        require!(pool.open_order_count == 0, DarkPoolError::OpenOrdersRemaining);
+        // A batch left behind would sit at the address a re-created pool opens its first batch at
+        require!(pool.open_batch_count == 0, DarkPoolError::OpenBatchesRemaining);
+
+        let pool_key = ctx.accounts.dark_pool.key();
+        let vault_auth_seeds = &[
+            b"dp_vault_auth".as_ref(),
+            pool_key.as_ref(),
+            &[ctx.bumps.dp_vault_authority],
+        ];
+        let signer_seeds = &[&vault_auth_seeds[..]];
+
+        // With no open orders the vaults hold no escrow. Anything left is rounding or a
+        // donation, which goes to the authority rather than blocking the close.
+        let accounts = &ctx.accounts;
+        for (token_program, mint, vault, to) in [
+            (
+                &accounts.base_token_program,
+                &accounts.base_mint,
+                &accounts.dp_base_vault,
+                &accounts.authority_base_account,
+            ),
+            (
+                &accounts.quote_token_program,
+                &accounts.quote_mint,
+                &accounts.dp_quote_vault,
+                &accounts.authority_quote_account,
+            ),
+        ] {
+            if vault.amount > 0 {
+                dp_vault_transfer(
+                    token_program,
+                    mint,
+                    vault,
+                    to,
+                    &accounts.dp_vault_authority,
+                    ctx.remaining_accounts,
+                    &pool_key,
+                    ctx.bumps.dp_vault_authority,
+                    vault.amount,
+                )?;
+            }
+        }
+
+        for (token_program, mint, vault) in [
+            (&accounts.base_token_program, &accounts.base_mint, &accounts.dp_base_vault),
+            (&accounts.quote_token_program, &accounts.quote_mint, &accounts.dp_quote_vault),
+            (&accounts.quote_token_program, &accounts.quote_mint, &accounts.dp_fee_vault),
+        ] {
+            harvest_withheld_fees(token_program, mint, vault)?;
+            token_interface::close_account(CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                CloseAccount {
+                    account: vault.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: ctx.accounts.dp_vault_authority.to_account_info(),
+                },
+                signer_seeds,
+            ))?;
+        }
+
+        emit!(DarkPoolClosed {
+            dark_pool: pool.key(),
+            order_count: pool.order_count,
+        });
+        Ok(())
+    }
+
+    // ─── Protocol Instructions ───
+
+    /// Creates the global fee config. Only the program's upgrade authority can call this, and
+    /// becomes its admin.
+    pub fn initialize_protocol_config(
+        ctx: Context<InitializeProtocolConfig>,
+        fee_recipient: Pubkey,
+        presale_fee_bps: u16,
+        taker_fee_bps: u16,
+        maker_fee_bps: u16,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.protocol_config;
+        config.admin = ctx.accounts.admin.key();
+        config.pending_admin = Pubkey::default();
+        config.paused = PauseFlags::default();
+        config.bump = ctx.bumps.protocol_config;
+        config.apply(fee_recipient, presale_fee_bps, taker_fee_bps, maker_fee_bps)?;
+
+        emit!(ProtocolConfigUpdated {
+            admin: config.admin,
+            fee_recipient,
+            presale_fee_bps,
+            taker_fee_bps,
+            maker_fee_bps,
+        });
+        Ok(())
+    }
+
+    pub fn update_protocol_config(
+        ctx: Context<UpdateProtocolConfig>,
+        fee_recipient: Pubkey,
+        presale_fee_bps: u16,
+        taker_fee_bps: u16,
+        maker_fee_bps: u16,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.protocol_config;
+        config.apply(fee_recipient, presale_fee_bps, taker_fee_bps, maker_fee_bps)?;
+
+        emit!(ProtocolConfigUpdated {
+            admin: config.admin,
+            fee_recipient,
+            presale_fee_bps,
+            taker_fee_bps,
+            maker_fee_bps,
+        });
+        Ok(())
+    }
+
+    pub fn set_pause_flags(ctx: Context<UpdateProtocolConfig>, paused: PauseFlags) -> Result<()> {
+        let config = &mut ctx.accounts.protocol_config;
+        config.paused = paused;
+
+        emit!(PauseFlagsUpdated {
+            admin: config.admin,
+            commits: paused.commits,
+            claims: paused.claims,
+            placement: paused.placement,
+            fills: paused.fills,
+        });
+        Ok(())
+    }
+
+    /// First step of an admin handover. Proposing the default pubkey withdraws the offer.
+    pub fn propose_admin(ctx: Context<UpdateProtocolConfig>, new_admin: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.protocol_config;
+        config.pending_admin = new_admin;
+
+        emit!(AdminProposed {
+            admin: config.admin,
+            pending_admin: new_admin,
+        });
+        Ok(())
+    }
+
+    /// Second step: the proposed admin signs to take over.
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        let config = &mut ctx.accounts.protocol_config;
+        let previous_admin = config.admin;
+        config.admin = config.pending_admin;
+        config.pending_admin = Pubkey::default();
+
+        emit!(AdminTransferred {
+            previous_admin,
+            admin: config.admin,
+        });
+        Ok(())
+    }
+
+    /// Installs the verifying key `claim_tokens_zk` checks proofs against. It must come from a
+    /// trusted setup ceremony for the claim circuit: whoever knows a key's toxic waste can
+    /// forge claims, so the key can be set only once.
+    pub fn set_claim_verifying_key(
+        ctx: Context<SetClaimVerifyingKey>,
+        key: groth16::VerifyingKeyData,
+    ) -> Result<()> {
+        let account = &mut ctx.accounts.claim_verifying_key;
+        require!(account.bump == 0, ProtocolError::VerifyingKeyAlreadySet);
+        require!(key.is_valid(), ProtocolError::InvalidVerifyingKey);
+        account.key = key;
+        account.bump = ctx.bumps.claim_verifying_key;
+
+        emit!(ClaimVerifyingKeySet {
+            admin: ctx.accounts.admin.key(),
+        });
+        Ok(())
+    }
+
+    /// Permissionless: sweeps presale fees held on the config account to the fee recipient.
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        let config_info = ctx.accounts.protocol_config.to_account_info();
+        let rent_exempt = Rent::get()?.minimum_balance(config_info.data_len());
+        let amount = config_info.lamports().saturating_sub(rent_exempt);
+        require!(amount > 0, ProtocolError::NothingToCollect);
+
+        **config_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.fee_recipient.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        emit!(ProtocolFeesCollected {
+            source: config_info.key(),
+            fee_recipient: ctx.accounts.fee_recipient.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Permissionless: sweeps a dark pool's fee vault to the fee recipient's quote account.
+    pub fn collect_dark_pool_fees<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CollectDarkPoolFees<'info>>,
+    ) -> Result<()> {
+        let amount = ctx.accounts.dp_fee_vault.amount;
+        require!(amount > 0, ProtocolError::NothingToCollect);
+
+        dp_vault_transfer(
+            &ctx.accounts.quote_token_program,
+            &ctx.accounts.quote_mint,
+            &ctx.accounts.dp_fee_vault,
+            &ctx.accounts.fee_recipient_quote_account,
+            &ctx.accounts.dp_vault_authority,
+            ctx.remaining_accounts,
+            &ctx.accounts.dark_pool.key(),
+            ctx.bumps.dp_vault_authority,
+            amount,
+        )?;
 
-        msg!("Dark order cancelled: id={}", order.order_id);
+        emit!(ProtocolFeesCollected {
+            source: ctx.accounts.dp_fee_vault.key(),
+            fee_recipient: ctx.accounts.protocol_config.fee_recipient,
+            amount,
+        });
         Ok(())
     }
 }
 
+// ─── Helpers ───
+
+/// Checks a claim proof against the key installed by `set_claim_verifying_key`. Until the
+/// admin installs one, the account is empty and ZK claims are refused.
+fn verify_claim_proof(
+    key_account: Option<&UncheckedAccount>,
+    proof: &groth16::Groth16Proof,
+    public_inputs: &[[u8; 32]],
+) -> Result<()> {
+    let key_account = match key_account {
+        Some(account) if *account.owner == crate::ID => account,
+        _ => return err!(PresaleError::ZkClaimsDisabled),
+    };
+    let key = ClaimVerifyingKey::try_deserialize(&mut &key_account.try_borrow_data()?[..])?;
+    groth16::verify(&key.key.verifying_key(), proof, public_inputs)
+}
+
+pub const BPS_DENOMINATOR: u16 = 10_000;
+
+/// Ceiling on every protocol fee rate.
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+/// SHA256(secret || wallet), the commitment a participant registers at commit time
+/// and later opens to claim (or refund) into `wallet`.
+pub fn commitment_hash(secret: &[u8; 32], wallet: &Pubkey) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[secret, wallet.as_ref()]).to_bytes()
+}
+
+/// SHA256(secret || side || base_amount_le || quote_amount_le || maker), the hidden terms of
+/// a fixed-price dark order.
+pub fn dark_order_hash(
+    secret: &[u8; 32],
+    side: u8,
+    base_amount: u64,
+    quote_amount: u64,
+    maker: &Pubkey,
+) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[
+        secret,
+        &[side],
+        &base_amount.to_le_bytes(),
+        &quote_amount.to_le_bytes(),
+        maker.as_ref(),
+    ])
+    .to_bytes()
+}
+
+/// SHA256("taker" || secret || side || base_amount_le || quote_amount_le || taker || maker),
+/// a fixed-price dark order that only `taker` can fill.
+pub fn designated_order_hash(
+    secret: &[u8; 32],
+    side: u8,
+    base_amount: u64,
+    quote_amount: u64,
+    taker: &Pubkey,
+    maker: &Pubkey,
+) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[
+        b"taker",
+        secret,
+        &[side],
+        &base_amount.to_le_bytes(),
+        &quote_amount.to_le_bytes(),
+        taker.as_ref(),
+        maker.as_ref(),
+    ])
+    .to_bytes()
+}
+
+/// SHA256("limit" || nonce || side || price_le || max_size_le || maker), the hidden terms of a
+/// limit order. The tag keeps a limit order from being opened as a fixed-price one.
+pub fn limit_order_hash(
+    nonce: &[u8; 32],
+    side: u8,
+    price: u64,
+    max_size: u64,
+    maker: &Pubkey,
+) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[
+        b"limit",
+        nonce,
+        &[side],
+        &price.to_le_bytes(),
+        &max_size.to_le_bytes(),
+        maker.as_ref(),
+    ])
+    .to_bytes()
+}
+
+/// SHA256("batch" || nonce || side || price_le || size_le || owner), the hidden terms of a
+/// batch auction order.
+pub fn batch_order_hash(
+    nonce: &[u8; 32],
+    side: u8,
+    price: u64,
+    size: u64,
+    owner: &Pubkey,
+) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[
+        b"batch",
+        nonce,
+        &[side],
+        &price.to_le_bytes(),
+        &size.to_le_bytes(),
+        owner.as_ref(),
+    ])
+    .to_bytes()
+}
+
+/// `transfer_checked` through whichever token program owns `mint`. Token-2022 transfer hooks
+/// resolve their extra accounts from `hook_accounts`, forwarded from the remaining accounts.
+#[allow(clippy::too_many_arguments)]
+fn transfer_tokens<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    authority: AccountInfo<'info>,
+    hook_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    spl_token_2022::onchain::invoke_transfer_checked(
+        &token_program.key(),
+        from.to_account_info(),
+        mint.to_account_info(),
+        to.to_account_info(),
+        authority,
+        hook_accounts,
+        amount,
+        mint.decimals,
+        signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+/// Deposits `amount` into a program vault and returns what the vault actually received, which
+/// falls short of `amount` when the mint charges a transfer fee.
+fn deposit_to_vault<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    vault: &mut InterfaceAccount<'info, TokenAccount>,
+    authority: AccountInfo<'info>,
+    hook_accounts: &[AccountInfo<'info>],
+    amount: u64,
+) -> Result<u64> {
+    if amount == 0 {
+        return Ok(0);
+    }
+    let before = vault.amount;
+    transfer_tokens(token_program, from, mint, vault, authority, hook_accounts, amount, &[])?;
+    vault.reload()?;
+    Ok(vault.amount.checked_sub(before).unwrap())
+}
+
+/// Pays `amount` out of one of a dark pool's vaults, signed by its vault authority PDA.
+#[allow(clippy::too_many_arguments)]
+fn dp_vault_transfer<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    vault_authority: &UncheckedAccount<'info>,
+    hook_accounts: &[AccountInfo<'info>],
+    dark_pool: &Pubkey,
+    vault_auth_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let seeds = &[
+        b"dp_vault_auth".as_ref(),
+        dark_pool.as_ref(),
+        &[vault_auth_bump],
+    ];
+    transfer_tokens(
+        token_program,
+        vault,
+        mint,
+        to,
+        vault_authority.to_account_info(),
+        hook_accounts,
+        amount,
+        &[&seeds[..]],
+    )
+}
+
+/// Moves Token-2022 transfer fees withheld on `vault` into its mint, since an account still
+/// holding them cannot be closed. Mints without the transfer fee extension are skipped.
+fn harvest_withheld_fees<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if token_interface::get_mint_extension_data::<TransferFeeConfig>(&mint_info).is_err() {
+        return Ok(());
+    }
+    token_interface::harvest_withheld_tokens_to_mint(
+        CpiContext::new(
+            token_program.to_account_info(),
+            HarvestWithheldTokensToMint {
+                token_program_id: token_program.to_account_info(),
+                mint: mint_info,
+            },
+        ),
+        vec![vault.to_account_info()],
+    )
+}
+
 // ─── Account Structs ───
 
+/// Release schedule applied to every commitment's allocation, measured from finalization.
+/// `tge_unlock_bps` unlocks at finalization, the rest vests linearly over `vesting_duration`
+/// once `cliff_duration` has elapsed. The default schedule releases everything at finalization.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct VestingSchedule {
+    pub tge_unlock_bps: u16,
+    pub cliff_duration: i64,
+    pub vesting_duration: i64,
+}
+
+/// Per-commitment bounds on `commit_to_presale`. A zero `max_commitment` or `max_commitments`
+/// leaves that bound unlimited.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct ContributionLimits {
+    pub min_commitment: u64,
+    pub max_commitment: u64,
+    pub max_commitments: u32,
+}
+
+impl VestingSchedule {
+    pub fn vested_amount(&self, total: u64, start: i64, now: i64) -> u64 {
+        let tge = (total as u128) * (self.tge_unlock_bps as u128) / (BPS_DENOMINATOR as u128);
+        let elapsed = now.saturating_sub(start);
+        if elapsed < self.cliff_duration {
+            return tge as u64;
+        }
+        if self.vesting_duration == 0 {
+            return total;
+        }
+
+        let vesting_elapsed = (elapsed - self.cliff_duration).min(self.vesting_duration);
+        let linear =
+            (total as u128 - tge) * (vesting_elapsed as u128) / (self.vesting_duration as u128);
+        (tge + linear) as u64
+    }
+}
+
 #[account]
 pub struct Presale {
     pub creator: Pubkey,
     pub mint: Pubkey,
     pub total_sol_committed: u64,
     pub hard_cap: u64,
+    pub soft_cap: u64,
     pub tokens_for_sale: u64,
     pub start_time: i64,
     pub end_time: i64,
+    /// Seconds after `finalized_at` during which tokens can be claimed; zero for no limit
+    pub claim_window: i64,
+    pub vesting: VestingSchedule,
+    pub limits: ContributionLimits,
+    /// Fixed commitment size; zero accepts any amount. A denominated commitment is exactly one
+    /// unit rather than a multiple, so larger buyers commit several times from separate
+    /// burners instead of standing out by amount.
+    pub denomination: u64,
+    /// Root of the allowlist's Poseidon tree; all zeros leaves the presale open
+    pub whitelist_root: [u8; 32],
+    pub finalized_at: i64,
     pub is_finalized: bool,
+    pub is_cancelled: bool,
+    pub vault_closed: bool,
     pub commitment_count: u32,
+    pub settled_count: u32,
+    /// Allowlist nullifiers spent by commits and not yet closed
+    pub whitelist_nullifier_count: u32,
+    /// Pooled leaves neither fully claimed nor refunded
+    pub open_pool_leaves: u32,
     pub bump: u8,
     pub vault_auth_bump: u8,
 }
 
+impl Presale {
+    /// Checks a new commitment of `sol_amount` against the presale window, caps and limits.
+    pub fn validate_commitment(&self, sol_amount: u64, now: i64) -> Result<()> {
+        require!(now >= self.start_time, PresaleError::NotStarted);
+        require!(now <= self.end_time, PresaleError::Ended);
+        require!(!self.is_finalized, PresaleError::AlreadyFinalized);
+        require!(!self.is_cancelled, PresaleError::Cancelled);
+        require!(sol_amount > 0, PresaleError::InvalidAmount);
+        // A denominated commitment is exactly one unit, so no commitment stands out by size
+        // and every claim releases the same allocation
+        require!(
+            self.denomination == 0 || sol_amount == self.denomination,
+            PresaleError::InvalidDenomination
+        );
+        require!(
+            sol_amount >= self.limits.min_commitment,
+            PresaleError::BelowMinCommitment
+        );
+        require!(
+            self.limits.max_commitment == 0 || sol_amount <= self.limits.max_commitment,
+            PresaleError::AboveMaxCommitment
+        );
+        require!(
+            self.limits.max_commitments == 0 || self.commitment_count < self.limits.max_commitments,
+            PresaleError::MaxCommitmentsReached
+        );
+        require!(
+            self.total_sol_committed.checked_add(sol_amount).unwrap() <= self.hard_cap,
+            PresaleError::HardCapExceeded
+        );
+        Ok(())
+    }
+
+    /// When the presale has an allowlist, requires a proof that `participant` holds one of its
+    /// leaves and spends the leaf's nullifier, so each leaf admits a single commitment.
+    pub fn check_whitelist(
+        &mut self,
+        participant: &Pubkey,
+        nullifier_hash: [u8; 32],
+        proof: Option<&groth16::Groth16Proof>,
+        verifying_key: Option<&UncheckedAccount>,
+        spent: Option<&mut Account<WhitelistNullifier>>,
+    ) -> Result<()> {
+        if self.whitelist_root == [0u8; 32] {
+            // A nullifier created here would never be counted, or closed
+            require!(spent.is_none(), PresaleError::NoWhitelist);
+            return Ok(());
+        }
+        let (Some(proof), Some(spent)) = (proof, spent) else {
+            return err!(PresaleError::NotWhitelisted);
+        };
+        require!(
+            spent.payer == Pubkey::default(),
+            PresaleError::WhitelistNullifierUsed
+        );
+
+        // Public inputs: [whitelist_root, nullifier_hash, participant_hi, participant_lo]
+        let [participant_hi, participant_lo] = merkle::wallet_halves(participant);
+        let public_inputs = [self.whitelist_root, nullifier_hash, participant_hi, participant_lo];
+        verify_claim_proof(verifying_key, proof, &public_inputs)?;
+
+        spent.payer = *participant;
+        self.whitelist_nullifier_count = self.whitelist_nullifier_count.checked_add(1).unwrap();
+        Ok(())
+    }
+
+    /// Whether a finalized presale's claim window has run out, releasing unclaimed tokens.
+    pub fn claim_window_closed(&self, now: i64) -> bool {
+        self.is_finalized
+            && self.claim_window != 0
+            && now > self.finalized_at.saturating_add(self.claim_window)
+    }
+
+    /// Allocation for `sol_amount` at the presale's fixed price of `tokens_for_sale` per
+    /// `hard_cap`, so a presale that ends under its cap leaves the unsold share in the vault
+    /// for the creator. In denomination mode every commitment is one unit, so every claim
+    /// receives the same allocation and claims are indistinguishable.
+    pub fn tokens_owed(&self, sol_amount: u64) -> u64 {
+        (sol_amount as u128)
+            .checked_mul(self.tokens_for_sale as u128)
+            .unwrap()
+            .checked_div(self.hard_cap as u128)
+            .unwrap() as u64
+    }
+}
+
 #[account]
 pub struct Commitment {
     pub presale: Pubkey,
     pub commitment_hash: [u8; 32],
     pub sol_amount: u64,
+    pub claimed_amount: u64,
     pub is_claimed: bool,
     pub bump: u8,
 }
 
 #[account]
-pub struct DarkPool {
-    pub mint: Pubkey,
-    pub authority: Pubkey,
-    pub order_count: u64,
-    pub total_volume_sol: u64,
+pub struct CommitmentTree {
+    pub presale: Pubkey,
+    pub next_index: u32,
+    pub root: [u8; 32],
+    pub filled_subtrees: [[u8; 32]; merkle::TREE_DEPTH],
     pub bump: u8,
 }
 
+/// The claim circuit's verifying key, installed once by the protocol admin.
 #[account]
-pub struct DarkOrder {
-    pub pool: Pubkey,
-    pub maker: Pubkey,
-    pub order_hash: [u8; 32],
-    pub escrow_sol: u64,
-    pub escrow_tokens: u64,
-    pub is_filled: bool,
-    pub is_cancelled: bool,
-    pub created_at: i64,
-    pub order_id: u64,
+pub struct ClaimVerifyingKey {
+    pub key: groth16::VerifyingKeyData,
     pub bump: u8,
 }
 
-// ─── Instruction Accounts ───
+/// Marks an allowlist leaf spent. Seeded by its nullifier hash, so it names neither the leaf
+/// nor its index.
+#[account]
+pub struct WhitelistNullifier {
+    /// The burner that committed, which gets the rent back once the presale ends.
+    pub payer: Pubkey,
+}
 
-#[derive(Accounts)]
+/// Marks a pooled leaf as spent. Seeded by its nullifier hash, so a leaf cannot be
+/// claimed or refunded twice even though the claim never names the leaf's index.
+#[account]
+pub struct Nullifier {
+    pub presale: Pubkey,
+    pub claimed_amount: u64,
+    pub is_settled: bool,
+    pub bump: u8,
+}
+
+/// A hidden order book for one `base_mint` / `quote_mint` pair. SOL markets quote against
+/// the wrapped SOL mint.
+#[account]
+pub struct DarkPool {
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub authority: Pubkey,
+    pub order_count: u64,
+    pub open_order_count: u64,
+    pub total_volume_quote: u64,
+    pub batch_count: u64,
+    /// Batches opened and not yet closed by `close_batch`.
+    pub open_batch_count: u32,
+    pub bump: u8,
+}
+
+#[account]
+pub struct DarkOrder {
+    pub pool: Pubkey,
+    pub maker: Pubkey,
+    pub order_hash: [u8; 32],
+    pub escrow_base: u64,
+    pub escrow_quote: u64,
+    /// Escrow not yet paid out to takers.
+    pub remaining_base: u64,
+    pub remaining_quote: u64,
+    pub filled_base: u64,
+    pub filled_quote: u64,
+    pub is_filled: bool,
+    pub is_cancelled: bool,
+    pub created_at: i64,
+    /// Zero for orders that never expire.
+    pub expires_at: i64,
+    pub order_id: u64,
+    pub bump: u8,
+}
+
+impl DarkOrder {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at != 0 && now >= self.expires_at
+    }
+
+    /// Quote owed for `fill_base` of an order for `base_amount` at `quote_amount`, rounded in
+    /// the maker's favour: up when the maker sells, down when the maker buys.
+    pub fn fill_quote(side: u8, base_amount: u64, quote_amount: u64, fill_base: u64) -> Result<u64> {
+        require!(base_amount > 0, DarkPoolError::InvalidOrderParams);
+        let numerator = (fill_base as u128).checked_mul(quote_amount as u128).unwrap();
+        let denominator = base_amount as u128;
+        let fill_quote = match side {
+            0 => numerator.div_ceil(denominator),
+            1 => numerator / denominator,
+            _ => return Err(DarkPoolError::InvalidSide.into()),
+        };
+        Ok(fill_quote as u64)
+    }
+}
+
+/// One commit/reveal/settle round of a dark pool's batch auction.
+#[account]
+pub struct BatchAuction {
+    pub pool: Pubkey,
+    pub batch_id: u64,
+    pub commit_end: i64,
+    pub reveal_end: i64,
+    pub order_count: u32,
+    pub revealed_count: u32,
+    pub claimed_count: u32,
+    pub is_settled: bool,
+    pub clearing_price: u64,
+    pub clearing_volume: u64,
+    pub bump: u8,
+}
+
+impl BatchAuction {
+    /// Whether the batch missed its settlement window, so it can only refund.
+    pub fn is_abandoned(&self, now: i64) -> bool {
+        !self.is_settled && now >= self.reveal_end.saturating_add(batch::SETTLE_WINDOW)
+    }
+}
+
+#[account]
+pub struct BatchOrder {
+    pub batch: Pubkey,
+    pub owner: Pubkey,
+    pub order_hash: [u8; 32],
+    pub escrow_base: u64,
+    pub escrow_quote: u64,
+    pub is_revealed: bool,
+    pub side: u8,
+    /// Limit price in quote units per whole base token.
+    pub price: u64,
+    pub size: u64,
+    pub reveal_index: u32,
+    /// Set by `settle_batch`.
+    pub fill_base: u64,
+    pub fill_quote: u64,
+    /// Protocol fee owed on the fill, paid to `dp_fee_vault` when the order is claimed.
+    pub fee_quote: u64,
+    pub bump: u8,
+}
+
+/// Emergency switches, each stopping one class of instruction. Refunds, cancellations and
+/// expiry stay available so users can always get their funds back out.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct PauseFlags {
+    /// `commit_to_presale` and `commit_to_pool`.
+    pub commits: bool,
+    /// `claim_tokens`, `claim_from_pool` and `claim_tokens_zk`.
+    pub claims: bool,
+    /// `place_dark_order` and `commit_batch_order`.
+    pub placement: bool,
+    /// Dark order fills and `settle_batch`.
+    pub fills: bool,
+}
+
+/// Global admin and fee settings. Presale fees accrue as lamports on this account; dark pool
+/// fees accrue in each pool's `dp_fee_vault`.
+#[account]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,
+    /// Set by `propose_admin`; takes over once it calls `accept_admin`.
+    pub pending_admin: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub presale_fee_bps: u16,
+    pub taker_fee_bps: u16,
+    pub maker_fee_bps: u16,
+    pub paused: PauseFlags,
+    pub bump: u8,
+}
+
+impl ProtocolConfig {
+    pub fn fee(amount: u64, bps: u16) -> u64 {
+        ((amount as u128) * (bps as u128) / (BPS_DENOMINATOR as u128)) as u64
+    }
+
+    fn apply(
+        &mut self,
+        fee_recipient: Pubkey,
+        presale_fee_bps: u16,
+        taker_fee_bps: u16,
+        maker_fee_bps: u16,
+    ) -> Result<()> {
+        require!(
+            presale_fee_bps <= MAX_FEE_BPS
+                && taker_fee_bps <= MAX_FEE_BPS
+                && maker_fee_bps <= MAX_FEE_BPS,
+            ProtocolError::FeeTooHigh
+        );
+        self.fee_recipient = fee_recipient;
+        self.presale_fee_bps = presale_fee_bps;
+        self.taker_fee_bps = taker_fee_bps;
+        self.maker_fee_bps = maker_fee_bps;
+        Ok(())
+    }
+}
+
+// ─── Instruction Accounts ───
+
+#[derive(Accounts)]
 pub struct InitializePresale<'info> {
     #[account(mut)]
     pub creator: Signer<'info>,
 
-    pub mint: Account<'info, Mint>,
+    pub mint: InterfaceAccount<'info, Mint>,
 
     #[account(
         init,
         payer = creator,
-        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 4 + 1 + 1 + 64,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + (2 + 8 + 8) + (8 + 8 + 4) + 8 + 32 + 8 + 1 + 1 + 1 + 4 + 4 + 4 + 4 + 1 + 1 + 64,
         seeds = [b"presale", mint.key().as_ref(), creator.key().as_ref()],
         bump
     )]
@@ -478,262 +2278,1725 @@ pub struct InitializePresale<'info> {
         seeds = [b"token_vault", presale.key().as_ref()],
         bump
     )]
-    pub token_vault: Account<'info, TokenAccount>,
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
 
     /// CHECK: PDA authority for token vault, validated by seeds
     #[account(
         seeds = [b"vault_auth", presale.key().as_ref()],
         bump
     )]
-    pub vault_authority: UncheckedAccount<'info>,
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = creator,
+    )]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(commitment_hash: [u8; 32], sol_amount: u64, whitelist_nullifier_hash: [u8; 32])]
+pub struct CommitToPresale<'info> {
+    #[account(mut)]
+    pub participant: Signer<'info>,
+
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused.commits @ ProtocolError::Paused
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = participant,
+        space = 8 + 32 + 32 + 8 + 8 + 1 + 1 + 32,
+        seeds = [b"commitment", presale.key().as_ref(), &commitment_hash],
+        bump
+    )]
+    pub commitment: Account<'info, Commitment>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: The `ClaimVerifyingKey` PDA. This and `whitelist_nullifier` are only needed
+    /// when the presale has a whitelist.
+    #[account(seeds = [b"claim_verifying_key"], bump)]
+    pub claim_verifying_key: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        init_if_needed,
+        payer = participant,
+        space = 8 + 32,
+        seeds = [b"whitelist_nullifier", presale.key().as_ref(), &whitelist_nullifier_hash],
+        bump
+    )]
+    pub whitelist_nullifier: Option<Account<'info, WhitelistNullifier>>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizePresale<'info> {
+    #[account(
+        mut,
+        constraint = creator.key() == presale.creator @ PresaleError::Unauthorized
+    )]
+    pub creator: Signer<'info>,
+
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+
+    #[account(mut, seeds = [b"protocol_config"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(secret: [u8; 32])]
+pub struct ClaimTokens<'info> {
+    #[account(mut)]
+    pub claimer: Signer<'info>,
+
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused.claims @ ProtocolError::Paused
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = commitment.presale == presale.key() @ PresaleError::InvalidCommitment
+    )]
+    pub commitment: Account<'info, Commitment>,
+
+    /// CHECK: The wallet receiving tokens, verified via commitment hash in instruction logic
+    pub claim_wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Any wallet chosen by the claimer to receive the commitment's rent once fully claimed
+    #[account(mut)]
+    pub rent_recipient: UncheckedAccount<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", presale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority, validated by seeds
+    #[account(
+        seeds = [b"vault_auth", presale.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = claimer,
+        associated_token::mint = mint,
+        associated_token::authority = claim_wallet,
+    )]
+    pub claim_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(secret: [u8; 32])]
+pub struct RefundCommitment<'info> {
+    #[account(mut)]
+    pub claimer: Signer<'info>,
+
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        mut,
+        close = rent_recipient,
+        constraint = commitment.presale == presale.key() @ PresaleError::InvalidCommitment
+    )]
+    pub commitment: Account<'info, Commitment>,
+
+    /// CHECK: The wallet receiving the refund, verified via commitment hash in instruction logic
+    #[account(mut)]
+    pub refund_wallet: UncheckedAccount<'info>,
+
+    /// CHECK: Any wallet chosen by the claimer to receive the commitment's rent
+    #[account(mut)]
+    pub rent_recipient: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelPresale<'info> {
+    #[account(
+        mut,
+        constraint = creator.key() == presale.creator @ PresaleError::Unauthorized
+    )]
+    pub creator: Signer<'info>,
+
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+
+    #[account(address = presale.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", presale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority, validated by seeds
+    #[account(
+        seeds = [b"vault_auth", presale.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = presale.mint,
+        token::authority = creator,
+    )]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawRemainingTokens<'info> {
+    #[account(
+        mut,
+        constraint = creator.key() == presale.creator @ PresaleError::Unauthorized
+    )]
+    pub creator: Signer<'info>,
+
+    /// Records that the vault is closed, which `close_presale` requires
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+
+    /// Receives fees withheld on the vault before it closes
+    #[account(mut, address = presale.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", presale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority, validated by seeds
+    #[account(
+        seeds = [b"vault_auth", presale.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = presale.mint,
+        token::authority = creator,
+    )]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetWhitelistRoot<'info> {
+    #[account(constraint = creator.key() == presale.creator @ PresaleError::Unauthorized)]
+    pub creator: Signer<'info>,
+
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+}
+
+#[derive(Accounts)]
+#[instruction(nullifier_hash: [u8; 32])]
+pub struct CloseWhitelistNullifier<'info> {
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"whitelist_nullifier", presale.key().as_ref(), &nullifier_hash],
+        bump
+    )]
+    pub whitelist_nullifier: Account<'info, WhitelistNullifier>,
+
+    /// CHECK: The burner that paid for the nullifier, verified against it
+    #[account(mut, address = whitelist_nullifier.payer)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseStaleCommitment<'info> {
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        mut,
+        close = rent_recipient,
+        constraint = commitment.presale == presale.key() @ PresaleError::InvalidCommitment
+    )]
+    pub commitment: Account<'info, Commitment>,
+
+    /// CHECK: Any wallet chosen by the caller to receive the commitment's rent
+    #[account(mut)]
+    pub rent_recipient: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePresale<'info> {
+    #[account(
+        mut,
+        constraint = creator.key() == presale.creator @ PresaleError::Unauthorized
+    )]
+    pub creator: Signer<'info>,
+
+    #[account(mut, close = creator)]
+    pub presale: Account<'info, Presale>,
+
+    /// CHECK: The presale's commitment tree, closed along with it if one was created
+    #[account(mut, seeds = [b"commitment_tree", presale.key().as_ref()], bump)]
+    pub commitment_tree: UncheckedAccount<'info>,
+}
+
+// ─── Commitment Pool Instruction Accounts ───
+
+#[derive(Accounts)]
+pub struct InitializeCommitmentTree<'info> {
+    #[account(
+        mut,
+        constraint = creator.key() == presale.creator @ PresaleError::Unauthorized
+    )]
+    pub creator: Signer<'info>,
+
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + 32 + 4 + 32 + 32 * merkle::TREE_DEPTH + 1 + 32,
+        seeds = [b"commitment_tree", presale.key().as_ref()],
+        bump
+    )]
+    pub commitment_tree: Account<'info, CommitmentTree>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(leaf: [u8; 32], whitelist_nullifier_hash: [u8; 32])]
+pub struct CommitToPool<'info> {
+    #[account(mut)]
+    pub participant: Signer<'info>,
+
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused.commits @ ProtocolError::Paused
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"commitment_tree", presale.key().as_ref()],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Account<'info, CommitmentTree>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: The `ClaimVerifyingKey` PDA. This and `whitelist_nullifier` are only needed
+    /// when the presale has a whitelist.
+    #[account(seeds = [b"claim_verifying_key"], bump)]
+    pub claim_verifying_key: Option<UncheckedAccount<'info>>,
+
+    #[account(
+        init_if_needed,
+        payer = participant,
+        space = 8 + 32,
+        seeds = [b"whitelist_nullifier", presale.key().as_ref(), &whitelist_nullifier_hash],
+        bump
+    )]
+    pub whitelist_nullifier: Option<Account<'info, WhitelistNullifier>>,
+}
+
+#[derive(Accounts)]
+#[instruction(nullifier_hash: [u8; 32])]
+pub struct ClaimFromPool<'info> {
+    #[account(mut)]
+    pub claimer: Signer<'info>,
+
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused.claims @ ProtocolError::Paused
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        seeds = [b"commitment_tree", presale.key().as_ref()],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Account<'info, CommitmentTree>,
+
+    #[account(
+        init_if_needed,
+        payer = claimer,
+        space = 8 + 32 + 8 + 1 + 1 + 16,
+        seeds = [b"nullifier", presale.key().as_ref(), &nullifier_hash],
+        bump
+    )]
+    pub nullifier: Account<'info, Nullifier>,
+
+    /// CHECK: The wallet receiving tokens, verified via the leaf preimage in instruction logic
+    pub claim_wallet: UncheckedAccount<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", presale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority, validated by seeds
+    #[account(
+        seeds = [b"vault_auth", presale.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = claimer,
+        associated_token::mint = mint,
+        associated_token::authority = claim_wallet,
+    )]
+    pub claim_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: The `ClaimVerifyingKey` PDA, read by `claim_tokens_zk` and empty until installed
+    #[account(seeds = [b"claim_verifying_key"], bump)]
+    pub claim_verifying_key: Option<UncheckedAccount<'info>>,
+}
+
+impl<'info> ClaimFromPool<'info> {
+    /// Pays out whatever has vested for the nullifier's leaf, returning `(released, total_owed)`.
+    pub fn release_vested(
+        &mut self,
+        nullifier_bump: u8,
+        now: i64,
+        hook_accounts: &[AccountInfo<'info>],
+    ) -> Result<(u64, u64)> {
+        let presale = &self.presale;
+        require!(!presale.claim_window_closed(now), PresaleError::ClaimWindowClosed);
+
+        // Every pooled leaf is exactly one denomination unit
+        let tokens_owed = presale.tokens_owed(presale.denomination);
+        require!(tokens_owed > 0, PresaleError::InvalidAmount);
+
+        let spent = &self.nullifier;
+        require!(!spent.is_settled, PresaleError::AlreadyClaimed);
+        let vested = presale.vesting.vested_amount(tokens_owed, presale.finalized_at, now);
+        let releasable = vested.checked_sub(spent.claimed_amount).unwrap();
+        require!(releasable > 0, PresaleError::NothingToClaim);
+
+        let presale_key = self.presale.key();
+        let seeds = &[
+            b"vault_auth".as_ref(),
+            presale_key.as_ref(),
+            &[presale.vault_auth_bump],
+        ];
+        let signer_seeds = &[&seeds[..]];
+
+        transfer_tokens(
+            &self.token_program,
+            &self.token_vault,
+            &self.mint,
+            &self.claim_token_account,
+            self.vault_authority.to_account_info(),
+            hook_accounts,
+            releasable,
+            signer_seeds,
+        )?;
+
+        // The nullifier account persists forever; it is what stops the leaf being spent again
+        let spent = &mut self.nullifier;
+        spent.presale = presale_key;
+        spent.claimed_amount = spent.claimed_amount.checked_add(releasable).unwrap();
+        spent.bump = nullifier_bump;
+
+        if spent.claimed_amount == tokens_owed {
+            spent.is_settled = true;
+
+            let presale = &mut self.presale;
+            presale.settled_count = presale.settled_count.checked_add(1).unwrap();
+            presale.open_pool_leaves = presale.open_pool_leaves.checked_sub(1).unwrap();
+        }
+
+        Ok((releasable, tokens_owed))
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(nullifier_hash: [u8; 32])]
+pub struct RefundFromPool<'info> {
+    #[account(mut)]
+    pub claimer: Signer<'info>,
+
+    #[account(mut)]
+    pub presale: Account<'info, Presale>,
+
+    #[account(
+        seeds = [b"commitment_tree", presale.key().as_ref()],
+        bump = commitment_tree.bump
+    )]
+    pub commitment_tree: Account<'info, CommitmentTree>,
+
+    #[account(
+        init,
+        payer = claimer,
+        space = 8 + 32 + 8 + 1 + 1 + 16,
+        seeds = [b"nullifier", presale.key().as_ref(), &nullifier_hash],
+        bump
+    )]
+    pub nullifier: Account<'info, Nullifier>,
+
+    /// CHECK: The wallet receiving the refund, verified via the leaf preimage in instruction logic
+    #[account(mut)]
+    pub refund_wallet: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// ─── Dark Pool Instruction Accounts ───
+
+#[derive(Accounts)]
+pub struct InitializeDarkPool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mint::token_program = base_token_program)]
+    pub base_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = quote_token_program,
+        constraint = quote_mint.key() != base_mint.key() @ DarkPoolError::InvalidMarket
+    )]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 4 + 1 + 32,
+        seeds = [b"dark_pool", base_mint.key().as_ref(), quote_mint.key().as_ref()],
+        bump
+    )]
+    pub dark_pool: Account<'info, DarkPool>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = base_mint,
+        token::authority = dp_vault_authority,
+        token::token_program = base_token_program,
+        seeds = [b"dp_base_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_base_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = quote_mint,
+        token::authority = dp_vault_authority,
+        token::token_program = quote_token_program,
+        seeds = [b"dp_quote_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_quote_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Protocol fees on fills, in the quote mint
+    #[account(
+        init,
+        payer = authority,
+        token::mint = quote_mint,
+        token::authority = dp_vault_authority,
+        token::token_program = quote_token_program,
+        seeds = [b"dp_fee_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority for dark pool token vaults
+    #[account(
+        seeds = [b"dp_vault_auth", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_vault_authority: UncheckedAccount<'info>,
+
+    pub base_token_program: Interface<'info, TokenInterface>,
+    pub quote_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_hash: [u8; 32])]
+pub struct PlaceDarkOrder<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(mut)]
+    pub dark_pool: Account<'info, DarkPool>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused.placement @ ProtocolError::Paused
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = 8 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 8 + 1 + 32,
+        seeds = [b"dark_order", dark_pool.key().as_ref(), &order_hash],
+        bump
+    )]
+    pub dark_order: Account<'info, DarkOrder>,
+
+    #[account(address = dark_pool.base_mint)]
+    pub base_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = dark_pool.quote_mint)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"dp_base_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_base_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"dp_quote_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_quote_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Maker's base token account (may be empty if only escrowing quote)
+    #[account(mut)]
+    pub maker_base_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// Maker's quote token account (may be empty if only escrowing base)
+    #[account(mut)]
+    pub maker_quote_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub base_token_program: Interface<'info, TokenInterface>,
+    pub quote_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FillDarkOrder<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut)]
+    pub dark_pool: Account<'info, DarkPool>,
+
+    /// Closed to the maker once fully filled
+    #[account(
+        mut,
+        constraint = dark_order.pool == dark_pool.key() @ DarkPoolError::InvalidOrder
+    )]
+    pub dark_order: Account<'info, DarkOrder>,
+
+    /// CHECK: Maker wallet, verified against order.maker
+    #[account(
+        mut,
+        constraint = maker.key() == dark_order.maker @ DarkPoolError::InvalidMaker
+    )]
+    pub maker: UncheckedAccount<'info>,
+
+    /// Pool base mint, whose decimals scale limit prices
+    #[account(address = dark_pool.base_mint)]
+    pub base_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = dark_pool.quote_mint)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused.fills @ ProtocolError::Paused
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"dp_base_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_base_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"dp_quote_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_quote_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"dp_fee_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority for dark pool token vaults
+    #[account(
+        seeds = [b"dp_vault_auth", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub taker_base_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub taker_quote_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// The maker does not sign fills, so their accounts are pinned to them
+    #[account(
+        mut,
+        token::mint = dark_pool.base_mint,
+        token::authority = maker,
+    )]
+    pub maker_base_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = dark_pool.quote_mint,
+        token::authority = maker,
+    )]
+    pub maker_quote_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub base_token_program: Interface<'info, TokenInterface>,
+    pub quote_token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FillDarkOrder<'info> {
+    /// Swaps `fill_base` for `fill_quote` against the order's escrow. Once `order_base` has
+    /// been filled or the escrow is spent, the leftover escrow goes back and the order closes.
+    pub fn settle_fill(
+        &mut self,
+        side: u8,
+        fill_base: u64,
+        fill_quote: u64,
+        order_base: u64,
+        vault_auth_bump: u8,
+        hook_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let order = &self.dark_order;
+        require!(!order.is_filled, DarkPoolError::OrderAlreadyFilled);
+        require!(!order.is_cancelled, DarkPoolError::OrderCancelled);
+        require!(
+            !order.is_expired(Clock::get()?.unix_timestamp),
+            DarkPoolError::OrderExpired
+        );
+        require!(fill_base > 0, DarkPoolError::InvalidFillAmount);
+        require!(
+            order.filled_base.checked_add(fill_base).unwrap() <= order_base,
+            DarkPoolError::FillExceedsOrder
+        );
+
+        // Protocol fees are charged in quote on top of the taker's side and out of the maker's
+        let taker_fee = ProtocolConfig::fee(fill_quote, self.protocol_config.taker_fee_bps);
+        let maker_fee = ProtocolConfig::fee(fill_quote, self.protocol_config.maker_fee_bps);
+        let total_fee = taker_fee.checked_add(maker_fee).unwrap();
+
+        let pool_key = self.dark_pool.key();
+        if side == 0 {
+            // Maker sells base: vault sends base to taker, taker sends quote to maker
+            require!(order.remaining_base >= fill_base, DarkPoolError::InsufficientEscrow);
+
+            dp_vault_transfer(
+                &self.base_token_program,
+                &self.base_mint,
+                &self.dp_base_vault,
+                &self.taker_base_account,
+                &self.dp_vault_authority,
+                hook_accounts,
+                &pool_key,
+                vault_auth_bump,
+                fill_base,
+            )?;
+            transfer_tokens(
+                &self.quote_token_program,
+                &self.taker_quote_account,
+                &self.quote_mint,
+                &self.maker_quote_account,
+                self.taker.to_account_info(),
+                hook_accounts,
+                fill_quote.checked_sub(maker_fee).unwrap(),
+                &[],
+            )?;
+            if total_fee > 0 {
+                transfer_tokens(
+                    &self.quote_token_program,
+                    &self.taker_quote_account,
+                    &self.quote_mint,
+                    &self.dp_fee_vault,
+                    self.taker.to_account_info(),
+                    hook_accounts,
+                    total_fee,
+                    &[],
+                )?;
+            }
+        } else if side == 1 {
+            // Maker buys base: vault sends escrowed quote to taker, taker sends base to maker
+            require!(
+                order.remaining_quote >= fill_quote.checked_add(maker_fee).unwrap(),
+                DarkPoolError::InsufficientEscrow
+            );
+
+            dp_vault_transfer(
+                &self.quote_token_program,
+                &self.quote_mint,
+                &self.dp_quote_vault,
+                &self.taker_quote_account,
+                &self.dp_vault_authority,
+                hook_accounts,
+                &pool_key,
+                vault_auth_bump,
+                fill_quote.checked_sub(taker_fee).unwrap(),
+            )?;
+            if total_fee > 0 {
+                dp_vault_transfer(
+                    &self.quote_token_program,
+                    &self.quote_mint,
+                    &self.dp_quote_vault,
+                    &self.dp_fee_vault,
+                    &self.dp_vault_authority,
+                    hook_accounts,
+                    &pool_key,
+                    vault_auth_bump,
+                    total_fee,
+                )?;
+            }
+            transfer_tokens(
+                &self.base_token_program,
+                &self.taker_base_account,
+                &self.base_mint,
+                &self.maker_base_account,
+                self.taker.to_account_info(),
+                hook_accounts,
+                fill_base,
+                &[],
+            )?;
+        } else {
+            return Err(DarkPoolError::InvalidSide.into());
+        }
+
+        let order = &mut self.dark_order;
+        order.filled_base = order.filled_base.checked_add(fill_base).unwrap();
+        order.filled_quote = order.filled_quote.checked_add(fill_quote).unwrap();
+        if side == 0 {
+            order.remaining_base = order.remaining_base.checked_sub(fill_base).unwrap();
+        } else {
+            order.remaining_quote = order
+                .remaining_quote
+                .checked_sub(fill_quote.checked_add(maker_fee).unwrap())
+                .unwrap();
+        }
+        order.is_filled = order.filled_base == order_base
+            || (side == 0 && order.remaining_base == 0)
+            || (side == 1 && order.remaining_quote == 0);
+
+        emit!(DarkOrderFilled {
+            dark_pool: pool_key,
+            dark_order: order.key(),
+            order_id: order.order_id,
+            taker: self.taker.key(),
+            side,
+            fill_base,
+            fill_quote,
+            taker_fee,
+            maker_fee,
+            filled_base: order.filled_base,
+            is_filled: order.is_filled,
+        });
+
+        let pool = &mut self.dark_pool;
+        pool.total_volume_quote = pool.total_volume_quote.checked_add(fill_quote).unwrap();
+        if !self.dark_order.is_filled {
+            return Ok(());
+        }
+        pool.open_order_count = pool.open_order_count.checked_sub(1).unwrap();
+
+        // Exhausted: hand any escrow the fills did not consume back to the maker and close
+        let (leftover_base, leftover_quote) =
+            (self.dark_order.remaining_base, self.dark_order.remaining_quote);
+        if leftover_base > 0 {
+            dp_vault_transfer(
+                &self.base_token_program,
+                &self.base_mint,
+                &self.dp_base_vault,
+                &self.maker_base_account,
+                &self.dp_vault_authority,
+                hook_accounts,
+                &pool_key,
+                vault_auth_bump,
+                leftover_base,
+            )?;
+        }
+        if leftover_quote > 0 {
+            dp_vault_transfer(
+                &self.quote_token_program,
+                &self.quote_mint,
+                &self.dp_quote_vault,
+                &self.maker_quote_account,
+                &self.dp_vault_authority,
+                hook_accounts,
+                &pool_key,
+                vault_auth_bump,
+                leftover_quote,
+            )?;
+        }
+        let maker_info = self.maker.to_account_info();
+        self.dark_order.close(maker_info)
+    }
+}
+
+#[derive(Accounts)]
+pub struct CancelDarkOrder<'info> {
+    #[account(
+        mut,
+        constraint = maker.key() == dark_order.maker @ DarkPoolError::Unauthorized
+    )]
+    pub maker: Signer<'info>,
+
+    #[account(mut)]
+    pub dark_pool: Account<'info, DarkPool>,
+
+    #[account(
+        mut,
+        close = maker,
+        constraint = dark_order.pool == dark_pool.key() @ DarkPoolError::InvalidOrder
+    )]
+    pub dark_order: Account<'info, DarkOrder>,
+
+    #[account(address = dark_pool.base_mint)]
+    pub base_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = dark_pool.quote_mint)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"dp_base_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_base_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"dp_quote_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_quote_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority for dark pool token vaults
+    #[account(
+        seeds = [b"dp_vault_auth", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_vault_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub maker_base_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub maker_quote_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub base_token_program: Interface<'info, TokenInterface>,
+    pub quote_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireDarkOrder<'info> {
+    pub cranker: Signer<'info>,
+
+    #[account(mut)]
+    pub dark_pool: Account<'info, DarkPool>,
+
+    #[account(
+        mut,
+        close = maker,
+        constraint = dark_order.pool == dark_pool.key() @ DarkPoolError::InvalidOrder
+    )]
+    pub dark_order: Account<'info, DarkOrder>,
+
+    /// CHECK: Maker wallet, verified against order.maker
+    #[account(
+        mut,
+        constraint = maker.key() == dark_order.maker @ DarkPoolError::InvalidMaker
+    )]
+    pub maker: UncheckedAccount<'info>,
+
+    #[account(address = dark_pool.base_mint)]
+    pub base_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = dark_pool.quote_mint)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"dp_base_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_base_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"dp_quote_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_quote_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: PDA authority for dark pool token vaults
+    #[account(
+        seeds = [b"dp_vault_auth", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_vault_authority: UncheckedAccount<'info>,
+
+    /// The maker does not sign, so the refund accounts must be theirs
+    #[account(
+        mut,
+        token::mint = dark_pool.base_mint,
+        token::authority = maker,
+    )]
+    pub maker_base_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = dark_pool.quote_mint,
+        token::authority = maker,
+    )]
+    pub maker_quote_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub base_token_program: Interface<'info, TokenInterface>,
+    pub quote_token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct OpenBatch<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == dark_pool.authority @ DarkPoolError::Unauthorized
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub dark_pool: Account<'info, DarkPool>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 8 + 8 + 8 + 4 + 4 + 4 + 1 + 8 + 8 + 1 + 32,
+        seeds = [b"batch", dark_pool.key().as_ref(), &dark_pool.batch_count.to_le_bytes()],
+        bump
+    )]
+    pub batch: Account<'info, BatchAuction>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(order_hash: [u8; 32])]
+pub struct CommitBatchOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut)]
+    pub dark_pool: Account<'info, DarkPool>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused.placement @ ProtocolError::Paused
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = batch.pool == dark_pool.key() @ DarkPoolError::InvalidOrder
+    )]
+    pub batch: Account<'info, BatchAuction>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + 32 + 32 + 32 + 8 + 8 + 1 + 1 + 8 + 8 + 4 + 8 + 8 + 8 + 1 + 32,
+        seeds = [b"batch_order", batch.key().as_ref(), &order_hash],
+        bump
+    )]
+    pub batch_order: Account<'info, BatchOrder>,
+
+    #[account(address = dark_pool.base_mint)]
+    pub base_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(address = dark_pool.quote_mint)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"dp_base_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_base_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"dp_quote_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_quote_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner_base_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner_quote_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub base_token_program: Interface<'info, TokenInterface>,
+    pub quote_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevealBatchOrder<'info> {
+    #[account(constraint = owner.key() == batch_order.owner @ DarkPoolError::Unauthorized)]
+    pub owner: Signer<'info>,
+
+    pub dark_pool: Account<'info, DarkPool>,
+
+    #[account(
+        mut,
+        constraint = batch.pool == dark_pool.key() @ DarkPoolError::InvalidOrder
+    )]
+    pub batch: Account<'info, BatchAuction>,
+
+    #[account(
+        mut,
+        constraint = batch_order.batch == batch.key() @ DarkPoolError::InvalidOrder
+    )]
+    pub batch_order: Account<'info, BatchOrder>,
+
+    /// Pool base mint, whose decimals scale limit prices
+    #[account(address = dark_pool.base_mint)]
+    pub base_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(seeds = [b"protocol_config"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SettleBatch<'info> {
+    pub cranker: Signer<'info>,
+
+    #[account(mut)]
+    pub dark_pool: Account<'info, DarkPool>,
+
+    #[account(
+        seeds = [b"protocol_config"],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.paused.fills @ ProtocolError::Paused
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
 
     #[account(
         mut,
-        token::mint = mint,
-        token::authority = creator,
+        constraint = batch.pool == dark_pool.key() @ DarkPoolError::InvalidOrder
     )]
-    pub creator_token_account: Account<'info, TokenAccount>,
+    pub batch: Account<'info, BatchAuction>,
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+    #[account(address = dark_pool.base_mint)]
+    pub base_mint: InterfaceAccount<'info, Mint>,
 }
 
 #[derive(Accounts)]
-#[instruction(commitment_hash: [u8; 32])]
-pub struct CommitToPresale<'info> {
-    #[account(mut)]
-    pub participant: Signer<'info>,
+pub struct ClaimBatchOrder<'info> {
+    pub cranker: Signer<'info>,
 
     #[account(mut)]
-    pub presale: Account<'info, Presale>,
+    pub dark_pool: Account<'info, DarkPool>,
 
     #[account(
-        init,
-        payer = participant,
-        space = 8 + 32 + 32 + 8 + 1 + 1 + 32,
-        seeds = [b"commitment", presale.key().as_ref(), &commitment_hash],
-        bump
+        mut,
+        constraint = batch.pool == dark_pool.key() @ DarkPoolError::InvalidOrder
     )]
-    pub commitment: Account<'info, Commitment>,
-
-    pub system_program: Program<'info, System>,
-}
+    pub batch: Account<'info, BatchAuction>,
 
-#[derive(Accounts)]
-pub struct FinalizePresale<'info> {
     #[account(
         mut,
-        constraint = creator.key() == presale.creator @ PresaleError::Unauthorized
+        close = owner,
+        constraint = batch_order.batch == batch.key() @ DarkPoolError::InvalidOrder
     )]
-    pub creator: Signer<'info>,
-
-    #[account(mut)]
-    pub presale: Account<'info, Presale>,
+    pub batch_order: Account<'info, BatchOrder>,
 
-    pub system_program: Program<'info, System>,
-}
+    /// CHECK: Order owner, verified against batch_order.owner
+    #[account(
+        mut,
+        constraint = owner.key() == batch_order.owner @ DarkPoolError::InvalidMaker
+    )]
+    pub owner: UncheckedAccount<'info>,
 
-#[derive(Accounts)]
-#[instruction(secret: [u8; 32])]
-pub struct ClaimTokens<'info> {
-    #[account(mut)]
-    pub claimer: Signer<'info>,
+    #[account(address = dark_pool.base_mint)]
+    pub base_mint: InterfaceAccount<'info, Mint>,
 
-    pub presale: Account<'info, Presale>,
+    #[account(address = dark_pool.quote_mint)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
-        constraint = commitment.presale == presale.key() @ PresaleError::InvalidCommitment
+        seeds = [b"dp_base_vault", dark_pool.key().as_ref()],
+        bump
     )]
-    pub commitment: Account<'info, Commitment>,
-
-    /// CHECK: The wallet receiving tokens, verified via commitment hash in instruction logic
-    pub claim_wallet: UncheckedAccount<'info>,
+    pub dp_base_vault: InterfaceAccount<'info, TokenAccount>,
 
-    pub mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"dp_quote_vault", dark_pool.key().as_ref()],
+        bump
+    )]
+    pub dp_quote_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [b"token_vault", presale.key().as_ref()],
+        seeds = [b"dp_fee_vault", dark_pool.key().as_ref()],
         bump
     )]
-    pub token_vault: Account<'info, TokenAccount>,
+    pub dp_fee_vault: InterfaceAccount<'info, TokenAccount>,
 
-    /// CHECK: PDA authority, validated by seeds
+    /// CHECK: PDA authority for dark pool token vaults
     #[account(
-        seeds = [b"vault_auth", presale.key().as_ref()],
+        seeds = [b"dp_vault_auth", dark_pool.key().as_ref()],
         bump
     )]
-    pub vault_authority: UncheckedAccount<'info>,
+    pub dp_vault_authority: UncheckedAccount<'info>,
 
+    /// The owner does not sign, so the payout accounts must be theirs
     #[account(
-        init_if_needed,
-        payer = claimer,
-        associated_token::mint = mint,
-        associated_token::authority = claim_wallet,
+        mut,
+        token::mint = dark_pool.base_mint,
+        token::authority = owner,
     )]
-    pub claim_token_account: Account<'info, TokenAccount>,
+    pub owner_base_account: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
-}
+    #[account(
+        mut,
+        token::mint = dark_pool.quote_mint,
+        token::authority = owner,
+    )]
+    pub owner_quote_account: InterfaceAccount<'info, TokenAccount>,
 
-// ─── Dark Pool Instruction Accounts ───
+    pub base_token_program: Interface<'info, TokenInterface>,
+    pub quote_token_program: Interface<'info, TokenInterface>,
+}
 
 #[derive(Accounts)]
-pub struct InitializeDarkPool<'info> {
-    #[account(mut)]
+pub struct CloseDarkPool<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == dark_pool.authority @ DarkPoolError::Unauthorized
+    )]
     pub authority: Signer<'info>,
 
-    pub mint: Account<'info, Mint>,
+    #[account(mut, close = authority)]
+    pub dark_pool: Account<'info, DarkPool>,
+
+    /// The mints receive fees withheld on the vaults before they close
+    #[account(mut, address = dark_pool.base_mint)]
+    pub base_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut, address = dark_pool.quote_mint)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
-        init,
-        payer = authority,
-        space = 8 + 32 + 32 + 8 + 8 + 1 + 32,
-        seeds = [b"dark_pool", mint.key().as_ref()],
+        mut,
+        seeds = [b"dp_base_vault", dark_pool.key().as_ref()],
         bump
     )]
-    pub dark_pool: Account<'info, DarkPool>,
+    pub dp_base_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
-        init,
-        payer = authority,
-        token::mint = mint,
-        token::authority = dp_vault_authority,
-        seeds = [b"dp_token_vault", dark_pool.key().as_ref()],
+        mut,
+        seeds = [b"dp_quote_vault", dark_pool.key().as_ref()],
         bump
     )]
-    pub dp_token_vault: Account<'info, TokenAccount>,
+    pub dp_quote_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Must have been emptied with `collect_dark_pool_fees`
+    #[account(
+        mut,
+        seeds = [b"dp_fee_vault", dark_pool.key().as_ref()],
+        bump,
+        constraint = dp_fee_vault.amount == 0 @ DarkPoolError::FeesUncollected
+    )]
+    pub dp_fee_vault: InterfaceAccount<'info, TokenAccount>,
 
-    /// CHECK: PDA authority for dark pool token vault
+    /// CHECK: PDA authority for dark pool token vaults
     #[account(
         seeds = [b"dp_vault_auth", dark_pool.key().as_ref()],
         bump
     )]
     pub dp_vault_authority: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+    /// The authority signs, so leftovers go wherever it chooses
+    #[account(mut, token::mint = dark_pool.base_mint)]
+    pub authority_base_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, token::mint = dark_pool.quote_mint)]
+    pub authority_quote_account: InterfaceAccount<'info, TokenAccount>,
+
+    pub base_token_program: Interface<'info, TokenInterface>,
+    pub quote_token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-#[instruction(order_hash: [u8; 32])]
-pub struct PlaceDarkOrder<'info> {
+pub struct CloseBatch<'info> {
     #[account(mut)]
-    pub maker: Signer<'info>,
+    pub dark_pool: Account<'info, DarkPool>,
+
+    #[account(
+        mut,
+        close = authority,
+        constraint = batch.pool == dark_pool.key() @ DarkPoolError::InvalidOrder
+    )]
+    pub batch: Account<'info, BatchAuction>,
+
+    /// CHECK: Pool authority, who paid for the batch and gets its rent back
+    #[account(mut, address = dark_pool.authority @ DarkPoolError::Unauthorized)]
+    pub authority: UncheckedAccount<'info>,
+}
+
+// ─── Protocol Instruction Accounts ───
 
+#[derive(Accounts)]
+pub struct InitializeProtocolConfig<'info> {
     #[account(mut)]
-    pub dark_pool: Account<'info, DarkPool>,
+    pub admin: Signer<'info>,
 
     #[account(
         init,
-        payer = maker,
-        space = 8 + 32 + 32 + 32 + 8 + 8 + 1 + 1 + 8 + 8 + 1 + 32,
-        seeds = [b"dark_order", dark_pool.key().as_ref(), &order_hash],
+        payer = admin,
+        space = 8 + 32 + 32 + 32 + 2 + 2 + 2 + (1 + 1 + 1 + 1) + 1 + 64,
+        seeds = [b"protocol_config"],
         bump
     )]
-    pub dark_order: Account<'info, DarkOrder>,
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::AnonPresale>,
 
     #[account(
-        mut,
-        seeds = [b"dp_token_vault", dark_pool.key().as_ref()],
-        bump
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ProtocolError::Unauthorized
     )]
-    pub dp_token_vault: Account<'info, TokenAccount>,
+    pub program_data: Account<'info, ProgramData>,
 
-    /// Maker's token account (may be empty if only escrowing SOL)
-    #[account(mut)]
-    pub maker_token_account: Account<'info, TokenAccount>,
-
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct FillDarkOrder<'info> {
-    #[account(mut)]
-    pub taker: Signer<'info>,
+pub struct UpdateProtocolConfig<'info> {
+    #[account(constraint = admin.key() == protocol_config.admin @ ProtocolError::Unauthorized)]
+    pub admin: Signer<'info>,
 
-    #[account(mut)]
-    pub dark_pool: Account<'info, DarkPool>,
+    #[account(mut, seeds = [b"protocol_config"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
 
+#[derive(Accounts)]
+pub struct SetClaimVerifyingKey<'info> {
     #[account(
         mut,
-        constraint = dark_order.pool == dark_pool.key() @ DarkPoolError::InvalidOrder
+        constraint = admin.key() == protocol_config.admin @ ProtocolError::Unauthorized
     )]
-    pub dark_order: Account<'info, DarkOrder>,
+    pub admin: Signer<'info>,
 
-    /// CHECK: Maker wallet, verified against order.maker
-    #[account(
-        mut,
-        constraint = maker.key() == dark_order.maker @ DarkPoolError::InvalidMaker
-    )]
-    pub maker: UncheckedAccount<'info>,
+    #[account(seeds = [b"protocol_config"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
 
     #[account(
-        mut,
-        seeds = [b"dp_token_vault", dark_pool.key().as_ref()],
+        init_if_needed,
+        payer = admin,
+        space = 8 + groth16::VerifyingKeyData::LEN + 1,
+        seeds = [b"claim_verifying_key"],
         bump
     )]
-    pub dp_token_vault: Account<'info, TokenAccount>,
+    pub claim_verifying_key: Box<Account<'info, ClaimVerifyingKey>>,
+
+    pub system_program: Program<'info, System>,
+}
 
-    /// CHECK: PDA authority for dark pool token vault
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
     #[account(
-        seeds = [b"dp_vault_auth", dark_pool.key().as_ref()],
-        bump
+        constraint = pending_admin.key() == protocol_config.pending_admin
+            @ ProtocolError::Unauthorized
     )]
-    pub dp_vault_authority: UncheckedAccount<'info>,
+    pub pending_admin: Signer<'info>,
 
-    /// Taker's token account
-    #[account(mut)]
-    pub taker_token_account: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"protocol_config"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
 
-    /// Maker's token account (for receiving tokens in buy-side fills)
-    #[account(mut)]
-    pub maker_token_account: Account<'info, TokenAccount>,
+#[derive(Accounts)]
+pub struct CollectProtocolFees<'info> {
+    #[account(mut, seeds = [b"protocol_config"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+    /// CHECK: Fee recipient wallet, verified against the config
+    #[account(mut, address = protocol_config.fee_recipient)]
+    pub fee_recipient: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct CancelDarkOrder<'info> {
-    #[account(
-        mut,
-        constraint = maker.key() == dark_order.maker @ DarkPoolError::Unauthorized
-    )]
-    pub maker: Signer<'info>,
+pub struct CollectDarkPoolFees<'info> {
+    #[account(seeds = [b"protocol_config"], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
 
-    #[account(mut)]
     pub dark_pool: Account<'info, DarkPool>,
 
-    #[account(
-        mut,
-        constraint = dark_order.pool == dark_pool.key() @ DarkPoolError::InvalidOrder
-    )]
-    pub dark_order: Account<'info, DarkOrder>,
+    #[account(address = dark_pool.quote_mint)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
-        seeds = [b"dp_token_vault", dark_pool.key().as_ref()],
+        seeds = [b"dp_fee_vault", dark_pool.key().as_ref()],
         bump
     )]
-    pub dp_token_vault: Account<'info, TokenAccount>,
+    pub dp_fee_vault: InterfaceAccount<'info, TokenAccount>,
 
-    /// CHECK: PDA authority for dark pool token vault
+    /// CHECK: PDA authority for dark pool token vaults
     #[account(
         seeds = [b"dp_vault_auth", dark_pool.key().as_ref()],
         bump
     )]
     pub dp_vault_authority: UncheckedAccount<'info>,
 
-    #[account(mut)]
-    pub maker_token_account: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = dark_pool.quote_mint,
+        token::authority = protocol_config.fee_recipient,
+    )]
+    pub fee_recipient_quote_account: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+    pub quote_token_program: Interface<'info, TokenInterface>,
+}
+
+// ─── Events ───
+
+#[event]
+pub struct PresaleInitialized {
+    pub presale: Pubkey,
+    pub creator: Pubkey,
+    pub mint: Pubkey,
+    pub hard_cap: u64,
+    pub soft_cap: u64,
+    pub tokens_for_sale: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct CommitmentAdded {
+    pub presale: Pubkey,
+    pub commitment: Pubkey,
+    /// Omitted for denominated presales, where the amount would narrow the anonymity set.
+    pub sol_amount: Option<u64>,
+    pub commitment_count: u32,
+}
+
+#[event]
+pub struct CommitmentTreeInitialized {
+    pub presale: Pubkey,
+    pub commitment_tree: Pubkey,
+    pub root: [u8; 32],
+}
+
+#[event]
+pub struct PoolCommitmentAdded {
+    pub presale: Pubkey,
+    pub leaf: [u8; 32],
+    pub leaf_index: u32,
+    pub root: [u8; 32],
+    pub commitment_count: u32,
+}
+
+#[event]
+pub struct PresaleFinalized {
+    pub presale: Pubkey,
+    pub total_sol_committed: u64,
+    pub transferred: u64,
+    pub protocol_fee: u64,
+}
+
+#[event]
+pub struct TokensClaimed {
+    pub presale: Pubkey,
+    pub claim_wallet: Pubkey,
+    pub amount: u64,
+    pub total_owed: u64,
+    /// Set for claims from the commitment pool.
+    pub nullifier_hash: Option<[u8; 32]>,
+}
+
+#[event]
+pub struct CommitmentRefunded {
+    pub presale: Pubkey,
+    pub refund_wallet: Pubkey,
+    pub amount: u64,
+    /// Set for refunds from the commitment pool.
+    pub nullifier_hash: Option<[u8; 32]>,
+}
+
+#[event]
+pub struct PresaleCancelled {
+    pub presale: Pubkey,
+    pub tokens_returned: u64,
+    pub commitment_count: u32,
+}
+
+#[event]
+pub struct RemainingTokensWithdrawn {
+    pub presale: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct WhitelistRootSet {
+    pub presale: Pubkey,
+    pub whitelist_root: [u8; 32],
+}
+
+#[event]
+pub struct WhitelistNullifierClosed {
+    pub presale: Pubkey,
+    pub nullifier_hash: [u8; 32],
+}
+
+#[event]
+pub struct StaleCommitmentClosed {
+    pub presale: Pubkey,
+    pub commitment: Pubkey,
+    pub claimed_amount: u64,
+}
+
+#[event]
+pub struct PresaleClosed {
+    pub presale: Pubkey,
+    pub commitment_count: u32,
+}
+
+#[event]
+pub struct DarkPoolInitialized {
+    pub dark_pool: Pubkey,
+    pub base_mint: Pubkey,
+    pub quote_mint: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct DarkOrderPlaced {
+    pub dark_pool: Pubkey,
+    pub dark_order: Pubkey,
+    pub order_id: u64,
+    pub maker: Pubkey,
+    pub escrow_base: u64,
+    pub escrow_quote: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct DarkOrderFilled {
+    pub dark_pool: Pubkey,
+    pub dark_order: Pubkey,
+    pub order_id: u64,
+    pub taker: Pubkey,
+    pub side: u8,
+    pub fill_base: u64,
+    pub fill_quote: u64,
+    pub taker_fee: u64,
+    pub maker_fee: u64,
+    pub filled_base: u64,
+    /// True when this fill exhausted the order and closed it.
+    pub is_filled: bool,
+}
+
+#[event]
+pub struct DarkOrderCancelled {
+    pub dark_pool: Pubkey,
+    pub dark_order: Pubkey,
+    pub order_id: u64,
+    pub filled_base: u64,
+    pub returned_base: u64,
+    pub returned_quote: u64,
+}
+
+#[event]
+pub struct DarkOrderExpired {
+    pub dark_pool: Pubkey,
+    pub dark_order: Pubkey,
+    pub order_id: u64,
+    pub filled_base: u64,
+    pub returned_base: u64,
+    pub returned_quote: u64,
+}
+
+#[event]
+pub struct BatchOpened {
+    pub dark_pool: Pubkey,
+    pub batch: Pubkey,
+    pub batch_id: u64,
+    pub commit_end: i64,
+    pub reveal_end: i64,
+}
+
+#[event]
+pub struct BatchOrderCommitted {
+    pub batch: Pubkey,
+    pub batch_order: Pubkey,
+    pub owner: Pubkey,
+    pub escrow_base: u64,
+    pub escrow_quote: u64,
+}
+
+#[event]
+pub struct BatchOrderRevealed {
+    pub batch: Pubkey,
+    pub batch_order: Pubkey,
+    pub reveal_index: u32,
+    pub side: u8,
+    pub price: u64,
+    pub size: u64,
+}
+
+#[event]
+pub struct BatchSettled {
+    pub dark_pool: Pubkey,
+    pub batch: Pubkey,
+    pub batch_id: u64,
+    pub clearing_price: u64,
+    pub clearing_volume: u64,
+    pub volume_quote: u64,
+    pub total_fee: u64,
+    pub revealed_count: u32,
+}
+
+#[event]
+pub struct BatchOrderClaimed {
+    pub batch: Pubkey,
+    pub batch_order: Pubkey,
+    pub owner: Pubkey,
+    pub fill_base: u64,
+    pub fill_quote: u64,
+    pub fee_quote: u64,
+    pub base_out: u64,
+    pub quote_out: u64,
+}
+
+#[event]
+pub struct BatchClosed {
+    pub dark_pool: Pubkey,
+    pub batch: Pubkey,
+    pub batch_id: u64,
+}
+
+#[event]
+pub struct DarkPoolClosed {
+    pub dark_pool: Pubkey,
+    pub order_count: u64,
+}
+
+#[event]
+pub struct ProtocolConfigUpdated {
+    pub admin: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub presale_fee_bps: u16,
+    pub taker_fee_bps: u16,
+    pub maker_fee_bps: u16,
+}
+
+#[event]
+pub struct PauseFlagsUpdated {
+    pub admin: Pubkey,
+    pub commits: bool,
+    pub claims: bool,
+    pub placement: bool,
+    pub fills: bool,
+}
+
+#[event]
+pub struct AdminProposed {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminTransferred {
+    pub previous_admin: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct ClaimVerifyingKeySet {
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct ProtocolFeesCollected {
+    /// The config account for presale fees, or a pool's fee vault.
+    pub source: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub amount: u64,
 }
 
 // ─── Errors ───
@@ -764,6 +4027,62 @@ pub enum PresaleError {
     Unauthorized,
     #[msg("Invalid commitment")]
     InvalidCommitment,
+    #[msg("Soft cap must not exceed hard cap")]
+    InvalidSoftCap,
+    #[msg("Soft cap not reached")]
+    SoftCapNotReached,
+    #[msg("Soft cap reached - refunds are not available")]
+    SoftCapReached,
+    #[msg("Presale is cancelled")]
+    Cancelled,
+    #[msg("Commitments are still unclaimed and the claim deadline has not passed")]
+    ClaimsOutstanding,
+    #[msg("Token vault must be withdrawn and closed first")]
+    VaultNotClosed,
+    #[msg("Invalid vesting schedule")]
+    InvalidVestingSchedule,
+    #[msg("Nothing has vested since the last claim")]
+    NothingToClaim,
+    #[msg("Minimum commitment must not exceed maximum commitment")]
+    InvalidContributionLimits,
+    #[msg("Commitment is below the presale minimum")]
+    BelowMinCommitment,
+    #[msg("Commitment is above the presale maximum")]
+    AboveMaxCommitment,
+    #[msg("Presale has reached its maximum number of commitments")]
+    MaxCommitmentsReached,
+    #[msg("Amount does not match the presale denomination")]
+    InvalidDenomination,
+    #[msg("Commitment pool requires a denominated presale")]
+    PoolRequiresDenomination,
+    #[msg("Commitment tree is full")]
+    TreeFull,
+    #[msg("Invalid Merkle proof - leaf is not in the commitment tree")]
+    InvalidMerkleProof,
+    #[msg("Nullifier does not match nullifier hash")]
+    InvalidNullifier,
+    #[msg("Value is not a valid field element")]
+    InvalidFieldElement,
+    #[msg("Invalid zero-knowledge proof")]
+    InvalidZkProof,
+    #[msg("Participant is not on the presale whitelist")]
+    NotWhitelisted,
+    #[msg("Whitelist can only be changed before the presale starts")]
+    WhitelistLocked,
+    #[msg("Claim window ends before the vesting schedule does")]
+    ClaimWindowTooShort,
+    #[msg("ZK proofs are disabled until a trusted setup verifying key is installed")]
+    ZkClaimsDisabled,
+    #[msg("Whitelist entry has already been used")]
+    WhitelistNullifierUsed,
+    #[msg("Every whitelist nullifier must be closed before the presale is closed")]
+    WhitelistNullifiersOutstanding,
+    #[msg("Presale has no whitelist")]
+    NoWhitelist,
+    #[msg("The claim window has closed")]
+    ClaimWindowClosed,
+    #[msg("The claim window is still open")]
+    ClaimWindowOpen,
 }
 
 #[error_code]
@@ -786,4 +4105,60 @@ pub enum DarkPoolError {
     InvalidMaker,
     #[msg("Unauthorized")]
     Unauthorized,
+    #[msg("Dark pool still has open orders")]
+    OpenOrdersRemaining,
+    #[msg("Fill amount must be greater than zero")]
+    InvalidFillAmount,
+    #[msg("Fill exceeds the order's unfilled size")]
+    FillExceedsOrder,
+    #[msg("Fill is worse than the order's limit price")]
+    PriceLimitExceeded,
+    #[msg("Order expiry must be zero or in the future")]
+    InvalidExpiry,
+    #[msg("Order has expired")]
+    OrderExpired,
+    #[msg("Order has not expired")]
+    OrderNotExpired,
+    #[msg("Base and quote mints must differ")]
+    InvalidMarket,
+    #[msg("Batch windows must be in the future and commit must end before reveal")]
+    InvalidBatchWindow,
+    #[msg("Batch is no longer accepting orders")]
+    BatchCommitClosed,
+    #[msg("Batch has reached its order limit")]
+    BatchFull,
+    #[msg("Batch is not in its reveal window")]
+    BatchNotInReveal,
+    #[msg("Batch order already revealed")]
+    AlreadyRevealed,
+    #[msg("Batch reveal window is still open")]
+    BatchRevealOpen,
+    #[msg("Batch already settled")]
+    BatchAlreadySettled,
+    #[msg("Batch not settled")]
+    BatchNotSettled,
+    #[msg("Every revealed order must be passed in reveal order")]
+    IncompleteBatch,
+    #[msg("Protocol fees must be collected before the pool is closed")]
+    FeesUncollected,
+    #[msg("Every batch must be closed before the pool is closed")]
+    OpenBatchesRemaining,
+    #[msg("Batch missed its settlement window and can only refund")]
+    BatchAbandoned,
+}
+
+#[error_code]
+pub enum ProtocolError {
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Fee exceeds the protocol maximum")]
+    FeeTooHigh,
+    #[msg("No fees to collect")]
+    NothingToCollect,
+    #[msg("This instruction is paused")]
+    Paused,
+    #[msg("The claim verifying key is already set")]
+    VerifyingKeyAlreadySet,
+    #[msg("Invalid verifying key")]
+    InvalidVerifyingKey,
 }
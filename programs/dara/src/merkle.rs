@@ -0,0 +1,121 @@
+//! Poseidon-hashed incremental Merkle tree backing pooled presale commitments.
+//!
+//! Leaves and nodes are big-endian BN254 scalar field elements hashed with the circom
+//! Poseidon parameters, so the same tree can later be proven over inside a SNARK.
+
+use anchor_lang::prelude::*;
+
+use crate::{CommitmentTree, PresaleError};
+
+pub const TREE_DEPTH: usize = 16;
+
+/// Poseidon over BN254 (x^5, circom parameters) of big-endian field elements.
+/// Fails if any input is not a canonical field element.
+pub fn poseidon(inputs: &[&[u8]]) -> Result<[u8; 32]> {
+    #[cfg(target_os = "solana")]
+    {
+        let mut hash = [0u8; 32];
+        // Parameters: 0 = Bn254X5, endianness: 0 = big-endian
+        let result = unsafe {
+            solana_define_syscall::definitions::sol_poseidon(
+                0,
+                0,
+                inputs as *const _ as *const u8,
+                inputs.len() as u64,
+                &mut hash as *mut _ as *mut u8,
+            )
+        };
+        require!(result == 0, PresaleError::InvalidFieldElement);
+        Ok(hash)
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    {
+        use light_poseidon::{Poseidon, PoseidonBytesHasher};
+
+        Poseidon::<ark_bn254::Fr>::new_circom(inputs.len())
+            .and_then(|mut hasher| hasher.hash_bytes_be(inputs))
+            .map_err(|_| PresaleError::InvalidFieldElement.into())
+    }
+}
+
+/// Splits a wallet into two 16-byte halves, each left-padded to a 32-byte field element.
+pub fn wallet_halves(wallet: &Pubkey) -> [[u8; 32]; 2] {
+    let wallet = wallet.as_ref();
+    let mut hi = [0u8; 32];
+    let mut lo = [0u8; 32];
+    hi[16..].copy_from_slice(&wallet[..16]);
+    lo[16..].copy_from_slice(&wallet[16..]);
+    [hi, lo]
+}
+
+/// Poseidon(nullifier, secret, wallet_hi, wallet_lo), the leaf a participant inserts at
+/// commit time. The claim wallet is split in halves so each input fits in the scalar field.
+pub fn leaf_hash(nullifier: &[u8; 32], secret: &[u8; 32], wallet: &Pubkey) -> Result<[u8; 32]> {
+    let [wallet_hi, wallet_lo] = wallet_halves(wallet);
+    poseidon(&[nullifier, secret, &wallet_hi, &wallet_lo])
+}
+
+/// Poseidon(nullifier), published on claim to prevent the same leaf being spent twice.
+pub fn nullifier_hash(nullifier: &[u8; 32]) -> Result<[u8; 32]> {
+    poseidon(&[nullifier])
+}
+
+/// Root of a tree whose leaves are all zero.
+pub fn empty_root() -> Result<[u8; 32]> {
+    let mut zero = [0u8; 32];
+    for _ in 0..TREE_DEPTH {
+        zero = poseidon(&[&zero, &zero])?;
+    }
+    Ok(zero)
+}
+
+/// Recomputes the root from a leaf, its index and its sibling path (leaf level first).
+pub fn root_from_path(leaf: [u8; 32], leaf_index: u32, path: &[[u8; 32]]) -> Result<[u8; 32]> {
+    require!(path.len() == TREE_DEPTH, PresaleError::InvalidMerkleProof);
+    require!(
+        (leaf_index as u64) < (1u64 << TREE_DEPTH),
+        PresaleError::InvalidMerkleProof
+    );
+
+    let mut current = leaf;
+    let mut index = leaf_index;
+    for sibling in path {
+        current = if index & 1 == 0 {
+            poseidon(&[&current, sibling])?
+        } else {
+            poseidon(&[sibling, &current])?
+        };
+        index >>= 1;
+    }
+    Ok(current)
+}
+
+impl CommitmentTree {
+    /// Appends `leaf` and updates the root, returning the leaf's index.
+    pub fn insert(&mut self, leaf: [u8; 32]) -> Result<u32> {
+        let leaf_index = self.next_index;
+        require!(
+            (leaf_index as u64) < (1u64 << TREE_DEPTH),
+            PresaleError::TreeFull
+        );
+
+        let mut current = leaf;
+        let mut zero = [0u8; 32];
+        let mut index = leaf_index;
+        for level in 0..TREE_DEPTH {
+            current = if index & 1 == 0 {
+                self.filled_subtrees[level] = current;
+                poseidon(&[&current, &zero])?
+            } else {
+                poseidon(&[&self.filled_subtrees[level], &current])?
+            };
+            zero = poseidon(&[&zero, &zero])?;
+            index >>= 1;
+        }
+
+        self.root = current;
+        self.next_index = leaf_index + 1;
+        Ok(leaf_index)
+    }
+}
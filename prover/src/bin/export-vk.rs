@@ -0,0 +1,18 @@
+//! Prints the development setup's verifying key as the hex-encoded argument of
+//! `set_claim_verifying_key`.
+//!
+//!     cargo run -p dara-prover --bin export-vk
+//!
+//! Anyone can rebuild this key's proving key, so it is only for local validators and tests.
+//! A deployment installs the key from a trusted setup ceremony instead.
+
+use anchor_lang::AnchorSerialize;
+use dara_prover::{dev_setup, verifying_key_data};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (_, vk) = dev_setup()?;
+    let data = verifying_key_data(&vk).try_to_vec()?;
+    let hex: String = data.iter().map(|b| format!("{b:02x}")).collect();
+    println!("{hex}");
+    Ok(())
+}
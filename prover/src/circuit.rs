@@ -0,0 +1,74 @@
+//! Claim circuit: the prover knows `(nullifier, secret)` for a leaf under `root` that binds
+//! `recipient`, and `nullifier_hash` is derived from that leaf's nullifier.
+
+use anon_presale::merkle::TREE_DEPTH;
+use ark_bn254::Fr;
+use ark_ff::Zero;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+use crate::poseidon::poseidon_var;
+
+#[derive(Clone)]
+pub struct ClaimCircuit {
+    // Public inputs, in the order `claim_tokens_zk` passes them to the verifier
+    pub root: Fr,
+    pub nullifier_hash: Fr,
+    pub recipient_hi: Fr,
+    pub recipient_lo: Fr,
+
+    // Private witness
+    pub nullifier: Fr,
+    pub secret: Fr,
+    /// Sibling hashes from the leaf level up.
+    pub path: Vec<Fr>,
+    /// Leaf index bits, least significant first. `true` means the node is a right child.
+    pub path_bits: Vec<bool>,
+}
+
+impl ClaimCircuit {
+    /// All-zero assignment with the right shape, used for key generation.
+    pub fn blank() -> Self {
+        Self {
+            root: Fr::zero(),
+            nullifier_hash: Fr::zero(),
+            recipient_hi: Fr::zero(),
+            recipient_lo: Fr::zero(),
+            nullifier: Fr::zero(),
+            secret: Fr::zero(),
+            path: vec![Fr::zero(); TREE_DEPTH],
+            path_bits: vec![false; TREE_DEPTH],
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for ClaimCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        if self.path.len() != TREE_DEPTH || self.path_bits.len() != TREE_DEPTH {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let root = FpVar::new_input(cs.clone(), || Ok(self.root))?;
+        let nullifier_hash = FpVar::new_input(cs.clone(), || Ok(self.nullifier_hash))?;
+        let recipient_hi = FpVar::new_input(cs.clone(), || Ok(self.recipient_hi))?;
+        let recipient_lo = FpVar::new_input(cs.clone(), || Ok(self.recipient_lo))?;
+
+        let nullifier = FpVar::new_witness(cs.clone(), || Ok(self.nullifier))?;
+        let secret = FpVar::new_witness(cs.clone(), || Ok(self.secret))?;
+
+        poseidon_var(std::slice::from_ref(&nullifier))?.enforce_equal(&nullifier_hash)?;
+
+        let mut current = poseidon_var(&[nullifier, secret, recipient_hi, recipient_lo])?;
+        for (sibling, is_right) in self.path.iter().zip(&self.path_bits) {
+            let sibling = FpVar::new_witness(cs.clone(), || Ok(*sibling))?;
+            let is_right = Boolean::new_witness(cs.clone(), || Ok(*is_right))?;
+
+            let left = FpVar::conditionally_select(&is_right, &sibling, &current)?;
+            let right = FpVar::conditionally_select(&is_right, &current, &sibling)?;
+            current = poseidon_var(&[left, right])?;
+        }
+
+        current.enforce_equal(&root)
+    }
+}
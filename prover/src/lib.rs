@@ -0,0 +1,175 @@
+//! Groth16 prover for `claim_tokens_zk`.
+//!
+//! A participant commits `Poseidon(nullifier, secret, wallet_hi, wallet_lo)` with
+//! `commit_to_pool`. To claim, they prove knowledge of a leaf under the tree root that binds
+//! their claim wallet, revealing only `Poseidon(nullifier)`.
+
+pub mod circuit;
+pub mod poseidon;
+pub mod tree;
+
+use anchor_lang::prelude::Pubkey;
+use anon_presale::groth16::{Groth16Proof, VerifyingKeyData};
+use anon_presale::merkle;
+use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{Groth16, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::SynthesisError;
+use ark_snark::SNARK;
+use ark_std::rand::rngs::StdRng;
+use ark_std::rand::{CryptoRng, RngCore, SeedableRng};
+
+pub use circuit::ClaimCircuit;
+pub use tree::MerkleTree;
+
+/// Seed of the deterministic development setup the tests install as the claim verifying
+/// key. Anyone can rebuild this proving key, so it must never secure real funds.
+pub const DEV_SETUP_SEED: u64 = 0xda7a;
+
+#[derive(Debug)]
+pub enum ProverError {
+    InvalidFieldElement,
+    LeafNotFound,
+    Synthesis(SynthesisError),
+}
+
+impl std::fmt::Display for ProverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProverError::InvalidFieldElement => write!(f, "value is not a valid field element"),
+            ProverError::LeafNotFound => write!(f, "leaf index is outside the tree"),
+            ProverError::Synthesis(err) => write!(f, "constraint synthesis failed: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ProverError {}
+
+impl From<SynthesisError> for ProverError {
+    fn from(err: SynthesisError) -> Self {
+        ProverError::Synthesis(err)
+    }
+}
+
+/// Everything needed to prove a pooled claim.
+#[derive(Clone)]
+pub struct ClaimWitness {
+    pub nullifier: [u8; 32],
+    pub secret: [u8; 32],
+    pub claim_wallet: Pubkey,
+    pub leaf_index: u32,
+    pub path: Vec<[u8; 32]>,
+    pub root: [u8; 32],
+}
+
+/// A proof plus the public values `claim_tokens_zk` needs alongside it.
+pub struct ClaimProof {
+    pub proof: Groth16Proof,
+    pub nullifier_hash: [u8; 32],
+    pub public_inputs: [[u8; 32]; 4],
+}
+
+/// Generates a fresh circuit-specific key pair.
+pub fn setup<R: RngCore + CryptoRng>(
+    rng: &mut R,
+) -> Result<(ProvingKey<Bn254>, VerifyingKey<Bn254>), ProverError> {
+    Ok(Groth16::<Bn254>::circuit_specific_setup(
+        ClaimCircuit::blank(),
+        rng,
+    )?)
+}
+
+/// Rebuilds the development key pair from `DEV_SETUP_SEED`.
+pub fn dev_setup() -> Result<(ProvingKey<Bn254>, VerifyingKey<Bn254>), ProverError> {
+    setup(&mut StdRng::seed_from_u64(DEV_SETUP_SEED))
+}
+
+pub fn prove<R: RngCore + CryptoRng>(
+    pk: &ProvingKey<Bn254>,
+    witness: &ClaimWitness,
+    rng: &mut R,
+) -> Result<ClaimProof, ProverError> {
+    let nullifier_hash =
+        merkle::nullifier_hash(&witness.nullifier).map_err(|_| ProverError::InvalidFieldElement)?;
+    let [wallet_hi, wallet_lo] = merkle::wallet_halves(&witness.claim_wallet);
+    let public_inputs = [witness.root, nullifier_hash, wallet_hi, wallet_lo];
+
+    let circuit = ClaimCircuit {
+        root: fr_from_be(&witness.root)?,
+        nullifier_hash: fr_from_be(&nullifier_hash)?,
+        recipient_hi: fr_from_be(&wallet_hi)?,
+        recipient_lo: fr_from_be(&wallet_lo)?,
+        nullifier: fr_from_be(&witness.nullifier)?,
+        secret: fr_from_be(&witness.secret)?,
+        path: witness
+            .path
+            .iter()
+            .map(fr_from_be)
+            .collect::<Result<_, _>>()?,
+        path_bits: (0..merkle::TREE_DEPTH)
+            .map(|level| (witness.leaf_index >> level) & 1 == 1)
+            .collect(),
+    };
+
+    let proof = Groth16::<Bn254>::prove(pk, circuit, rng)?;
+    Ok(ClaimProof {
+        proof: Groth16Proof {
+            a: g1_to_be(&proof.a),
+            b: g2_to_be(&proof.b),
+            c: g1_to_be(&proof.c),
+        },
+        nullifier_hash,
+        public_inputs,
+    })
+}
+
+/// The argument `set_claim_verifying_key` takes to install `vk`.
+pub fn verifying_key_data(vk: &VerifyingKey<Bn254>) -> VerifyingKeyData {
+    let mut ic = [[0u8; 64]; 5];
+    assert_eq!(vk.gamma_abc_g1.len(), ic.len(), "not a claim circuit key");
+    for (out, point) in ic.iter_mut().zip(&vk.gamma_abc_g1) {
+        *out = g1_to_be(point);
+    }
+    VerifyingKeyData {
+        alpha_g1: g1_to_be(&vk.alpha_g1),
+        beta_g2: g2_to_be(&vk.beta_g2),
+        gamma_g2: g2_to_be(&vk.gamma_g2),
+        delta_g2: g2_to_be(&vk.delta_g2),
+        ic,
+    }
+}
+
+/// Parses a canonical big-endian scalar, rejecting values at or above the field modulus.
+pub fn fr_from_be(bytes: &[u8; 32]) -> Result<Fr, ProverError> {
+    let value = Fr::from_be_bytes_mod_order(bytes);
+    if fr_to_be(&value) != *bytes {
+        return Err(ProverError::InvalidFieldElement);
+    }
+    Ok(value)
+}
+
+pub fn fr_to_be(value: &Fr) -> [u8; 32] {
+    to_32(value.into_bigint().to_bytes_be())
+}
+
+/// EIP-197 encoding of a G1 point: `x || y`, big-endian.
+pub fn g1_to_be(point: &G1Affine) -> [u8; 64] {
+    let mut out = [0u8; 64];
+    out[..32].copy_from_slice(&to_32(point.x.into_bigint().to_bytes_be()));
+    out[32..].copy_from_slice(&to_32(point.y.into_bigint().to_bytes_be()));
+    out
+}
+
+/// EIP-197 encoding of a G2 point: `x.c1 || x.c0 || y.c1 || y.c0`, big-endian.
+pub fn g2_to_be(point: &G2Affine) -> [u8; 128] {
+    let mut out = [0u8; 128];
+    out[..32].copy_from_slice(&to_32(point.x.c1.into_bigint().to_bytes_be()));
+    out[32..64].copy_from_slice(&to_32(point.x.c0.into_bigint().to_bytes_be()));
+    out[64..96].copy_from_slice(&to_32(point.y.c1.into_bigint().to_bytes_be()));
+    out[96..].copy_from_slice(&to_32(point.y.c0.into_bigint().to_bytes_be()));
+    out
+}
+
+fn to_32(bytes: Vec<u8>) -> [u8; 32] {
+    bytes.try_into().expect("BN254 field elements are 32 bytes")
+}
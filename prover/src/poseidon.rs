@@ -0,0 +1,56 @@
+//! R1CS gadget for the circom-parameter Poseidon hash used by the on-chain commitment tree.
+
+use ark_bn254::Fr;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_r1cs_std::prelude::*;
+use ark_relations::r1cs::SynthesisError;
+use light_poseidon::parameters::bn254_x5::get_poseidon_parameters;
+
+/// Poseidon over `inputs`, matching `light_poseidon::Poseidon::<Fr>::new_circom(inputs.len())`
+/// and therefore the `sol_poseidon` syscall.
+pub fn poseidon_var(inputs: &[FpVar<Fr>]) -> Result<FpVar<Fr>, SynthesisError> {
+    let params = get_poseidon_parameters::<Fr>((inputs.len() + 1) as u8)
+        .map_err(|_| SynthesisError::Unsatisfiable)?;
+    assert_eq!(params.alpha, 5, "gadget only implements the x^5 S-box");
+
+    // Domain tag 0 followed by the inputs
+    let mut state: Vec<FpVar<Fr>> = std::iter::once(FpVar::zero())
+        .chain(inputs.iter().cloned())
+        .collect();
+
+    let half_full_rounds = params.full_rounds / 2;
+    let all_rounds = params.full_rounds + params.partial_rounds;
+    for round in 0..all_rounds {
+        for (i, element) in state.iter_mut().enumerate() {
+            *element = &*element + params.ark[round * params.width + i];
+        }
+
+        let is_full_round =
+            round < half_full_rounds || round >= half_full_rounds + params.partial_rounds;
+        if is_full_round {
+            for element in state.iter_mut() {
+                *element = sbox(element)?;
+            }
+        } else {
+            state[0] = sbox(&state[0])?;
+        }
+
+        state = params
+            .mds
+            .iter()
+            .map(|row| {
+                state
+                    .iter()
+                    .zip(row)
+                    .fold(FpVar::zero(), |acc, (element, m)| acc + element * *m)
+            })
+            .collect();
+    }
+
+    Ok(state.swap_remove(0))
+}
+
+fn sbox(x: &FpVar<Fr>) -> Result<FpVar<Fr>, SynthesisError> {
+    let x4 = x.square()?.square()?;
+    Ok(x4 * x)
+}
@@ -0,0 +1,68 @@
+//! Off-chain mirror of the on-chain `CommitmentTree`, used to build claim paths.
+
+use anon_presale::merkle::{poseidon, TREE_DEPTH};
+
+use crate::ProverError;
+
+#[derive(Clone, Default)]
+pub struct MerkleTree {
+    leaves: Vec<[u8; 32]>,
+}
+
+impl MerkleTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuilds a tree from leaves in insertion order, e.g. replayed from commit logs.
+    pub fn from_leaves(leaves: Vec<[u8; 32]>) -> Self {
+        Self { leaves }
+    }
+
+    pub fn insert(&mut self, leaf: [u8; 32]) -> u32 {
+        self.leaves.push(leaf);
+        (self.leaves.len() - 1) as u32
+    }
+
+    pub fn leaves(&self) -> &[[u8; 32]] {
+        &self.leaves
+    }
+
+    pub fn root(&self) -> Result<[u8; 32], ProverError> {
+        let (root, _) = self.walk(0)?;
+        Ok(root)
+    }
+
+    /// Sibling path for `leaf_index`, leaf level first.
+    pub fn path(&self, leaf_index: u32) -> Result<Vec<[u8; 32]>, ProverError> {
+        if leaf_index as usize >= self.leaves.len() {
+            return Err(ProverError::LeafNotFound);
+        }
+        let (_, path) = self.walk(leaf_index as usize)?;
+        Ok(path)
+    }
+
+    fn walk(&self, leaf_index: usize) -> Result<([u8; 32], Vec<[u8; 32]>), ProverError> {
+        let mut level = self.leaves.clone();
+        let mut zero = [0u8; 32];
+        let mut index = leaf_index;
+        let mut path = Vec::with_capacity(TREE_DEPTH);
+
+        for _ in 0..TREE_DEPTH {
+            if level.len() % 2 == 1 {
+                level.push(zero);
+            }
+            path.push(level.get(index ^ 1).copied().unwrap_or(zero));
+
+            level = level
+                .chunks(2)
+                .map(|pair| poseidon(&[&pair[0], &pair[1]]))
+                .collect::<Result<_, _>>()
+                .map_err(|_| ProverError::InvalidFieldElement)?;
+            zero = poseidon(&[&zero, &zero]).map_err(|_| ProverError::InvalidFieldElement)?;
+            index >>= 1;
+        }
+
+        Ok((level.first().copied().unwrap_or(zero), path))
+    }
+}
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::Pubkey;
+use anon_presale::merkle::{self, TREE_DEPTH};
+use anon_presale::{groth16, CommitmentTree};
+use ark_std::rand::rngs::StdRng;
+use ark_std::rand::{RngCore, SeedableRng};
+use dara_prover::{dev_setup, prove, verifying_key_data, ClaimWitness, MerkleTree};
+
+/// Random 31-byte value, always below the scalar field modulus.
+fn random_scalar(rng: &mut StdRng) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    rng.fill_bytes(&mut bytes[1..]);
+    bytes
+}
+
+fn empty_on_chain_tree() -> CommitmentTree {
+    CommitmentTree {
+        presale: Pubkey::default(),
+        next_index: 0,
+        root: merkle::empty_root().unwrap(),
+        filled_subtrees: [[0u8; 32]; TREE_DEPTH],
+        bump: 0,
+    }
+}
+
+#[test]
+fn off_chain_tree_matches_on_chain_insertion() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut on_chain = empty_on_chain_tree();
+    let mut off_chain = MerkleTree::new();
+    assert_eq!(off_chain.root().unwrap(), on_chain.root);
+
+    for _ in 0..5 {
+        let leaf = random_scalar(&mut rng);
+        assert_eq!(on_chain.insert(leaf).unwrap(), off_chain.insert(leaf));
+        assert_eq!(off_chain.root().unwrap(), on_chain.root);
+    }
+
+    for index in 0..5 {
+        let path = off_chain.path(index).unwrap();
+        let leaf = off_chain.leaves()[index as usize];
+        assert_eq!(merkle::root_from_path(leaf, index, &path).unwrap(), on_chain.root);
+    }
+}
+
+#[test]
+fn proof_verifies_against_exported_key_and_binds_wallet() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let (pk, vk) = dev_setup().unwrap();
+
+    let claim_wallet = Pubkey::new_unique();
+    let nullifier = random_scalar(&mut rng);
+    let secret = random_scalar(&mut rng);
+
+    let mut tree = MerkleTree::new();
+    tree.insert(random_scalar(&mut rng));
+    let leaf_index = tree.insert(merkle::leaf_hash(&nullifier, &secret, &claim_wallet).unwrap());
+    tree.insert(random_scalar(&mut rng));
+
+    let witness = ClaimWitness {
+        nullifier,
+        secret,
+        claim_wallet,
+        leaf_index,
+        path: tree.path(leaf_index).unwrap(),
+        root: tree.root().unwrap(),
+    };
+    let claim = prove(&pk, &witness, &mut rng).unwrap();
+    assert_eq!(claim.nullifier_hash, merkle::nullifier_hash(&nullifier).unwrap());
+
+    let vk_data = verifying_key_data(&vk);
+    assert!(vk_data.is_valid());
+    let vk = &vk_data.verifying_key();
+    groth16::verify(vk, &claim.proof, &claim.public_inputs).unwrap();
+
+    // Redirecting the claim to another wallet must not verify
+    let [other_hi, other_lo] = merkle::wallet_halves(&Pubkey::new_unique());
+    let mut redirected = claim.public_inputs;
+    redirected[2] = other_hi;
+    redirected[3] = other_lo;
+    assert!(groth16::verify(vk, &claim.proof, &redirected).is_err());
+
+    // Nor may the proof be replayed under a different nullifier
+    let mut renullified = claim.public_inputs;
+    renullified[1] = merkle::nullifier_hash(&random_scalar(&mut rng)).unwrap();
+    assert!(groth16::verify(vk, &claim.proof, &renullified).is_err());
+}